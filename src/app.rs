@@ -1,19 +1,29 @@
 use egui_wgpu::wgpu::SurfaceError;
 use egui_wgpu::{wgpu, ScreenDescriptor};
-use gui_renderer::GUIRenderer;
+use gui_renderer::{GUIRenderer, SceneRequest};
 use std::sync::Arc;
-use std::time::Duration;
 use winit::application::ApplicationHandler;
 use winit::dpi::{LogicalSize};
-use winit::event::WindowEvent;
+use winit::event::{DeviceEvent, DeviceId, WindowEvent};
 use winit::event_loop::ActiveEventLoop;
-use winit::window::{Window, WindowId};
+use winit::window::{CursorGrabMode, Window, WindowId};
 
 pub mod main_renderer;
 pub mod gui_renderer;
-
+pub mod app_builder;
+pub mod render_graph;
+pub mod camera_controller;
+pub mod input;
+pub mod scene_file;
+
+use app_builder::{AppBuilder, AppConfig, FrameContext, GuiCallback, UpdateHook};
+use camera_controller::CameraControls;
+use input::{Input, InputBindings};
 use main_renderer::MainRenderer;
+use render_graph::RenderGraph;
+use scene_file::SceneFile;
 
+use crate::error::{CandleError, CandleResult};
 use crate::utilities::FPSCounter;
 
 
@@ -22,12 +32,29 @@ pub struct App {
     main_renderer: Option<MainRenderer>,
     gui_renderer: Option<GUIRenderer>,
     fps_counter: FPSCounter,
+    camera_controls: CameraControls,
+    input: Input,
     window: Option<Arc<Window>>,
+    error: Option<CandleError>,
+    /// Why the last load or save from the GUI failed, shown until the next one.
+    scene_error: Option<String>,
+
+    config: AppConfig,
+    gui_callbacks: Vec<GuiCallback>,
+    update_hooks: Vec<UpdateHook>,
 }
 
 impl App {
 
     pub fn new() -> Self {
+        Self::builder().build()
+    }
+
+    pub fn builder() -> AppBuilder {
+        AppBuilder::new()
+    }
+
+    fn from_parts(config: AppConfig, gui_callbacks: Vec<GuiCallback>, update_hooks: Vec<UpdateHook>) -> Self {
 
         let wgpu_instance = egui_wgpu::wgpu::Instance::new(wgpu::InstanceDescriptor::default());
 
@@ -36,35 +63,72 @@ impl App {
             main_renderer: None,
             gui_renderer: None,
             fps_counter: FPSCounter::new(),
+            camera_controls: CameraControls::new(),
+            input: Input::default(),
             window: None,
+            error: None,
+            scene_error: None,
+            config,
+            gui_callbacks,
+            update_hooks,
         }
     }
 
-    async fn set_window(&mut self, window: Window) {
-        
+    /// Returns the error that stopped the app, if any, so the caller can report it after the event loop exits.
+    pub fn take_error(&mut self) -> Option<CandleError> {
+        self.error.take()
+    }
+
+    async fn set_window(&mut self, window: Window) -> CandleResult<()> {
+
+        if let Some(path) = &self.config.input_bindings {
+            if path.exists() {
+                self.input.bindings = InputBindings::load(path)?;
+            } else {
+                log::info!("No input bindings at {}, using the defaults", path.display());
+            }
+        }
+
         let window = Arc::new(window);
-        let initial_width = 1280;
-        let initial_height = 720;
+        let initial_width = self.config.width;
+        let initial_height = self.config.height;
 
         let _ = window.request_inner_size(LogicalSize::new(initial_width, initial_height));
 
         let surface = self
             .wgpu_instance
             .create_surface(window.clone())
-            .expect("Failed to create surface!");
+            .map_err(CandleError::SurfaceCreation)?;
 
-        let main_renderer = MainRenderer::new(
+        let mut main_renderer = MainRenderer::new(
             &self.wgpu_instance,
             surface,
             initial_width,
             initial_height,
+            self.config.present_mode,
+            self.config.asset_server(),
         )
-        .await;
+        .await?;
+
+        main_renderer.set_depth_format(self.config.depth_format);
+
+        if self.config.shader_hot_reload {
+            match &self.config.shader_path {
+                Some(path) if path.is_file() => main_renderer.enable_shader_hot_reload(path),
+                Some(path) => log::warn!("Not hot reloading shaders, {} doesn't exist", path.display()),
+                None => log::warn!("Not hot reloading shaders, no shader path is set"),
+            }
+        }
+
+        if let Some(path) = &self.config.scene {
+            SceneFile::load(path)?.apply(&mut main_renderer, &mut self.camera_controls)?;
+            log::info!("Opened scene {}", path.display());
+        }
 
         let gui_renderer = GUIRenderer::new(
             &main_renderer.device,
             main_renderer.surface_config.format,
-            None,
+            Some(main_renderer.depth_texture.format),
             1,
             &window
         );
@@ -72,6 +136,16 @@ impl App {
         self.window.get_or_insert(window);
         self.main_renderer.get_or_insert(main_renderer);
         self.gui_renderer.get_or_insert(gui_renderer);
+
+        Ok(())
+    }
+
+    fn fail(&mut self, event_loop: &ActiveEventLoop, error: CandleError) {
+
+        log::error!("{error}");
+
+        self.error = Some(error);
+        event_loop.exit();
     }
 
 
@@ -83,41 +157,158 @@ impl App {
 
     }
 
-    fn redraw(&mut self) {
+    /// Hides the cursor and keeps it in place while the camera controls use the mouse to look around.
+    fn update_cursor_capture(&self, captured: bool) {
+
+        let window = self.window.as_ref().unwrap();
+
+        if captured {
+            // Not every platform can lock the cursor in place, confining it to the window is the next best thing
+            let result = window
+                .set_cursor_grab(CursorGrabMode::Locked)
+                .or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined));
+            if let Err(error) = result {
+                log::warn!("Failed to capture the cursor: {error}");
+            }
+        } else {
+            let _ = window.set_cursor_grab(CursorGrabMode::None);
+        }
+
+        window.set_cursor_visible(!captured);
+    }
+
+    /// Recreates both renderers on a fresh device after the old one was lost.
+    fn recover_from_device_loss(&mut self) -> CandleResult<()> {
+
+        log::warn!("Rebuilding renderers on a new device");
+
+        let main_renderer = self.main_renderer.take().unwrap();
+        let main_renderer = pollster::block_on(main_renderer.rebuild(&self.wgpu_instance))?;
+
+        self.gui_renderer.as_mut().unwrap().rebuild(
+            &main_renderer.device,
+            main_renderer.surface_config.format,
+            Some(main_renderer.depth_texture.format),
+            self.window.as_ref().unwrap(),
+        );
+
+        self.main_renderer = Some(main_renderer);
+
+        Ok(())
+    }
+
+    fn handle_scene_request(
+        request: SceneRequest,
+        main_renderer: &mut MainRenderer,
+        camera_controls: &mut CameraControls,
+    ) -> CandleResult<()> {
+
+        match request {
+            SceneRequest::Load(path) => {
+                SceneFile::load(&path)?.apply(main_renderer, camera_controls)?;
+                log::info!("Loaded scene {}", path.display());
+            }
+            SceneRequest::Save(path) => {
+                SceneFile::capture(main_renderer, camera_controls).save(&path)?;
+                log::info!("Saved scene {}", path.display());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn reconfigure_surface(&mut self) {
+
+        let size = self.window.as_ref().unwrap().inner_size();
+        self.resize(size.width, size.height);
+    }
+
+    fn redraw(&mut self) -> CandleResult<()> {
+
+        let result = self.draw_frame();
+
+        // Presses and mouse motion only count for one frame, also when it was skipped or failed,
+        // otherwise they'd be reported again on the next frame that's drawn
+        self.input.end_frame();
+
+        result
+    }
+
+    fn draw_frame(&mut self) -> CandleResult<()> {
         
         // Attempt to handle minimizing window
-        if let Some(window) = self.window.as_ref() {
-            if let Some(min) = window.is_minimized() {
-                if min {
-                    return;
-                }
-            }
+        if let Some(window) = self.window.as_ref()
+            && let Some(true) = window.is_minimized()
+        {
+            return Ok(());
+        }
+
+        if self.main_renderer.as_ref().unwrap().is_device_lost() {
+            return self.recover_from_device_loss();
         }
 
+        let surface_texture = self
+            .main_renderer
+            .as_ref()
+            .unwrap()
+            .surface
+            .as_ref()
+            .expect("Windowed redraw requires a surface!")
+            .get_current_texture();
+
+        let surface_texture = match surface_texture {
+            Ok(surface_texture) => surface_texture,
+            Err(SurfaceError::Lost | SurfaceError::Outdated) => {
+                // Happens on resizing, minimization and display changes, the next frame will be fine
+                log::info!("wgpu surface is lost or outdated, reconfiguring");
+                self.reconfigure_surface();
+                return Ok(());
+            }
+            Err(SurfaceError::Timeout) => {
+                log::warn!("Timed out acquiring the next swap chain texture, skipping frame");
+                return Ok(());
+            }
+            Err(SurfaceError::OutOfMemory) => {
+                log::error!("Out of memory acquiring the next swap chain texture");
+                return self.recover_from_device_loss();
+            }
+        };
+
         let main_renderer = self.main_renderer.as_mut().unwrap();
         let gui_renderer = self.gui_renderer.as_mut().unwrap();
+        let window = self.window.as_ref().unwrap();
 
-        let screen_descriptor = ScreenDescriptor {
-            size_in_pixels: [main_renderer.surface_config.width, main_renderer.surface_config.height],
-            pixels_per_point: self.window.as_ref().unwrap().scale_factor() as f32,
-        };
+        main_renderer.reload_changed_shaders();
+        main_renderer.update_assets();
 
-        let surface_texture = main_renderer.surface.get_current_texture();
+        let scene_bounds = main_renderer.bounds();
+        self.camera_controls.update(&mut main_renderer.camera, &self.input, scene_bounds, self.fps_counter.delta_time);
 
-        match surface_texture {
-            Err(SurfaceError::Outdated) => {
-                // Ignoring outdated to allow resizing and minimization
-                println!("wgpu surface is outdated!");
-                return;
-            }
-            Err(_) => {
-                surface_texture.expect("Failed to acquire next swap chain texture!");
-                return;
+        {
+            let mut frame_context = FrameContext {
+                renderer: main_renderer,
+                window,
+                input: &self.input,
+                delta_time: self.fps_counter.delta_time,
+            };
+
+            for hook in self.update_hooks.iter_mut() {
+                hook(&mut frame_context);
             }
-            Ok(_) => {}
-        };
+        }
+
+        // Hooks can switch the depth format, the GUI pipeline has to match the depth buffer it's drawn with
+        let depth_format = main_renderer.depth_texture.format;
+        if gui_renderer.output_depth_format() != Some(depth_format) {
+            gui_renderer.rebuild(&main_renderer.device, main_renderer.surface_config.format, Some(depth_format), window);
+        }
+
+        main_renderer.prepare_scene();
 
-        let surface_texture = surface_texture.unwrap();
+        let screen_descriptor = ScreenDescriptor {
+            size_in_pixels: [main_renderer.surface_config.width, main_renderer.surface_config.height],
+            pixels_per_point: window.scale_factor() as f32,
+        };
 
         let surface_view = surface_texture
             .texture
@@ -127,33 +318,65 @@ impl App {
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Encoder") });
 
-        let window = self.window.as_ref().unwrap();
+        gui_renderer.begin_gui(window);
 
-        // Main pass
-        {
-            main_renderer.render(&mut encoder, &surface_view);
-        }
-
-        // GUI pass
-        {
-            gui_renderer.begin_gui(window);
+        let scene_request = gui_renderer.render(
+            self.fps_counter.fps,
+            main_renderer.shader_error.as_deref(),
+            self.scene_error.as_deref(),
+            &mut self.camera_controls,
+            &main_renderer.camera,
+        );
 
-            gui_renderer.render(self.fps_counter.fps);
+        // Takes effect from the next frame, this one was already prepared
+        if let Some(request) = scene_request {
+            self.scene_error = Self::handle_scene_request(request, main_renderer, &mut self.camera_controls)
+                .err()
+                .map(|error| error.to_string());
+        }
 
-            gui_renderer.end_gui(
-                &main_renderer.device,
-                &main_renderer.queue,
-                &mut encoder,
-                window,
-                &surface_view,
-                screen_descriptor,
-            );
+        for callback in self.gui_callbacks.iter_mut() {
+            callback(gui_renderer.get_context());
         }
 
+        // The pool is taken out for the duration of the frame, the passes borrow the rest of the renderer
+        let mut transient_pool = std::mem::take(&mut main_renderer.transient_pool);
+        let graph_result = {
+            let main_renderer = &*main_renderer;
+            let output_size = (main_renderer.surface_config.width, main_renderer.surface_config.height);
+
+            let mut graph = RenderGraph::new();
+            main_renderer.add_passes(&mut graph);
+
+            graph.set_composite_pass("GUI", |encoder, resources| {
+                gui_renderer.end_gui(
+                    &main_renderer.device,
+                    &main_renderer.queue,
+                    encoder,
+                    window,
+                    resources.view(render_graph::OUTPUT),
+                    Some(&main_renderer.depth_texture),
+                    screen_descriptor,
+                );
+            });
+
+            graph.execute(&main_renderer.device, &mut encoder, &surface_view, output_size, &mut transient_pool)
+        };
+        main_renderer.transient_pool = transient_pool;
+        graph_result?;
+
         main_renderer.queue.submit(Some(encoder.finish()));
         surface_texture.present();
 
         self.fps_counter.update();
+
+        Ok(())
+    }
+}
+
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -165,17 +388,35 @@ impl ApplicationHandler for App {
         let window = event_loop
             .create_window(
                 Window::default_attributes()
-                .with_title("Candle")
+                .with_title(self.config.title.as_str())
             )
-            .unwrap();
+            .map_err(CandleError::WindowCreation);
+
+        let result = match window {
+            Ok(window) => pollster::block_on(self.set_window(window)),
+            Err(error) => Err(error),
+        };
 
-        pollster::block_on(self.set_window(window));
+        if let Err(error) = result {
+            self.fail(event_loop, error);
+        }
     }
 
     fn window_event(&mut self, event_loop: &ActiveEventLoop, _: WindowId, event: WindowEvent) {
 
+        // Initialization or device recovery failed, the event loop is already shutting down
+        if self.main_renderer.is_none() || self.gui_renderer.is_none() {
+            return;
+        }
+
         self.gui_renderer.as_mut().unwrap().handle_input(self.window.as_ref().unwrap(), &event);
 
+        let was_capturing = self.camera_controls.is_capturing_cursor(&self.input);
+        self.input.handle_window_event(&event, self.gui_renderer.as_ref().unwrap().get_context());
+        if self.camera_controls.is_capturing_cursor(&self.input) != was_capturing {
+            self.update_cursor_capture(!was_capturing);
+        }
+
         match event {
             WindowEvent::CloseRequested => {
                 println!("The close button was pressed; stopping");
@@ -183,7 +424,10 @@ impl ApplicationHandler for App {
             }
 
             WindowEvent::RedrawRequested => {
-                self.redraw();
+                if let Err(error) = self.redraw() {
+                    self.fail(event_loop, error);
+                    return;
+                }
                 self.window.as_ref().unwrap().request_redraw();
             }
 
@@ -193,4 +437,8 @@ impl ApplicationHandler for App {
             _ => (),
         }
     }
+
+    fn device_event(&mut self, _: &ActiveEventLoop, _: DeviceId, event: DeviceEvent) {
+        self.input.handle_device_event(&event);
+    }
 }
@@ -0,0 +1,187 @@
+use std::path::PathBuf;
+
+use egui_wgpu::wgpu;
+use winit::event_loop::{ControlFlow, EventLoop};
+use winit::window::Window;
+
+use super::input::Input;
+use super::main_renderer::depth_texture::DepthTexture;
+use super::main_renderer::asset_server::AssetServer;
+use super::main_renderer::MainRenderer;
+use super::App;
+use crate::error::{CandleError, CandleResult};
+
+/// Everything a per-frame update hook gets to work with.
+pub struct FrameContext<'a> {
+    pub renderer: &'a mut MainRenderer,
+    pub window: &'a Window,
+    /// Input since the previous frame, see `Input::action_pressed` and friends.
+    pub input: &'a Input,
+    /// Seconds since the previous frame.
+    pub delta_time: f32,
+}
+
+/// The main shader in the source tree, which only exists where the crate was built.
+#[cfg(debug_assertions)]
+const SOURCE_SHADER: Option<&str> = Some(concat!(env!("CARGO_MANIFEST_DIR"), "/src/shaders/shader.wgsl"));
+#[cfg(not(debug_assertions))]
+const SOURCE_SHADER: Option<&str> = None;
+
+pub type GuiCallback = Box<dyn FnMut(&egui::Context)>;
+pub type UpdateHook = Box<dyn FnMut(&mut FrameContext)>;
+
+pub struct AppConfig {
+    pub title: String,
+    pub width: u32,
+    pub height: u32,
+    /// Falls back to `Fifo` if the surface doesn't support it.
+    pub present_mode: wgpu::PresentMode,
+    /// Of the depth buffer. Pick one with stencil, e.g. `Depth24PlusStencil8`, when a pass needs it.
+    pub depth_format: wgpu::TextureFormat,
+    /// Watches `shader_path` and reloads shaders on change. On by default in debug builds.
+    pub shader_hot_reload: bool,
+    /// WGSL file watched for hot reloading, `src/shaders/shader.wgsl` in the source tree by default in
+    /// debug builds and nothing in release builds.
+    pub shader_path: Option<PathBuf>,
+    /// Where textures and other assets are loaded from. See `AssetServer::default_root` when unset.
+    pub asset_root: Option<PathBuf>,
+    /// JSON file with `InputBindings` to use instead of the defaults, if it exists.
+    pub input_bindings: Option<PathBuf>,
+    /// Scene file opened at startup instead of the default scene.
+    pub scene: Option<PathBuf>,
+}
+
+impl AppConfig {
+
+    pub fn asset_server(&self) -> AssetServer {
+
+        match &self.asset_root {
+            Some(root) => AssetServer::new(root),
+            None => AssetServer::default(),
+        }
+    }
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            title: "Candle".to_owned(),
+            width: 1280,
+            height: 720,
+            present_mode: wgpu::PresentMode::Immediate,
+            depth_format: DepthTexture::DEFAULT_FORMAT,
+            shader_hot_reload: cfg!(debug_assertions),
+            shader_path: SOURCE_SHADER.map(PathBuf::from),
+            asset_root: None,
+            input_bindings: None,
+            scene: None,
+        }
+    }
+}
+
+/// Configures and launches an `App`.
+///
+/// ```no_run
+/// candle::App::builder()
+///     .with_title("My Tool")
+///     .with_gui(|ctx| {
+///         candle::egui::Window::new("Hello").show(ctx, |ui| ui.label("Hi!"));
+///     })
+///     .run()
+///     .unwrap();
+/// ```
+#[derive(Default)]
+pub struct AppBuilder {
+    config: AppConfig,
+    gui_callbacks: Vec<GuiCallback>,
+    update_hooks: Vec<UpdateHook>,
+}
+
+impl AppBuilder {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.config.title = title.into();
+        self
+    }
+
+    /// Initial window size in logical pixels.
+    pub fn with_size(mut self, width: u32, height: u32) -> Self {
+        self.config.width = width;
+        self.config.height = height;
+        self
+    }
+
+    pub fn with_present_mode(mut self, present_mode: wgpu::PresentMode) -> Self {
+        self.config.present_mode = present_mode;
+        self
+    }
+
+    pub fn with_depth_format(mut self, format: wgpu::TextureFormat) -> Self {
+        self.config.depth_format = format;
+        self
+    }
+
+    pub fn with_shader_hot_reload(mut self, enabled: bool) -> Self {
+        self.config.shader_hot_reload = enabled;
+        self
+    }
+
+    /// Hot reloads the shader from `path`, e.g. a copy shipped with a release build.
+    pub fn with_shader_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.shader_hot_reload = true;
+        self.config.shader_path = Some(path.into());
+        self
+    }
+
+    pub fn with_asset_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.config.asset_root = Some(root.into());
+        self
+    }
+
+    /// Loads key and mouse bindings from a JSON file written by `InputBindings::save`, when it exists.
+    pub fn with_input_bindings(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.input_bindings = Some(path.into());
+        self
+    }
+
+    /// Opens a scene file written by `SceneFile::save` at startup. Failing to load it stops the app.
+    pub fn with_scene(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.scene = Some(path.into());
+        self
+    }
+
+    /// Registers a callback that can add its own egui windows and panels every frame.
+    pub fn with_gui(mut self, callback: impl FnMut(&egui::Context) + 'static) -> Self {
+        self.gui_callbacks.push(Box::new(callback));
+        self
+    }
+
+    /// Registers a hook that runs every frame before anything is rendered.
+    pub fn with_update(mut self, hook: impl FnMut(&mut FrameContext) + 'static) -> Self {
+        self.update_hooks.push(Box::new(hook));
+        self
+    }
+
+    pub fn build(self) -> App {
+        App::from_parts(self.config, self.gui_callbacks, self.update_hooks)
+    }
+
+    /// Builds the app, opens its window and blocks until it's closed.
+    pub fn run(self) -> CandleResult<()> {
+
+        let event_loop = EventLoop::new().map_err(CandleError::EventLoop)?;
+        event_loop.set_control_flow(ControlFlow::Poll);
+
+        let mut app = self.build();
+        event_loop.run_app(&mut app).map_err(CandleError::EventLoop)?;
+
+        match app.take_error() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,458 @@
+use glam::{EulerRot, Quat, Vec2, Vec3};
+use serde::{Deserialize, Serialize};
+
+use super::input::{actions, axes, Input};
+use super::main_renderer::camera::{Camera, Projection};
+use super::main_renderer::mesh::Aabb;
+
+/// Just short of straight up or down, where yaw stops making sense.
+const MAX_PITCH: f32 = 89.0 * std::f32::consts::PI / 180.0;
+
+/// How the camera reacts to input.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CameraMode {
+    #[default]
+    Fly,
+    Orbit,
+}
+
+/// Looking straight along one of the world axes, like the numpad views in Blender.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AxisView {
+    /// Looking down -Z.
+    Front,
+    Back,
+    /// Looking down -X.
+    Right,
+    Left,
+    /// Looking down -Y, with -Z up on screen.
+    Top,
+    Bottom,
+}
+
+impl AxisView {
+
+    pub const ALL: [Self; 6] = [Self::Front, Self::Back, Self::Right, Self::Left, Self::Top, Self::Bottom];
+
+    pub fn name(self) -> &'static str {
+
+        match self {
+            Self::Front => "Front",
+            Self::Back => "Back",
+            Self::Right => "Right",
+            Self::Left => "Left",
+            Self::Top => "Top",
+            Self::Bottom => "Bottom",
+        }
+    }
+
+    pub fn orientation(self) -> Quat {
+
+        use std::f32::consts::{FRAC_PI_2, PI};
+
+        match self {
+            Self::Front => Quat::IDENTITY,
+            Self::Back => Quat::from_rotation_y(PI),
+            Self::Right => Quat::from_rotation_y(FRAC_PI_2),
+            Self::Left => Quat::from_rotation_y(-FRAC_PI_2),
+            Self::Top => Quat::from_rotation_x(-FRAC_PI_2),
+            Self::Bottom => Quat::from_rotation_x(FRAC_PI_2),
+        }
+    }
+}
+
+/// Saved view to come back to later.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CameraBookmark {
+    pub name: String,
+    /// The aspect isn't restored, it always follows the window.
+    pub camera: Camera,
+    pub orbit_distance: f32,
+}
+
+/// Changes to the camera asked for from the GUI or by hotkeys, applied on the next `CameraControls::update`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CameraRequest {
+    /// Frames the scene bounds.
+    Focus,
+    /// Turns to look along an axis at the point the orbit camera circles around.
+    AxisView(AxisView),
+    SetProjection(Projection),
+    ToggleProjection,
+    /// Adds a bookmark of the current view.
+    SaveBookmark(String),
+    /// Index into `CameraControls::bookmarks`.
+    RestoreBookmark(usize),
+}
+
+struct CameraTransition {
+    from: Camera,
+    to: Camera,
+    /// Seconds.
+    elapsed: f32,
+}
+
+/// Runs the controller of the active `CameraMode` and handles the actions shared by both,
+/// with these default bindings:
+///
+/// - F or numpad . frames everything in view
+/// - Numpad 1, 3 and 7 look from the front, right and top, with Ctrl from the opposite side
+/// - Numpad 5 switches between perspective and orthographic
+///
+/// Both controllers work off the camera's current position and orientation,
+/// so switching between them never makes the view jump.
+pub struct CameraControls {
+    pub fly: FlyCameraController,
+    pub orbit: OrbitCameraController,
+    pub bookmarks: Vec<CameraBookmark>,
+    /// Seconds it takes to move to a bookmark, axis view or framed scene. 0 jumps right away.
+    pub transition_duration: f32,
+    /// Whether axis views switch to orthographic projection, like technical drawings.
+    pub orthographic_axis_views: bool,
+    mode: CameraMode,
+    requests: Vec<CameraRequest>,
+    transition: Option<CameraTransition>,
+}
+
+impl Default for CameraControls {
+    fn default() -> Self {
+        Self {
+            fly: FlyCameraController::default(),
+            orbit: OrbitCameraController::default(),
+            bookmarks: Vec::new(),
+            transition_duration: 0.3,
+            orthographic_axis_views: true,
+            mode: CameraMode::default(),
+            requests: Vec::new(),
+            transition: None,
+        }
+    }
+}
+
+impl CameraControls {
+
+    const HOTKEYS: [(&'static str, CameraRequest); 8] = [
+        (actions::CAMERA_FOCUS, CameraRequest::Focus),
+        (actions::CAMERA_TOGGLE_PROJECTION, CameraRequest::ToggleProjection),
+        (actions::CAMERA_VIEW_FRONT, CameraRequest::AxisView(AxisView::Front)),
+        (actions::CAMERA_VIEW_BACK, CameraRequest::AxisView(AxisView::Back)),
+        (actions::CAMERA_VIEW_RIGHT, CameraRequest::AxisView(AxisView::Right)),
+        (actions::CAMERA_VIEW_LEFT, CameraRequest::AxisView(AxisView::Left)),
+        (actions::CAMERA_VIEW_TOP, CameraRequest::AxisView(AxisView::Top)),
+        (actions::CAMERA_VIEW_BOTTOM, CameraRequest::AxisView(AxisView::Bottom)),
+    ];
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn mode(&self) -> CameraMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: CameraMode) {
+        self.mode = mode;
+    }
+
+    /// Whether the cursor should be hidden and kept in place, while dragging to look around.
+    pub fn is_capturing_cursor(&self, input: &Input) -> bool {
+
+        match self.mode {
+            CameraMode::Fly => self.fly.is_looking(input),
+            CameraMode::Orbit => self.orbit.is_dragging(input),
+        }
+    }
+
+    pub fn is_transitioning(&self) -> bool {
+        self.transition.is_some()
+    }
+
+    /// Drops pending requests and any transition in progress, for when the camera is replaced outright.
+    pub fn cancel_pending(&mut self) {
+        self.requests.clear();
+        self.transition = None;
+    }
+
+    pub fn request(&mut self, request: CameraRequest) {
+        self.requests.push(request);
+    }
+
+    /// Applies this frame's input and the requests since the last update, and moves along the current transition.
+    /// `scene_bounds` is what gets framed on `CameraRequest::Focus`, see `MainRenderer::bounds`.
+    pub fn update(&mut self, camera: &mut Camera, input: &Input, scene_bounds: Option<Aabb>, delta_time: f32) {
+
+        for (action, request) in Self::HOTKEYS {
+            if input.action_pressed(action) {
+                self.request(request);
+            }
+        }
+
+        for request in std::mem::take(&mut self.requests) {
+            self.apply(request, camera, scene_bounds);
+        }
+
+        match self.mode {
+            CameraMode::Fly => self.fly.update(camera, input, delta_time),
+            CameraMode::Orbit => self.orbit.update(camera, input),
+        }
+
+        // Overrides whatever the controllers did, they pick up from wherever the transition ends
+        if let Some(transition) = self.transition.as_mut() {
+
+            transition.elapsed += delta_time;
+            let t = (transition.elapsed / self.transition_duration.max(f32::EPSILON)).min(1.0);
+            let eased = t * t * (3.0 - 2.0 * t);
+
+            *camera = Camera { aspect: camera.aspect, ..transition.from.lerp(&transition.to, eased) };
+
+            if t >= 1.0 {
+                self.transition = None;
+            }
+        }
+    }
+
+    fn apply(&mut self, request: CameraRequest, camera: &mut Camera, scene_bounds: Option<Aabb>) {
+
+        // Transitions start from wherever the camera is right now, even halfway through another one
+        let mut target = *camera;
+
+        match request {
+            CameraRequest::Focus => {
+                let Some(bounds) = scene_bounds else {
+                    return;
+                };
+                self.orbit.distance = target.frame(&bounds);
+            }
+            CameraRequest::AxisView(view) => {
+                let pivot = self.orbit.target(camera);
+                target.orientation = view.orientation();
+                target.position = pivot - target.forward() * self.orbit.distance;
+                if self.orthographic_axis_views {
+                    target.set_projection(Projection::Orthographic, self.orbit.distance);
+                }
+            }
+            CameraRequest::SetProjection(projection) => {
+                self.switch_projection(camera, projection);
+                return;
+            }
+            CameraRequest::ToggleProjection => {
+                let projection = match camera.projection {
+                    Projection::Perspective => Projection::Orthographic,
+                    Projection::Orthographic => Projection::Perspective,
+                };
+                self.switch_projection(camera, projection);
+                return;
+            }
+            CameraRequest::SaveBookmark(name) => {
+                self.bookmarks.push(CameraBookmark { name, camera: *camera, orbit_distance: self.orbit.distance });
+                return;
+            }
+            CameraRequest::RestoreBookmark(index) => {
+                let Some(bookmark) = self.bookmarks.get(index) else {
+                    return;
+                };
+                target = bookmark.camera;
+                self.orbit.distance = bookmark.orbit_distance;
+            }
+        }
+
+        self.transition = Some(CameraTransition { from: *camera, to: target, elapsed: 0.0 });
+    }
+
+    /// Keeps the orbit target the same size on screen.
+    fn switch_projection(&mut self, camera: &mut Camera, projection: Projection) {
+
+        // Distance doesn't matter to orthographic views, so it's picked to match their size
+        if projection == Projection::Perspective && camera.projection == Projection::Orthographic {
+            let pivot = self.orbit.target(camera);
+            self.orbit.distance = camera.ortho_height / (2.0 * (camera.fov_y / 2.0).tan());
+            camera.position = pivot - camera.forward() * self.orbit.distance;
+        }
+
+        camera.set_projection(projection, self.orbit.distance);
+    }
+}
+
+/// Free-flying camera: by default WASD to move, Q/E to go down/up, right mouse button to look around.
+/// Shift moves faster, Ctrl slower and the scroll wheel changes the base speed.
+pub struct FlyCameraController {
+    /// Units per second.
+    pub speed: f32,
+    /// Radians per pixel of mouse movement.
+    pub sensitivity: f32,
+    /// Applied to `speed` while `actions::CAMERA_FAST` is held.
+    pub fast_multiplier: f32,
+    /// Applied to `speed` while `actions::CAMERA_SLOW` is held.
+    pub slow_multiplier: f32,
+}
+
+impl Default for FlyCameraController {
+    fn default() -> Self {
+        Self {
+            speed: 2.0,
+            sensitivity: 0.003,
+            fast_multiplier: 4.0,
+            slow_multiplier: 0.25,
+        }
+    }
+}
+
+impl FlyCameraController {
+
+    /// How much one step of the scroll wheel changes `speed`.
+    const SCROLL_SPEED_FACTOR: f32 = 1.1;
+    const MIN_SPEED: f32 = 0.01;
+    const MAX_SPEED: f32 = 1000.0;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the look button is held, i.e. the cursor should be captured.
+    pub fn is_looking(&self, input: &Input) -> bool {
+        input.action_held(actions::CAMERA_LOOK)
+    }
+
+    /// Applies this frame's input. Movement is scaled by `delta_time` in seconds,
+    /// mouse movement isn't since it already adds up to more pixels on longer frames.
+    pub fn update(&mut self, camera: &mut Camera, input: &Input, delta_time: f32) {
+
+        if self.is_looking(input) {
+            turn(camera, Vec2::new(input.axis(axes::CAMERA_LOOK_X), input.axis(axes::CAMERA_LOOK_Y)) * self.sensitivity);
+        }
+
+        let steps = input.axis(axes::CAMERA_ZOOM);
+        if steps != 0.0 {
+            self.speed = (self.speed * Self::SCROLL_SPEED_FACTOR.powf(steps)).clamp(Self::MIN_SPEED, Self::MAX_SPEED);
+        }
+
+        let direction = camera.forward() * input.axis(axes::CAMERA_MOVE_FORWARD)
+            + camera.right() * input.axis(axes::CAMERA_MOVE_RIGHT)
+            + Vec3::Y * input.axis(axes::CAMERA_MOVE_UP);
+
+        let Some(direction) = direction.try_normalize() else {
+            return;
+        };
+
+        let mut speed = self.speed;
+        if input.action_held(actions::CAMERA_FAST) {
+            speed *= self.fast_multiplier;
+        }
+        if input.action_held(actions::CAMERA_SLOW) {
+            speed *= self.slow_multiplier;
+        }
+
+        camera.position += direction * speed * delta_time;
+    }
+}
+
+/// Rotates around a point in front of the camera: by default drag with the left mouse button to orbit,
+/// with the middle one or Shift + left to pan, scroll to zoom.
+pub struct OrbitCameraController {
+    /// From the camera to the point it orbits around, which is always straight ahead.
+    pub distance: f32,
+    /// Radians per pixel of mouse movement.
+    pub sensitivity: f32,
+    /// Fraction of `distance` panned per pixel of mouse movement.
+    pub pan_sensitivity: f32,
+    /// What the drag in progress does, decided when it started.
+    drag: Option<OrbitDrag>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum OrbitDrag {
+    Orbit,
+    Pan,
+}
+
+impl Default for OrbitCameraController {
+    fn default() -> Self {
+        Self {
+            distance: 2.0,
+            sensitivity: 0.005,
+            pan_sensitivity: 0.002,
+            drag: None,
+        }
+    }
+}
+
+impl OrbitCameraController {
+
+    /// How much closer one step of the scroll wheel moves the camera.
+    const ZOOM_FACTOR: f32 = 0.9;
+    const MIN_DISTANCE: f32 = 0.01;
+    const MAX_DISTANCE: f32 = 10000.0;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the orbit or pan button is held, i.e. the cursor should be captured.
+    pub fn is_dragging(&self, input: &Input) -> bool {
+        input.action_held(actions::CAMERA_ORBIT) || input.action_held(actions::CAMERA_PAN)
+    }
+
+    /// The point the camera orbits around.
+    pub fn target(&self, camera: &Camera) -> Vec3 {
+        camera.position + camera.forward() * self.distance
+    }
+
+    /// Applies this frame's input. Nothing here moves on its own, so there's no delta time.
+    /// Whether a drag pans or orbits is decided when it starts, panning winning since its default
+    /// Shift + left binding also holds the orbit button. Letting go of Shift mid-drag keeps panning.
+    pub fn update(&mut self, camera: &mut Camera, input: &Input) {
+
+        if !self.is_dragging(input) {
+            self.drag = None;
+        } else if self.drag.is_none() || input.action_pressed(actions::CAMERA_PAN) || input.action_pressed(actions::CAMERA_ORBIT) {
+            self.drag = Some(if input.action_held(actions::CAMERA_PAN) { OrbitDrag::Pan } else { OrbitDrag::Orbit });
+        }
+
+        // Everything is relative to the current view, so changes made elsewhere are kept
+        let target = self.target(camera);
+        let mouse_delta = Vec2::new(input.axis(axes::CAMERA_LOOK_X), input.axis(axes::CAMERA_LOOK_Y));
+
+        if mouse_delta != Vec2::ZERO {
+            match self.drag {
+                Some(OrbitDrag::Pan) => {
+                    let offset = camera.right() * -mouse_delta.x + camera.up() * mouse_delta.y;
+                    camera.position += offset * self.pan_sensitivity * self.distance;
+                }
+                Some(OrbitDrag::Orbit) => {
+                    turn(camera, mouse_delta * self.sensitivity);
+                    camera.position = target - camera.forward() * self.distance;
+                }
+                None => {}
+            }
+        }
+
+        let steps = input.axis(axes::CAMERA_ZOOM);
+        if steps != 0.0 {
+
+            let target = self.target(camera);
+            let distance = (self.distance * Self::ZOOM_FACTOR.powf(steps)).clamp(Self::MIN_DISTANCE, Self::MAX_DISTANCE);
+
+            // Moving closer doesn't change anything on screen without perspective
+            camera.ortho_height *= distance / self.distance;
+            self.distance = distance;
+            camera.position = target - camera.forward() * self.distance;
+        }
+    }
+}
+
+/// Turns the camera by yaw and pitch in radians, positive ones turning right and down.
+/// Never rolls the camera, and stops short of looking straight up or down.
+fn turn(camera: &mut Camera, delta: Vec2) {
+
+    let forward = camera.forward();
+    // Looking straight up or down, e.g. from the top view, the heading is only left in the right vector
+    let heading = if forward.y.abs() > 0.9999 {
+        (-camera.right().z).atan2(camera.right().x)
+    } else {
+        (-forward.x).atan2(-forward.z)
+    };
+    let yaw = heading - delta.x;
+    let pitch = (forward.y.clamp(-1.0, 1.0).asin() - delta.y).clamp(-MAX_PITCH, MAX_PITCH);
+
+    camera.orientation = Quat::from_euler(EulerRot::YXZ, yaw, pitch, 0.0);
+}
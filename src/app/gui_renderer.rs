@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use egui::Context;
 use egui_wgpu::wgpu::{CommandEncoder, Device, Queue, StoreOp, TextureFormat, TextureView};
 use egui_wgpu::{wgpu, Renderer, ScreenDescriptor};
@@ -5,10 +7,24 @@ use egui_winit::State;
 use winit::event::WindowEvent;
 use winit::window::Window;
 
+use super::camera_controller::{AxisView, CameraControls, CameraMode, CameraRequest};
+use super::main_renderer::camera::{Camera, Projection};
+use super::main_renderer::depth_texture::DepthTexture;
+
+/// Asked for from the "Scene" section, handled by `App` once the GUI is done.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SceneRequest {
+    Load(PathBuf),
+    Save(PathBuf),
+}
+
 pub struct GUIRenderer {
     state: State,
     renderer: Renderer,
     frame_started: bool,
+
+    output_depth_format: Option<TextureFormat>,
+    msaa_samples: u32,
 }
 
 impl GUIRenderer {
@@ -47,14 +63,51 @@ impl GUIRenderer {
             state,
             renderer,
             frame_started: false,
+            output_depth_format,
+            msaa_samples,
         }
     }
 
+    pub fn output_depth_format(&self) -> Option<TextureFormat> {
+        self.output_depth_format
+    }
+
+    /// Recreates the egui renderer for a new device or output format, keeping the GUI state (window positions, etc.).
+    pub fn rebuild(
+        &mut self,
+        device: &Device,
+        output_color_format: TextureFormat,
+        output_depth_format: Option<TextureFormat>,
+        window: &Window,
+    ) {
+
+        let memory = self.get_context().memory(|memory| memory.clone());
+
+        *self = GUIRenderer::new(
+            device,
+            output_color_format,
+            output_depth_format,
+            self.msaa_samples,
+            window,
+        );
+
+        self.get_context().memory_mut(|new_memory| *new_memory = memory);
+    }
+
     pub fn handle_input(&mut self, window: &Window, event: &WindowEvent) {
         let _ = self.state.on_window_event(window, event);
     }
 
-    pub fn render(&self, fps: f32) {
+    pub fn render(
+        &self,
+        fps: f32,
+        shader_error: Option<&str>,
+        scene_error: Option<&str>,
+        camera_controls: &mut CameraControls,
+        camera: &Camera,
+    ) -> Option<SceneRequest> {
+
+        let mut scene_request = None;
 
         egui::Window::new("Settings")
             .resizable(true)
@@ -67,7 +120,115 @@ impl GUIRenderer {
                 if ui.button("Test").clicked() {
                     println!("Click!")
                 }
+
+                ui.separator();
+                ui.collapsing("Scene", |ui| scene_request = Self::scene_settings(ui, scene_error));
+                ui.collapsing("Camera", |ui| Self::camera_settings(ui, camera_controls, camera));
+
+                if let Some(shader_error) = shader_error {
+                    ui.separator();
+                    ui.colored_label(egui::Color32::RED, "Shader error, using the last working version:");
+                    ui.label(egui::RichText::new(shader_error).monospace());
+                }
+            });
+
+        scene_request
+    }
+
+    fn scene_settings(ui: &mut egui::Ui, scene_error: Option<&str>) -> Option<SceneRequest> {
+
+        let path_id = ui.id().with("scene path");
+        let mut path = ui.data_mut(|data| data.get_temp::<String>(path_id)).unwrap_or_else(|| "scene.json".to_owned());
+        let mut request = None;
+
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut path).on_hover_text("JSON scene file, relative to the working directory");
+            let has_path = !path.trim().is_empty();
+            if ui.add_enabled(has_path, egui::Button::new("Load")).clicked() {
+                request = Some(SceneRequest::Load(path.trim().into()));
+            }
+            if ui.add_enabled(has_path, egui::Button::new("Save")).clicked() {
+                request = Some(SceneRequest::Save(path.trim().into()));
+            }
+        });
+        ui.data_mut(|data| data.insert_temp(path_id, path));
+
+        if let Some(scene_error) = scene_error {
+            ui.colored_label(egui::Color32::RED, scene_error);
+        }
+
+        request
+    }
+
+    fn camera_settings(ui: &mut egui::Ui, camera_controls: &mut CameraControls, camera: &Camera) {
+
+        let mut camera_mode = camera_controls.mode();
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut camera_mode, CameraMode::Fly, "Fly");
+            ui.radio_value(&mut camera_mode, CameraMode::Orbit, "Orbit");
+            if ui.button("Focus").on_hover_text("Frame everything in view (F)").clicked() {
+                camera_controls.request(CameraRequest::Focus);
+            }
+        });
+        camera_controls.set_mode(camera_mode);
+
+        ui.label(match camera_mode {
+            CameraMode::Fly => "WASD to move, Q/E down/up, hold right mouse button to look around",
+            CameraMode::Orbit => "Drag to orbit, Shift + drag or middle mouse button to pan, scroll to zoom",
+        });
+
+        let mut projection = camera.projection;
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut projection, Projection::Perspective, "Perspective");
+            ui.radio_value(&mut projection, Projection::Orthographic, "Orthographic")
+                .on_hover_text("Numpad 5 switches too");
+        });
+        if projection != camera.projection {
+            camera_controls.request(CameraRequest::SetProjection(projection));
+        }
+
+        ui.horizontal_wrapped(|ui| {
+            for view in AxisView::ALL {
+                if ui.button(view.name()).clicked() {
+                    camera_controls.request(CameraRequest::AxisView(view));
+                }
+            }
+        })
+        .response
+        .on_hover_text("Numpad 1, 3 and 7, with Ctrl for the opposite side");
+        ui.checkbox(&mut camera_controls.orthographic_axis_views, "Orthographic axis views");
+
+        ui.label("Bookmarks");
+
+        // Kept in egui's memory, the text field needs it from one frame to the next
+        let name_id = ui.id().with("bookmark name");
+        let mut name = ui.data_mut(|data| data.get_temp::<String>(name_id)).unwrap_or_default();
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut name);
+            if ui.add_enabled(!name.trim().is_empty(), egui::Button::new("Save")).clicked() {
+                camera_controls.request(CameraRequest::SaveBookmark(name.trim().to_owned()));
+                name.clear();
+            }
+        });
+        ui.data_mut(|data| data.insert_temp(name_id, name));
+
+        let (mut restored, mut removed) = (None, None);
+        for (index, bookmark) in camera_controls.bookmarks.iter().enumerate() {
+            ui.horizontal(|ui| {
+                if ui.button(&bookmark.name).clicked() {
+                    restored = Some(index);
+                }
+                if ui.small_button("✖").clicked() {
+                    removed = Some(index);
+                }
             });
+        }
+        if let Some(index) = restored {
+            camera_controls.request(CameraRequest::RestoreBookmark(index));
+        }
+        if let Some(index) = removed {
+            camera_controls.bookmarks.remove(index);
+        }
     }
 
     pub fn begin_gui(&mut self, window: &Window) {
@@ -76,6 +237,7 @@ impl GUIRenderer {
         self.frame_started = true;
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn end_gui(
         &mut self,
         device: &Device,
@@ -83,6 +245,7 @@ impl GUIRenderer {
         encoder: &mut CommandEncoder,
         window: &Window,
         surface_view: &TextureView,
+        depth_texture: Option<&DepthTexture>,
         screen_descriptor: ScreenDescriptor,
     ) {
         if !self.frame_started {
@@ -117,7 +280,8 @@ impl GUIRenderer {
                 },
             })],
 
-            depth_stencil_attachment: None,
+            // Only there because the pipeline was built for `output_depth_format`, the GUI ignores depth
+            depth_stencil_attachment: depth_texture.map(DepthTexture::load_attachment),
             timestamp_writes: None,
             label: Some("GUI Render Pass"),
             occlusion_query_set: None,
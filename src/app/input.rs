@@ -0,0 +1,414 @@
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+use winit::event::{DeviceEvent, ElementState, MouseButton, MouseScrollDelta, WindowEvent};
+use winit::keyboard::{KeyCode, PhysicalKey};
+
+use crate::error::{CandleError, CandleResult};
+
+/// Names of the actions Candle itself reads, bound in `InputBindings::default`.
+pub mod actions {
+    pub const CAMERA_LOOK: &str = "camera_look";
+    pub const CAMERA_FAST: &str = "camera_fast";
+    pub const CAMERA_SLOW: &str = "camera_slow";
+    pub const CAMERA_ORBIT: &str = "camera_orbit";
+    pub const CAMERA_PAN: &str = "camera_pan";
+    pub const CAMERA_FOCUS: &str = "camera_focus";
+    pub const CAMERA_TOGGLE_PROJECTION: &str = "camera_toggle_projection";
+    pub const CAMERA_VIEW_FRONT: &str = "camera_view_front";
+    pub const CAMERA_VIEW_BACK: &str = "camera_view_back";
+    pub const CAMERA_VIEW_RIGHT: &str = "camera_view_right";
+    pub const CAMERA_VIEW_LEFT: &str = "camera_view_left";
+    pub const CAMERA_VIEW_TOP: &str = "camera_view_top";
+    pub const CAMERA_VIEW_BOTTOM: &str = "camera_view_bottom";
+}
+
+/// Names of the axes Candle itself reads, bound in `InputBindings::default`.
+pub mod axes {
+    pub const CAMERA_MOVE_FORWARD: &str = "camera_move_forward";
+    pub const CAMERA_MOVE_RIGHT: &str = "camera_move_right";
+    pub const CAMERA_MOVE_UP: &str = "camera_move_up";
+    pub const CAMERA_LOOK_X: &str = "camera_look_x";
+    pub const CAMERA_LOOK_Y: &str = "camera_look_y";
+    pub const CAMERA_ZOOM: &str = "camera_zoom";
+}
+
+/// A key, by its position on the keyboard rather than what it types, or a mouse button.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Button {
+    Key(KeyCode),
+    Mouse(MouseButton),
+}
+
+/// Modifier keys, left and right count the same.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Modifiers {
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub ctrl: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub shift: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub alt: bool,
+    /// The Windows, Command or Super key.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub logo: bool,
+}
+
+impl Modifiers {
+
+    pub const NONE: Self = Self { ctrl: false, shift: false, alt: false, logo: false };
+    pub const CTRL: Self = Self { ctrl: true, ..Self::NONE };
+    pub const SHIFT: Self = Self { shift: true, ..Self::NONE };
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::NONE
+    }
+
+    /// Whether every modifier in `other` is also in `self`.
+    pub fn contains(self, other: Self) -> bool {
+        (self.ctrl || !other.ctrl) && (self.shift || !other.shift) && (self.alt || !other.alt) && (self.logo || !other.logo)
+    }
+
+    fn without(self, other: Self) -> Self {
+        Self {
+            ctrl: self.ctrl && !other.ctrl,
+            shift: self.shift && !other.shift,
+            alt: self.alt && !other.alt,
+            logo: self.logo && !other.logo,
+        }
+    }
+
+    /// The modifier `button` is itself, if any.
+    fn of(button: Button) -> Self {
+
+        match button {
+            Button::Key(KeyCode::ControlLeft | KeyCode::ControlRight) => Self::CTRL,
+            Button::Key(KeyCode::ShiftLeft | KeyCode::ShiftRight) => Self::SHIFT,
+            Button::Key(KeyCode::AltLeft | KeyCode::AltRight) => Self { alt: true, ..Self::NONE },
+            Button::Key(KeyCode::SuperLeft | KeyCode::SuperRight) => Self { logo: true, ..Self::NONE },
+            _ => Self::NONE,
+        }
+    }
+}
+
+/// A button that triggers an action, optionally only together with modifiers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Binding {
+    pub button: Button,
+    #[serde(default, skip_serializing_if = "Modifiers::is_empty")]
+    pub modifiers: Modifiers,
+}
+
+impl Binding {
+
+    pub fn new(button: Button) -> Self {
+        Self { button, modifiers: Modifiers::NONE }
+    }
+
+    pub fn key(key: KeyCode) -> Self {
+        Self::new(Button::Key(key))
+    }
+
+    pub fn mouse(button: MouseButton) -> Self {
+        Self::new(Button::Mouse(button))
+    }
+
+    pub fn with_modifiers(mut self, modifiers: Modifiers) -> Self {
+        self.modifiers = modifiers;
+        self
+    }
+}
+
+/// Where the value of an axis comes from. An axis with several bindings adds them up.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum AxisBinding {
+    /// 1 while `positive` is held, -1 while `negative` is and 0 for both or neither.
+    Buttons { positive: Button, negative: Button },
+    /// Raw mouse movement to the right this frame, in pixels times `scale`.
+    MouseX { scale: f32 },
+    /// Raw mouse movement down this frame, in pixels times `scale`.
+    MouseY { scale: f32 },
+    /// Scroll wheel steps away from the user this frame, times `scale`.
+    Scroll { scale: f32 },
+}
+
+/// Named actions and axes and what they're bound to, saved as JSON so they can be changed without rebuilding.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputBindings {
+    pub actions: BTreeMap<String, Vec<Binding>>,
+    pub axes: BTreeMap<String, Vec<AxisBinding>>,
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+
+        use actions::*;
+        use axes::*;
+
+        let key = Binding::key;
+        let keys = |positive, negative| AxisBinding::Buttons { positive: Button::Key(positive), negative: Button::Key(negative) };
+
+        let actions = [
+            (CAMERA_LOOK, vec![Binding::mouse(MouseButton::Right)]),
+            (CAMERA_FAST, vec![key(KeyCode::ShiftLeft), key(KeyCode::ShiftRight)]),
+            (CAMERA_SLOW, vec![key(KeyCode::ControlLeft), key(KeyCode::ControlRight)]),
+            (CAMERA_ORBIT, vec![Binding::mouse(MouseButton::Left)]),
+            (CAMERA_PAN, vec![
+                Binding::mouse(MouseButton::Middle),
+                Binding::mouse(MouseButton::Left).with_modifiers(Modifiers::SHIFT),
+            ]),
+            (CAMERA_FOCUS, vec![key(KeyCode::KeyF), key(KeyCode::NumpadDecimal)]),
+            (CAMERA_TOGGLE_PROJECTION, vec![key(KeyCode::Numpad5)]),
+            (CAMERA_VIEW_FRONT, vec![key(KeyCode::Numpad1)]),
+            (CAMERA_VIEW_BACK, vec![key(KeyCode::Numpad1).with_modifiers(Modifiers::CTRL)]),
+            (CAMERA_VIEW_RIGHT, vec![key(KeyCode::Numpad3)]),
+            (CAMERA_VIEW_LEFT, vec![key(KeyCode::Numpad3).with_modifiers(Modifiers::CTRL)]),
+            (CAMERA_VIEW_TOP, vec![key(KeyCode::Numpad7)]),
+            (CAMERA_VIEW_BOTTOM, vec![key(KeyCode::Numpad7).with_modifiers(Modifiers::CTRL)]),
+        ];
+
+        let axes = [
+            (CAMERA_MOVE_FORWARD, vec![keys(KeyCode::KeyW, KeyCode::KeyS)]),
+            (CAMERA_MOVE_RIGHT, vec![keys(KeyCode::KeyD, KeyCode::KeyA)]),
+            (CAMERA_MOVE_UP, vec![keys(KeyCode::KeyE, KeyCode::KeyQ)]),
+            (CAMERA_LOOK_X, vec![AxisBinding::MouseX { scale: 1.0 }]),
+            (CAMERA_LOOK_Y, vec![AxisBinding::MouseY { scale: 1.0 }]),
+            (CAMERA_ZOOM, vec![AxisBinding::Scroll { scale: 1.0 }]),
+        ];
+
+        Self {
+            actions: actions.into_iter().map(|(name, bindings)| (name.to_owned(), bindings)).collect(),
+            axes: axes.into_iter().map(|(name, bindings)| (name.to_owned(), bindings)).collect(),
+        }
+    }
+}
+
+impl InputBindings {
+
+    /// Parses bindings saved by `to_json`. Actions and axes the file leaves out keep their default bindings,
+    /// so files written by older versions pick up new ones. An empty list unbinds.
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+
+        let mut bindings = Self::default();
+        let loaded: Self = serde_json::from_str(json)?;
+
+        bindings.actions.extend(loaded.actions);
+        bindings.axes.extend(loaded.axes);
+
+        Ok(bindings)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Input bindings always serialize")
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> CandleResult<Self> {
+
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .map_err(|source| CandleError::InputBindingsRead { path: path.to_owned(), source })?;
+
+        Self::from_json(&json).map_err(|source| CandleError::InputBindingsParse { path: path.to_owned(), source })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> CandleResult<()> {
+
+        let path = path.as_ref();
+        std::fs::write(path, self.to_json())
+            .map_err(|source| CandleError::InputBindingsWrite { path: path.to_owned(), source })
+    }
+}
+
+/// Keyboard, mouse and scroll state for the current frame, and the actions and axes it adds up to.
+///
+/// Fed by `App` from winit events and cleared by `end_frame` once everything has had a look, so
+/// "pressed" and "released" mean since the previous frame. Presses egui wants for itself never arrive,
+/// releases always do so nothing gets stuck.
+#[derive(Default)]
+pub struct Input {
+    pub bindings: InputBindings,
+
+    held: HashSet<Button>,
+    pressed: HashSet<Button>,
+    released: HashSet<Button>,
+    mouse_delta: Vec2,
+    scroll: f32,
+    cursor_position: Option<Vec2>,
+}
+
+impl Input {
+
+    pub fn new(bindings: InputBindings) -> Self {
+        Self { bindings, ..Default::default() }
+    }
+
+    pub fn handle_window_event(&mut self, event: &WindowEvent, gui: &egui::Context) {
+
+        match event {
+            WindowEvent::KeyboardInput { event, .. } => {
+                if let PhysicalKey::Code(key) = event.physical_key {
+                    match event.state {
+                        ElementState::Pressed if !gui.wants_keyboard_input() => self.press(Button::Key(key)),
+                        ElementState::Pressed => (),
+                        ElementState::Released => self.release(Button::Key(key)),
+                    }
+                }
+            }
+            WindowEvent::MouseInput { state, button, .. } => match state {
+                ElementState::Pressed if !gui.wants_pointer_input() => self.press(Button::Mouse(*button)),
+                ElementState::Pressed => (),
+                ElementState::Released => self.release(Button::Mouse(*button)),
+            },
+            WindowEvent::MouseWheel { delta, .. } if !gui.wants_pointer_input() => {
+                let steps = match delta {
+                    MouseScrollDelta::LineDelta(_, lines) => *lines,
+                    MouseScrollDelta::PixelDelta(position) => position.y as f32 / 20.0,
+                };
+                self.add_scroll(steps);
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_position = Some(Vec2::new(position.x as f32, position.y as f32));
+            }
+            WindowEvent::CursorLeft { .. } => self.cursor_position = None,
+            WindowEvent::Focused(false) => self.release_all(),
+            _ => (),
+        }
+    }
+
+    pub fn handle_device_event(&mut self, event: &DeviceEvent) {
+
+        // Raw motion keeps coming while the cursor is locked in place, unlike `CursorMoved`
+        if let DeviceEvent::MouseMotion { delta } = event {
+            self.add_mouse_motion(Vec2::new(delta.0 as f32, delta.1 as f32));
+        }
+    }
+
+    /// Key repeats are ignored, a button only counts as pressed when it wasn't held yet.
+    pub fn press(&mut self, button: Button) {
+
+        if self.held.insert(button) {
+            self.pressed.insert(button);
+        }
+    }
+
+    pub fn release(&mut self, button: Button) {
+
+        if self.held.remove(&button) {
+            self.released.insert(button);
+        }
+    }
+
+    /// Raw mouse movement in pixels.
+    pub fn add_mouse_motion(&mut self, delta: Vec2) {
+        self.mouse_delta += delta;
+    }
+
+    /// Scroll wheel steps, positive away from the user.
+    pub fn add_scroll(&mut self, steps: f32) {
+        self.scroll += steps;
+    }
+
+    /// Releases every held button, e.g. when the window loses focus and won't see them released.
+    pub fn release_all(&mut self) {
+        self.released.extend(self.held.drain());
+    }
+
+    /// Forgets what happened this frame. Buttons stay held.
+    pub fn end_frame(&mut self) {
+
+        self.pressed.clear();
+        self.released.clear();
+        self.mouse_delta = Vec2::ZERO;
+        self.scroll = 0.0;
+    }
+
+    pub fn is_held(&self, button: Button) -> bool {
+        self.held.contains(&button)
+    }
+
+    pub fn was_pressed(&self, button: Button) -> bool {
+        self.pressed.contains(&button)
+    }
+
+    pub fn was_released(&self, button: Button) -> bool {
+        self.released.contains(&button)
+    }
+
+    pub fn modifiers(&self) -> Modifiers {
+
+        self.held.iter().fold(Modifiers::NONE, |modifiers, &button| {
+            let of = Modifiers::of(button);
+            Modifiers {
+                ctrl: modifiers.ctrl || of.ctrl,
+                shift: modifiers.shift || of.shift,
+                alt: modifiers.alt || of.alt,
+                logo: modifiers.logo || of.logo,
+            }
+        })
+    }
+
+    /// Raw mouse movement this frame in pixels, right and down positive.
+    pub fn mouse_delta(&self) -> Vec2 {
+        self.mouse_delta
+    }
+
+    /// Scroll wheel steps this frame, positive away from the user.
+    pub fn scroll(&self) -> f32 {
+        self.scroll
+    }
+
+    /// In physical pixels from the top left of the window, `None` while the cursor is outside.
+    pub fn cursor_position(&self) -> Option<Vec2> {
+        self.cursor_position
+    }
+
+    /// Whether a binding of the action was pressed this frame with exactly its modifiers held,
+    /// so Numpad 1 and Ctrl + Numpad 1 can trigger different actions.
+    pub fn action_pressed(&self, action: &str) -> bool {
+
+        let modifiers = self.modifiers();
+        self.action_bindings(action).any(|binding| {
+            self.was_pressed(binding.button) && modifiers.without(Modifiers::of(binding.button)) == binding.modifiers
+        })
+    }
+
+    /// Whether a binding of the action is held with at least its modifiers, so for instance
+    /// moving with Shift held still counts as moving.
+    pub fn action_held(&self, action: &str) -> bool {
+
+        let modifiers = self.modifiers();
+        self.action_bindings(action)
+            .any(|binding| self.is_held(binding.button) && modifiers.contains(binding.modifiers))
+    }
+
+    /// Whether a binding of the action was released this frame. Modifiers are ignored,
+    /// they may well have been let go first.
+    pub fn action_released(&self, action: &str) -> bool {
+        self.action_bindings(action).any(|binding| self.was_released(binding.button))
+    }
+
+    /// Sum of the axis bindings this frame, 0 for unknown axes.
+    pub fn axis(&self, axis: &str) -> f32 {
+
+        let bindings = self.bindings.axes.get(axis).map(Vec::as_slice).unwrap_or_default();
+        bindings
+            .iter()
+            .map(|binding| match *binding {
+                AxisBinding::Buttons { positive, negative } => {
+                    self.is_held(positive) as i32 as f32 - self.is_held(negative) as i32 as f32
+                }
+                AxisBinding::MouseX { scale } => self.mouse_delta.x * scale,
+                AxisBinding::MouseY { scale } => self.mouse_delta.y * scale,
+                AxisBinding::Scroll { scale } => self.scroll * scale,
+            })
+            .sum()
+    }
+
+    fn action_bindings(&self, action: &str) -> impl Iterator<Item = &Binding> {
+        self.bindings.actions.get(action).into_iter().flatten()
+    }
+}
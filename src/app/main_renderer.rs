@@ -1,22 +1,101 @@
-use egui_wgpu::wgpu::{self, util::DeviceExt, CommandEncoder, TextureView};
-use texture::Texture;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use asset_server::{AssetId, AssetServer, Handle};
+use camera::{Camera, CameraBuffer};
+use cpu_resources::CpuResources;
+use depth_texture::DepthTexture;
+use egui_wgpu::wgpu::{self, CommandEncoder, TextureView};
+use material::Material;
+use mesh::{Aabb, Mesh};
+use model::{MeshData, Model};
+use object_buffer::{ObjectBuffer, ObjectUniform};
+use offscreen::OffscreenTarget;
+use pipeline_cache::{PipelineCache, PipelineId, PipelineKey};
+use scene::{Node, NodeId, Scene, Transform};
+use settings::RendererSettings;
+use shader_preprocessor::ShaderPreprocessor;
+use shader_watcher::ShaderWatcher;
+use shapes::ProceduralShape;
+use texture::{Texture, TextureData};
 use vertex::Vertex;
 
-mod vertex;
-mod texture;
+use super::render_graph::{self, RenderGraph, TransientTexturePool};
+use crate::error::CandleResult;
+
+pub mod vertex;
+pub mod texture;
 mod renderer_utils;
+mod offscreen;
+mod cpu_resources;
+mod shader_watcher;
+mod asset_loader;
+pub mod shader_preprocessor;
+pub mod pipeline_cache;
+pub mod asset_server;
+pub mod model;
+pub mod mesh;
+pub mod shapes;
+pub mod camera;
+pub mod scene;
+pub mod material;
+pub mod object_buffer;
+pub mod settings;
+pub mod depth_texture;
+
+/// One sub-mesh of a scene node, collected by `MainRenderer::prepare_scene`.
+struct Draw {
+    mesh: Handle<Mesh>,
+    submesh: usize,
+    pipeline: PipelineId,
+    /// Base color texture of the material.
+    texture: AssetId,
+    /// Index into the `ObjectBuffer`.
+    object: usize,
+}
 
 pub struct MainRenderer {
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
+    /// Describes the current color target. In headless mode there is no real surface behind it.
     pub surface_config: wgpu::SurfaceConfiguration,
-    pub surface: wgpu::Surface<'static>,
-    pub render_pipeline: wgpu::RenderPipeline,
+    pub surface: Option<wgpu::Surface<'static>>,
+    pub offscreen_target: Option<OffscreenTarget>,
+    /// Same size as the color target, recreated by `resize_surface`.
+    pub depth_texture: DepthTexture,
+    pub pipeline_cache: PipelineCache,
+    pub main_pipeline_key: PipelineKey,
+
+    /// Everything drawn each frame.
+    pub scene: Scene,
+    /// Uploaded to `camera_buffer` every frame.
+    pub camera: Camera,
+    pub settings: RendererSettings,
+    pub camera_buffer: CameraBuffer,
+    pub object_buffer: ObjectBuffer,
+    pub texture_bind_group_layout: wgpu::BindGroupLayout,
+    pub diffuse_texture: Handle<Texture>,
+    /// Used by sub-meshes whose node has no material for them: the diffuse texture, untinted.
+    pub default_material: Handle<Material>,
+    /// Stands in for the texture of materials without one.
+    pub white_texture: Handle<Texture>,
+    pub transient_pool: TransientTexturePool,
+    draws: Vec<Draw>,
+    /// By texture, for the textures the last `prepare_scene` saw in use.
+    texture_bind_groups: HashMap<AssetId, wgpu::BindGroup>,
+    /// By mesh, resolved once rather than hashing a `PipelineKey` every frame. `None` if building it failed.
+    mesh_pipelines: HashMap<AssetId, Option<PipelineId>>,
+
+    pub assets: AssetServer,
+    pub cpu_resources: CpuResources,
+    force_fallback_adapter: bool,
+    device_lost: Arc<AtomicBool>,
 
-    pub vertex_buffer: wgpu::Buffer,
-    pub index_buffer: wgpu::Buffer,
-    pub amount_of_vertices: u32,
-    pub diffuse_bind_group: wgpu::BindGroup,
+    shader_watcher: Option<ShaderWatcher>,
+    /// Why the last shader reload failed. The previous pipeline stays in use until the error is fixed.
+    pub shader_error: Option<String>,
 }
 
 impl MainRenderer {
@@ -26,114 +105,613 @@ impl MainRenderer {
         surface: wgpu::Surface<'static>,
         width: u32,
         height: u32,
-    ) -> Self {
+        present_mode: wgpu::PresentMode,
+        assets: AssetServer,
+    ) -> CandleResult<Self> {
 
-        let (adapter, device, queue) = renderer_utils::get_device(instance, &surface).await;
-        let surface_config = renderer_utils::configure_surface(&surface, width, height, &device, &adapter);
+        let cpu_resources = CpuResources::load_default()?;
 
-        let mut diffuse_texture = Texture::new("Checker.png", "Diffuse", &device, &queue);
-        let diffuse_texture_bind_group = diffuse_texture.create_bind_group(&device);
+        Self::new_with_resources(instance, surface, width, height, present_mode, assets, cpu_resources).await
+    }
 
-        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/shader.wgsl").into()),
-        });
+    async fn new_with_resources(
+        instance: &wgpu::Instance,
+        surface: wgpu::Surface<'static>,
+        width: u32,
+        height: u32,
+        present_mode: wgpu::PresentMode,
+        assets: AssetServer,
+        cpu_resources: CpuResources,
+    ) -> CandleResult<Self> {
+
+        let (adapter, device, queue) = renderer_utils::get_device(instance, Some(&surface), false).await?;
+        let surface_config = renderer_utils::configure_surface(&surface, width, height, present_mode, &device, &adapter)?;
+
+        Self::build(device, queue, surface_config, Some(surface), None, assets, cpu_resources, false)
+    }
+
+    /// Creates a renderer without a window, drawing into an owned RGBA8 texture of the given size.
+    /// Set `force_fallback_adapter` to pick a software adapter, e.g. on CI machines without a GPU.
+    pub async fn new_headless(
+        instance: &wgpu::Instance,
+        width: u32,
+        height: u32,
+        force_fallback_adapter: bool,
+        assets: AssetServer,
+    ) -> CandleResult<Self> {
+
+        let cpu_resources = CpuResources::load_default()?;
+
+        Self::new_headless_with_resources(instance, width, height, force_fallback_adapter, assets, cpu_resources).await
+    }
+
+    async fn new_headless_with_resources(
+        instance: &wgpu::Instance,
+        width: u32,
+        height: u32,
+        force_fallback_adapter: bool,
+        assets: AssetServer,
+        cpu_resources: CpuResources,
+    ) -> CandleResult<Self> {
+
+        let (_, device, queue) = renderer_utils::get_device(instance, None, force_fallback_adapter).await?;
+        let surface_config = renderer_utils::configure_offscreen(width, height, wgpu::TextureFormat::Rgba8UnormSrgb);
+        let offscreen_target = OffscreenTarget::new(&device, &surface_config);
 
+        Self::build(device, queue, surface_config, None, Some(offscreen_target), assets, cpu_resources, force_fallback_adapter)
+    }
+
+    /// Requests a new device and recreates every GPU resource from the retained `cpu_resources`.
+    /// Used after the device is lost, e.g. on a driver reset or when the system switches GPUs.
+    pub async fn rebuild(self, instance: &wgpu::Instance) -> CandleResult<Self> {
+
+        let MainRenderer {
+            surface,
+            surface_config,
+            assets,
+            cpu_resources,
+            force_fallback_adapter,
+            shader_watcher,
+            shader_error,
+            scene,
+            camera,
+            settings,
+            depth_texture,
+            ..
+        } = self;
 
-        const VERTICES: &[Vertex] = &[
-            Vertex { position: [0.0, 0.5, 0.0], uv: [0.5, 0.0] },
-            Vertex { position: [-0.5, -0.5, 0.0], uv: [0.0, 1.0] },
-            Vertex { position: [0.5, -0.5, 0.0], uv: [1.0, 1.0] },
-        ];
+        let (width, height) = (surface_config.width, surface_config.height);
 
-        const INDICES: &[u16] = &[
-            0, 1, 2
-        ];
+        let mut renderer = match surface {
+            Some(surface) => {
+                let present_mode = surface_config.present_mode;
+                Self::new_with_resources(instance, surface, width, height, present_mode, assets, cpu_resources).await
+            }
+            None => {
+                Self::new_headless_with_resources(instance, width, height, force_fallback_adapter, assets, cpu_resources).await
+            }
+        }?;
 
-        let amount_of_vertices = VERTICES.len() as u32;
+        renderer.shader_watcher = shader_watcher;
+        renderer.shader_error = shader_error;
+        renderer.scene = scene;
+        renderer.camera = camera;
+        renderer.settings = settings;
+        renderer.set_depth_format(depth_texture.format);
 
-        let vertex_buffer_description = &wgpu::util::BufferInitDescriptor {
-            label: Some("Vertex Buffer"),
-            contents: bytemuck::cast_slice(VERTICES),
-            usage: wgpu::BufferUsages::VERTEX,
+        Ok(renderer)
+    }
+
+    /// Development mode: watches the WGSL file at `path` and everything it includes, rebuilding
+    /// the render pipeline whenever they change. The files are read right away on the next
+    /// `reload_changed_shaders` call.
+    pub fn enable_shader_hot_reload(&mut self, path: impl Into<PathBuf>) {
+        self.shader_watcher = Some(ShaderWatcher::new(path.into()));
+    }
+
+    /// Swaps in a new render pipeline if the watched shader changed and compiles. On errors the old
+    /// pipeline keeps being used and the error is stored in `shader_error`.
+    pub fn reload_changed_shaders(&mut self) {
+
+        let Some(watcher) = self.shader_watcher.as_mut() else {
+            return;
         };
 
-        let vertex_buffer = device.create_buffer_init(vertex_buffer_description);
+        if !watcher.poll() {
+            return;
+        }
+
+        let entry = watcher.entry().to_owned();
+        let root = entry.parent().map(PathBuf::from).unwrap_or_default();
+        let file_name = entry.file_name().map(PathBuf::from).unwrap_or_default();
 
-        let index_buffer_description = &wgpu::util::BufferInitDescriptor {
-            label: Some("Index Buffer"),
-            contents: bytemuck::cast_slice(INDICES),
-            usage: wgpu::BufferUsages::INDEX,
+        let shader = match ShaderPreprocessor::new(root).prefer_disk(true).process(file_name) {
+            Ok(shader) => shader,
+            Err(error) => {
+                log::error!("Shader reload failed, keeping the previous pipeline:\n{error}");
+                self.shader_error = Some(error.to_string());
+                return;
+            }
         };
 
-        let index_buffer = device.create_buffer_init(index_buffer_description);
+        watcher.watch(shader.files.clone());
 
+        if let Err(error) = shader.validate() {
+            log::error!("Shader reload failed, keeping the previous pipeline:\n{error}");
+            self.shader_error = Some(error.to_string());
+            return;
+        }
+
+        let source = shader.source;
+
+        if source == self.cpu_resources.shader_source && self.shader_error.is_none() {
+            return;
+        }
+
+        // Variants for depth formats and vertex layouts nothing draws with anymore could fail for no reason
+        self.evict_unused_pipelines();
+
+        match self.pipeline_cache.try_replace_shader(&self.device, self.main_pipeline_key.shader, &source) {
+            Err(error) => {
+                log::error!("Shader reload failed, keeping the previous pipeline:\n{error}");
+                self.shader_error = Some(error);
+            }
+            Ok(()) => {
+                log::info!("Reloaded shader");
+                self.cpu_resources.shader_source = source;
+                self.shader_error = None;
+                // Meshes the previous shader couldn't draw get another try
+                self.mesh_pipelines.retain(|_, pipeline| pipeline.is_some());
+                self.prepare_mesh_pipelines();
+            }
+        }
+    }
+
+    /// Swaps in textures that finished loading in the background and frees the ones nothing uses anymore.
+    /// Called once per frame, before rendering.
+    pub fn update_assets(&mut self) {
+
+        let loaded = self.assets.update(&self.device, &self.queue);
+        self.rebind_loaded_textures(&loaded);
+
+        self.assets.free_unused();
+        self.prepare_mesh_pipelines();
+        self.scene.update_world_matrices();
+    }
+
+    /// Blocks until every texture loading in the background is ready, e.g. before taking a screenshot.
+    pub fn finish_loading(&mut self) {
+
+        let loaded = self.assets.wait_for_pending(&self.device, &self.queue);
+        self.rebind_loaded_textures(&loaded);
+        self.prepare_mesh_pipelines();
+    }
+
+    /// Bind groups of textures swapped in by the asset server are created again on the next `prepare_scene`.
+    fn rebind_loaded_textures(&mut self, loaded: &[AssetId]) {
+
+        for id in loaded {
+            self.texture_bind_groups.remove(id);
+        }
+    }
+
+    /// Uploads `data` and adds a root node drawing it with the default material.
+    pub fn add_mesh(&mut self, data: MeshData) -> Handle<Mesh> {
+
+        let name = data.name.clone();
+        let handle = self.assets.meshes.add(Mesh::new(data, &self.device));
+        self.scene.add(Node::new(name).with_mesh(handle.clone()), None);
+        self.prepare_mesh_pipelines();
+
+        handle
+    }
+
+    /// Uploads the mesh of a generated shape, without adding it to the scene. Scene files store
+    /// the shape rather than the vertices of meshes added this way.
+    pub fn add_shape(&mut self, shape: ProceduralShape) -> Handle<Mesh> {
+
+        let handle = self.assets.meshes.add(Mesh::from_shape(shape, &self.device));
+        self.prepare_mesh_pipelines();
+
+        handle
+    }
+
+    /// Loads the model file at `path`, relative to the asset root, and adds it like `add_model`
+    /// below a node named after the file. The node remembers the path, for scene files.
+    pub fn load_model(&mut self, path: impl AsRef<Path>, parent: Option<NodeId>) -> CandleResult<NodeId> {
+
+        let path = self.assets.resolve(path);
+        let handle = self.assets.load_model(&path)?;
+        let model = self.assets.models.get(&handle).expect("Just loaded").clone();
+
+        let name = path.file_stem().unwrap_or_default().to_string_lossy();
+        let id = self.add_model(name, &model, parent);
+        self.scene.get_mut(id).expect("Just added").model = Some(self.assets.relative(&path).to_owned());
+
+        Ok(id)
+    }
+
+    /// Adds the node hierarchy of `model` below a new node called `name`, under `parent` or as a root.
+    /// Its meshes and the base color textures of its materials are uploaded, and the new node is returned.
+    pub fn add_model(&mut self, name: impl Into<String>, model: &Model, parent: Option<NodeId>) -> NodeId {
+
+        let meshes: Vec<Handle<Mesh>> = model
+            .meshes
+            .iter()
+            .map(|data| self.assets.meshes.add(Mesh::new(data.clone(), &self.device)))
+            .collect();
+
+        let mut textures = HashMap::new();
+        let materials: Vec<Handle<Material>> = model
+            .materials
+            .iter()
+            .map(|material| {
+                let texture = material.base_color_texture.and_then(|image| {
+                    let data = model.images.get(image)?;
+                    let texture = textures
+                        .entry(image)
+                        .or_insert_with(|| self.assets.textures.add(Texture::new(data.clone(), &self.device, &self.queue)));
+                    Some(texture.clone())
+                });
+
+                self.assets.materials.add(Material {
+                    name: material.name.clone(),
+                    base_color_factor: material.base_color_factor,
+                    base_color_texture: texture,
+                })
+            })
+            .collect();
+
+        // What glTF draws primitives without a material with
+        let plain = self.assets.materials.add(Material::default());
+
+        let name = name.into();
+        let root = self.scene.add(Node::new(name.clone()), parent);
+        let mut visited = vec![false; model.nodes.len()];
+        let mut stack: Vec<(usize, NodeId)> = model.root_nodes.iter().rev().map(|&index| (index, root)).collect();
+
+        while let Some((index, parent)) = stack.pop() {
+
+            // Broken files could list a node twice or in a cycle
+            let Some(model_node) = model.nodes.get(index).filter(|_| !std::mem::replace(&mut visited[index], true)) else {
+                log::warn!("Skipping node {index} of {name}, it's missing or already placed");
+                continue;
+            };
+
+            let mut node = Node::new(model_node.name.clone()).with_transform(Transform {
+                translation: model_node.translation.into(),
+                rotation: glam::Quat::from_array(model_node.rotation),
+                scale: model_node.scale.into(),
+            });
+
+            if let Some(mesh) = model_node.mesh.filter(|&mesh| mesh < meshes.len()) {
+                node.mesh = Some(meshes[mesh].clone());
+                node.materials = model.meshes[mesh]
+                    .primitives
+                    .iter()
+                    .map(|primitive| primitive.material.and_then(|material| materials.get(material)).unwrap_or(&plain).clone())
+                    .collect();
+            }
+
+            let id = self.scene.add(node, Some(parent));
+            stack.extend(model_node.children.iter().rev().map(|&child| (child, id)));
+        }
+
+        self.prepare_mesh_pipelines();
+
+        root
+    }
+
+    /// World space bounds of everything drawn, `None` when there's nothing with vertices.
+    pub fn bounds(&self) -> Option<Aabb> {
+        self.scene.bounds(&self.assets.meshes)
+    }
+
+    /// The main pipeline, with the vertex layouts of `mesh`.
+    pub fn pipeline_key_for(&self, mesh: &Mesh) -> PipelineKey {
+
+        PipelineKey {
+            vertex_layouts: mesh.vertex_layouts.clone(),
+            ..self.main_pipeline_key.clone()
+        }
+    }
+
+    /// Builds pipelines for meshes with vertex layouts that weren't drawn before, and remembers which
+    /// pipeline every mesh in the scene uses.
+    fn prepare_mesh_pipelines(&mut self) {
+
+        let meshes: HashSet<AssetId> = self
+            .scene
+            .iter_depth_first()
+            .filter_map(|(_, node)| node.mesh.as_ref())
+            .map(Handle::id)
+            .collect();
+
+        self.mesh_pipelines.retain(|id, _| meshes.contains(id));
+
+        for (_, node) in self.scene.iter_depth_first() {
+
+            let Some(handle) = node.mesh.as_ref().filter(|handle| !self.mesh_pipelines.contains_key(&handle.id())) else {
+                continue;
+            };
+            let Some(mesh) = self.assets.meshes.get(handle) else {
+                continue;
+            };
+
+            let key = self.pipeline_key_for(mesh);
+            let pipeline = self.pipeline_cache.prepare(&self.device, &key);
+            self.mesh_pipelines.insert(handle.id(), pipeline);
+        }
+    }
+
+    /// Drops the main shader's pipelines that neither the main key nor a mesh in the scene uses.
+    fn evict_unused_pipelines(&mut self) {
+
+        let mut used: HashSet<PipelineKey> = self
+            .scene
+            .iter_depth_first()
+            .filter_map(|(_, node)| self.assets.meshes.get(node.mesh.as_ref()?))
+            .map(|mesh| self.pipeline_key_for(mesh))
+            .collect();
+        used.insert(self.main_pipeline_key.clone());
+
+        let shader = self.main_pipeline_key.shader;
+        self.pipeline_cache.retain(|key| key.shader != shader || used.contains(key));
+    }
+
+    /// Updates the scene's world matrices and collects what `render` draws: every sub-mesh of every node
+    /// with a mesh. Has to be called after the scene changes and before rendering, `render_offscreen` does.
+    pub fn prepare_scene(&mut self) {
+
+        self.scene.update_world_matrices();
+        self.prepare_mesh_pipelines();
+        self.draws.clear();
+
+        let default_material = self
+            .assets
+            .materials
+            .get(&self.default_material)
+            .expect("The renderer holds a handle to the default material!");
+
+        let mut objects = Vec::new();
+
+        for (_, node) in self.scene.iter_depth_first() {
+
+            let Some(handle) = node.mesh.as_ref() else {
+                continue;
+            };
+            let Some(mesh) = self.assets.meshes.get(handle) else {
+                continue;
+            };
+            let Some(&Some(pipeline)) = self.mesh_pipelines.get(&handle.id()) else {
+                continue;
+            };
+
+            for submesh in 0..mesh.submeshes.len() {
+
+                let material = node
+                    .materials
+                    .get(submesh)
+                    .and_then(|material| self.assets.materials.get(material))
+                    .unwrap_or(default_material);
+
+                let texture_handle = material.base_color_texture.as_ref().unwrap_or(&self.white_texture);
+                let Some(texture) = self.assets.textures.get(texture_handle) else {
+                    continue;
+                };
+
+                self.texture_bind_groups
+                    .entry(texture_handle.id())
+                    .or_insert_with(|| texture.create_bind_group(&self.device, &self.texture_bind_group_layout));
+
+                self.draws.push(Draw {
+                    mesh: handle.clone(),
+                    submesh,
+                    pipeline,
+                    texture: texture_handle.id(),
+                    object: objects.len(),
+                });
+                objects.push(ObjectUniform {
+                    model: node.world_matrix().to_cols_array_2d(),
+                    base_color: material.base_color_factor,
+                });
+            }
+        }
+
+        // Bind groups keep their texture alive on the GPU, so unused ones are dropped
+        let used: HashSet<AssetId> = self.draws.iter().map(|draw| draw.texture).collect();
+        self.texture_bind_groups.retain(|id, _| used.contains(id));
+
+        self.object_buffer.write(&self.device, &self.queue, &objects);
+    }
+
+    /// Whether the device reported itself lost, after which nothing rendered with it will show up.
+    pub fn is_device_lost(&self) -> bool {
+        self.device_lost.load(Ordering::Acquire)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn build(
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        surface_config: wgpu::SurfaceConfiguration,
+        surface: Option<wgpu::Surface<'static>>,
+        offscreen_target: Option<OffscreenTarget>,
+        mut assets: AssetServer,
+        cpu_resources: CpuResources,
+        force_fallback_adapter: bool,
+    ) -> CandleResult<Self> {
+
+        let device_lost = Arc::new(AtomicBool::new(false));
+        {
+            let device_lost = device_lost.clone();
+            device.set_device_lost_callback(move |reason, message| {
+                // Dropping the device or replacing the callback also ends up here, neither is a real loss
+                if matches!(reason, wgpu::DeviceLostReason::Unknown | wgpu::DeviceLostReason::Destroyed) {
+                    log::error!("wgpu device lost ({reason:?}): {message}");
+                    device_lost.store(true, Ordering::Release);
+                }
+            });
+        }
+
+        // Assets surviving a device loss are uploaded again, their handles stay valid
+        assets.recreate_gpu_resources(&device, &queue);
+
+        let texture_bind_group_layout = Texture::create_bind_group_layout(&device);
+
+        let diffuse_texture = assets.load_texture_async(&cpu_resources.diffuse_texture_path, &device, &queue)?;
+        let white_texture = assets.textures.add(Texture::new(TextureData::solid("White", [255; 4]), &device, &queue));
+        let default_material = assets.materials.add(Material {
+            name: "Default".to_owned(),
+            base_color_texture: Some(diffuse_texture.clone()),
+            ..Default::default()
+        });
+
+        let default_mesh = assets.meshes.add(Mesh::new(cpu_resources.default_mesh.clone(), &device));
+        let mut scene = Scene::new();
+        scene.add(Node::new(cpu_resources.default_mesh.name.clone()).with_mesh(default_mesh), None);
+
+        let mut camera = Camera::default();
+        camera.set_viewport_size(surface_config.width, surface_config.height);
+        let camera_buffer = CameraBuffer::new(&device, &camera);
+        let object_buffer = ObjectBuffer::new(&device);
 
         let render_pipeline_layout = 
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[&diffuse_texture.bind_group_layout.unwrap()],
+                bind_group_layouts: &[
+                    &camera_buffer.bind_group_layout,
+                    &texture_bind_group_layout,
+                    &object_buffer.bind_group_layout,
+                ],
                 push_constant_ranges: &[]
             });
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vertex",
-                buffers: &[Vertex::get_buffer_layout()],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fragment",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: surface_config.format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false
-            },
-            multiview: None,
-            cache: None
-        });
+        let mut pipeline_cache = PipelineCache::new();
+        let main_shader = pipeline_cache.add_shader(&device, "Shader", &cpu_resources.shader_source, render_pipeline_layout);
 
-        Self {
+        let depth_texture = DepthTexture::new(&device, surface_config.width, surface_config.height, DepthTexture::DEFAULT_FORMAT);
+        let main_pipeline_key = PipelineKey::new(main_shader, Vertex::layout(), surface_config.format)
+            .with_depth(depth_texture.format);
+        pipeline_cache.prepare(&device, &main_pipeline_key);
+
+        Ok(Self {
             device,
             queue,
             surface,
+            offscreen_target,
+            depth_texture,
             surface_config,
-            render_pipeline,
-            vertex_buffer,
-            index_buffer,
-            amount_of_vertices,
-            diffuse_bind_group: diffuse_texture_bind_group,
-        }
+            pipeline_cache,
+            main_pipeline_key,
+            scene,
+            camera,
+            settings: RendererSettings::default(),
+            camera_buffer,
+            object_buffer,
+            texture_bind_group_layout,
+            diffuse_texture,
+            default_material,
+            white_texture,
+            transient_pool: TransientTexturePool::new(),
+            draws: Vec::new(),
+            texture_bind_groups: HashMap::new(),
+            mesh_pipelines: HashMap::new(),
+            cpu_resources,
+            force_fallback_adapter,
+            device_lost,
+            assets,
+            shader_watcher: None,
+            shader_error: None,
+        })
     }
 
     pub fn resize_surface(&mut self, width: u32, height: u32) {
         self.surface_config.width = width;
         self.surface_config.height = height;
-        self.surface.configure(&self.device, &self.surface_config);
+        self.camera.set_viewport_size(width, height);
+
+        if let Some(surface) = self.surface.as_ref() {
+            surface.configure(&self.device, &self.surface_config);
+        }
+
+        if self.offscreen_target.is_some() {
+            self.offscreen_target = Some(OffscreenTarget::new(&self.device, &self.surface_config));
+        }
+
+        self.depth_texture = DepthTexture::new(&self.device, width, height, self.depth_texture.format);
     }
 
-    pub fn render(&mut self, encoder: &mut CommandEncoder, surface_view: &TextureView) {
+    /// Switches the depth buffer to `format`, e.g. to one with stencil, and rebuilds the pipelines for it.
+    /// `App` rebuilds the GUI renderer to match before drawing it. Panics if `format` has no depth aspect.
+    pub fn set_depth_format(&mut self, format: wgpu::TextureFormat) {
+
+        let (width, height) = (self.surface_config.width, self.surface_config.height);
+        self.depth_texture = DepthTexture::new(&self.device, width, height, format);
+        self.main_pipeline_key = self.main_pipeline_key.clone().with_depth(format);
+        self.mesh_pipelines.clear();
+        self.draws.clear();
+        self.evict_unused_pipelines();
+
+        self.pipeline_cache.prepare(&self.device, &self.main_pipeline_key);
+        self.prepare_mesh_pipelines();
+    }
+
+    /// Renders a frame into the offscreen target and submits it.
+    pub fn render_offscreen(&mut self) -> CandleResult<()> {
+
+        self.prepare_scene();
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Offscreen Encoder") });
+
+        // The pool is taken out for the duration of the frame, the passes borrow the rest of the renderer
+        let mut transient_pool = std::mem::take(&mut self.transient_pool);
+        let result = {
+            let offscreen_target = self
+                .offscreen_target
+                .as_ref()
+                .expect("render_offscreen requires a headless renderer!");
+
+            let mut graph = RenderGraph::new();
+            self.add_passes(&mut graph);
+
+            graph.execute(
+                &self.device,
+                &mut encoder,
+                &offscreen_target.view,
+                (self.surface_config.width, self.surface_config.height),
+                &mut transient_pool,
+            )
+        };
+        self.transient_pool = transient_pool;
+        result?;
+
+        self.queue.submit(Some(encoder.finish()));
+
+        Ok(())
+    }
+
+    /// Registers the renderer's own passes, which end up drawing into `render_graph::OUTPUT`.
+    pub fn add_passes<'a>(&'a self, graph: &mut RenderGraph<'a>) {
+
+        graph.add_pass("Main", &[], &[render_graph::OUTPUT], |encoder, resources| {
+            self.render(encoder, resources.view(render_graph::OUTPUT));
+        });
+    }
+
+    /// Reads the offscreen target back as tightly packed RGBA8 pixels.
+    pub fn read_pixels(&self) -> Vec<u8> {
+
+        self.offscreen_target
+            .as_ref()
+            .expect("read_pixels requires a headless renderer!")
+            .read_pixels(&self.device, &self.queue)
+    }
+
+    /// Draws what the last `prepare_scene` collected.
+    pub fn render(&self, encoder: &mut CommandEncoder, surface_view: &TextureView) {
         let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("Render Pass"),
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
@@ -141,25 +719,37 @@ impl MainRenderer {
                 resolve_target: None,
                 ops: wgpu::Operations { 
                     load: wgpu::LoadOp::Clear(wgpu::Color {
-                        r: 0.5,
-                        g: 0.5,
-                        b: 0.5,
-                        a: 1.0 
+                        r: self.settings.clear_color[0] as f64,
+                        g: self.settings.clear_color[1] as f64,
+                        b: self.settings.clear_color[2] as f64,
+                        a: self.settings.clear_color[3] as f64,
                     }),
                     store: wgpu::StoreOp::Store
                 },
             })],
-            depth_stencil_attachment: None,
+            depth_stencil_attachment: Some(self.depth_texture.clear_attachment()),
             occlusion_query_set: None,
             timestamp_writes: None
         });
 
-        render_pass.set_pipeline(&self.render_pipeline);
-        render_pass.set_bind_group(0, &self.diffuse_bind_group, &[]);
-        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        self.camera_buffer.write(&self.queue, &self.camera);
+
+        render_pass.set_bind_group(0, &self.camera_buffer.bind_group, &[]);
+
+        for draw in &self.draws {
 
-        render_pass.draw_indexed(0..self.amount_of_vertices, 0, 0..1);
-           
+            let Some(mesh) = self.assets.meshes.get(&draw.mesh) else {
+                continue;
+            };
+            let Some(texture_bind_group) = self.texture_bind_groups.get(&draw.texture) else {
+                continue;
+            };
+
+            render_pass.set_pipeline(self.pipeline_cache.pipeline(draw.pipeline));
+            render_pass.set_bind_group(1, texture_bind_group, &[]);
+            render_pass.set_bind_group(2, &self.object_buffer.bind_group, &[self.object_buffer.offset(draw.object)]);
+            mesh.set_buffers(&mut render_pass);
+            mesh.draw_submesh(&mut render_pass, draw.submesh);
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,130 @@
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use super::asset_server::AssetId;
+use super::texture::TextureData;
+use crate::error::CandleResult;
+
+struct LoadJob {
+    id: AssetId,
+    path: PathBuf,
+}
+
+pub struct LoadedTexture {
+    pub id: AssetId,
+    pub path: PathBuf,
+    pub data: CandleResult<TextureData>,
+}
+
+/// Reads and decodes textures on a small pool of worker threads.
+/// The workers exit once the loader is dropped and their current job is done.
+pub struct AssetLoader {
+    jobs: Sender<LoadJob>,
+    results: Receiver<LoadedTexture>,
+    pending: usize,
+}
+
+impl AssetLoader {
+
+    const MAX_WORKERS: usize = 4;
+
+    pub fn new() -> Self {
+
+        let (job_sender, job_receiver) = mpsc::channel::<LoadJob>();
+        let (result_sender, result_receiver) = mpsc::channel();
+
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        let worker_count = thread::available_parallelism()
+            .map(|count| count.get())
+            .unwrap_or(1)
+            .min(Self::MAX_WORKERS);
+
+        for index in 0..worker_count {
+
+            let job_receiver = job_receiver.clone();
+            let result_sender = result_sender.clone();
+
+            let spawned = thread::Builder::new()
+                .name(format!("Candle asset loader {index}"))
+                .spawn(move || Self::work(job_receiver, result_sender));
+
+            if let Err(error) = spawned {
+                log::error!("Failed to spawn an asset loader thread: {error}");
+            }
+        }
+
+        Self {
+            jobs: job_sender,
+            results: result_receiver,
+            pending: 0,
+        }
+    }
+
+    fn work(jobs: Arc<Mutex<Receiver<LoadJob>>>, results: Sender<LoadedTexture>) {
+
+        loop {
+            // The lock is only held while waiting for the next job, not while decoding it
+            let job = match jobs.lock() {
+                Ok(jobs) => jobs.recv(),
+                Err(_) => return,
+            };
+
+            let Ok(LoadJob { id, path }) = job else {
+                return;
+            };
+
+            let data = TextureData::load(&path);
+
+            if results.send(LoadedTexture { id, path, data }).is_err() {
+                return;
+            }
+        }
+    }
+
+    pub fn load_texture(&mut self, id: AssetId, path: PathBuf) {
+
+        match self.jobs.send(LoadJob { id, path }) {
+            Ok(()) => self.pending += 1,
+            Err(error) => log::error!("Asset loader threads are gone, can't load {}", error.0.path.display()),
+        }
+    }
+
+    /// Textures finished since the last call, without waiting for the rest.
+    pub fn finished(&mut self) -> Vec<LoadedTexture> {
+
+        let finished: Vec<LoadedTexture> = self.results.try_iter().collect();
+        self.pending -= finished.len();
+
+        finished
+    }
+
+    /// Blocks until every requested texture is loaded.
+    pub fn wait_for_all(&mut self) -> Vec<LoadedTexture> {
+
+        let mut finished = Vec::with_capacity(self.pending);
+
+        while self.pending > 0 {
+            match self.results.recv() {
+                Ok(loaded) => {
+                    finished.push(loaded);
+                    self.pending -= 1;
+                }
+                Err(_) => break,
+            }
+        }
+
+        finished
+    }
+
+    pub fn pending(&self) -> usize {
+        self.pending
+    }
+}
+
+impl Default for AssetLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
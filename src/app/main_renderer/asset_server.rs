@@ -0,0 +1,393 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Weak};
+
+use egui_wgpu::wgpu::{Device, Queue};
+
+use super::asset_loader::{AssetLoader, LoadedTexture};
+use super::material::Material;
+use super::mesh::Mesh;
+use super::model::Model;
+use super::texture::{Texture, TextureData};
+use crate::error::CandleResult;
+
+/// Overrides where assets are loaded from at runtime.
+pub const ASSET_ROOT_ENV: &str = "CANDLE_ASSET_ROOT";
+
+/// The resources in the source tree, which only exist where the crate was built.
+#[cfg(debug_assertions)]
+const SOURCE_RESOURCES: Option<&str> = Some(concat!(env!("CARGO_MANIFEST_DIR"), "/src/resources"));
+#[cfg(not(debug_assertions))]
+const SOURCE_RESOURCES: Option<&str> = None;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AssetId(u64);
+
+/// Reference-counted, typed reference to an asset owned by an `AssetServer`.
+/// The asset is freed by `AssetServer::free_unused` once every handle to it is dropped.
+pub struct Handle<T> {
+    id: AssetId,
+    alive: Arc<()>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    pub fn id(&self) -> AssetId {
+        self.id
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            id: self.id,
+            alive: self.alive.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Handle<{}>({})", std::any::type_name::<T>(), self.id.0)
+    }
+}
+
+struct AssetEntry<T> {
+    asset: T,
+    path: Option<PathBuf>,
+    alive: Weak<()>,
+}
+
+/// Storage for one type of asset, deduplicated by path.
+pub struct Assets<T> {
+    entries: HashMap<AssetId, AssetEntry<T>>,
+    by_path: HashMap<PathBuf, AssetId>,
+    next_id: u64,
+}
+
+impl<T> Default for Assets<T> {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+            by_path: HashMap::new(),
+            next_id: 0,
+        }
+    }
+}
+
+impl<T> Assets<T> {
+
+    /// Adds an asset that wasn't loaded from a file, e.g. a generated one.
+    pub fn add(&mut self, asset: T) -> Handle<T> {
+        self.insert(asset, None)
+    }
+
+    pub fn get(&self, handle: &Handle<T>) -> Option<&T> {
+        self.entries.get(&handle.id).map(|entry| &entry.asset)
+    }
+
+    pub fn get_mut(&mut self, handle: &Handle<T>) -> Option<&mut T> {
+        self.entries.get_mut(&handle.id).map(|entry| &mut entry.asset)
+    }
+
+    /// Returns a new handle to the asset loaded from `path`, if it's still alive.
+    pub fn find_by_path(&self, path: &Path) -> Option<Handle<T>> {
+
+        let id = *self.by_path.get(path)?;
+        let alive = self.entries.get(&id)?.alive.upgrade()?;
+
+        Some(Handle {
+            id,
+            alive,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Where the asset was loaded from, `None` if it was added directly.
+    pub fn path(&self, handle: &Handle<T>) -> Option<&Path> {
+        self.entries.get(&handle.id)?.path.as_deref()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.entries.values_mut().map(|entry| &mut entry.asset)
+    }
+
+    /// Drops every asset without handles left and returns how many were freed.
+    pub fn free_unused(&mut self) -> usize {
+
+        let unused: Vec<AssetId> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.alive.strong_count() == 0)
+            .map(|(id, _)| *id)
+            .collect();
+
+        for id in &unused {
+            // The path may already point to a newer copy, loaded after the last handle to this one was dropped
+            if let Some(AssetEntry { path: Some(path), .. }) = self.entries.remove(id)
+                && self.by_path.get(&path) == Some(id)
+            {
+                self.by_path.remove(&path);
+            }
+        }
+
+        unused.len()
+    }
+
+    fn insert(&mut self, asset: T, path: Option<PathBuf>) -> Handle<T> {
+
+        let id = AssetId(self.next_id);
+        self.next_id += 1;
+
+        let alive = Arc::new(());
+
+        if let Some(path) = &path {
+            self.by_path.insert(path.clone(), id);
+        }
+
+        self.entries.insert(id, AssetEntry {
+            asset,
+            path,
+            alive: Arc::downgrade(&alive),
+        });
+
+        Handle {
+            id,
+            alive,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// Loads assets relative to a runtime asset root and hands out handles to them.
+/// Loading the same path twice returns the same asset while any handle to it is alive.
+pub struct AssetServer {
+    root: PathBuf,
+    pub textures: Assets<Texture>,
+    pub models: Assets<Model>,
+    pub meshes: Assets<Mesh>,
+    pub materials: Assets<Material>,
+    loader: AssetLoader,
+    placeholder: Option<TextureData>,
+}
+
+impl Default for AssetServer {
+    fn default() -> Self {
+        Self::new(Self::default_root())
+    }
+}
+
+impl AssetServer {
+
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+
+        Self {
+            root: root.into(),
+            textures: Assets::default(),
+            models: Assets::default(),
+            meshes: Assets::default(),
+            materials: Assets::default(),
+            loader: AssetLoader::new(),
+            placeholder: None,
+        }
+    }
+
+    /// `$CANDLE_ASSET_ROOT` if set, otherwise a `resources` directory next to the executable
+    /// or in the working directory. Debug builds fall back to the one in the source tree.
+    pub fn default_root() -> PathBuf {
+
+        if let Some(root) = std::env::var_os(ASSET_ROOT_ENV) {
+            return PathBuf::from(root);
+        }
+
+        let next_to_executable = std::env::current_exe()
+            .ok()
+            .and_then(|executable| executable.parent().map(|dir| dir.join("resources")));
+
+        next_to_executable
+            .into_iter()
+            .chain(std::env::current_dir().ok().map(|dir| dir.join("resources")))
+            .chain(SOURCE_RESOURCES.map(PathBuf::from))
+            .find(|dir| dir.is_dir())
+            .unwrap_or_else(|| PathBuf::from("resources"))
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Resolves `path` against the asset root, unless it's absolute.
+    pub fn resolve(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.root.join(path)
+    }
+
+    /// The inverse of `resolve`: `path` relative to the asset root, or unchanged if it's outside of it.
+    pub fn relative<'a>(&self, path: &'a Path) -> &'a Path {
+        path.strip_prefix(&self.root).unwrap_or(path)
+    }
+
+    pub fn load_texture(&mut self, path: impl AsRef<Path>, device: &Device, queue: &Queue) -> CandleResult<Handle<Texture>> {
+
+        let path = self.resolve(path);
+
+        if let Some(handle) = self.textures.find_by_path(&path) {
+            return Ok(handle);
+        }
+
+        let data = TextureData::load(&path)?;
+        let texture = Texture::new(data, device, queue);
+
+        Ok(self.textures.insert(texture, Some(path)))
+    }
+
+    /// Loads a `.gltf`, `.glb` or `.obj` model. Its meshes and images stay on the CPU until something uploads them.
+    pub fn load_model(&mut self, path: impl AsRef<Path>) -> CandleResult<Handle<Model>> {
+
+        let path = self.resolve(path);
+
+        if let Some(handle) = self.models.find_by_path(&path) {
+            return Ok(handle);
+        }
+
+        let model = Model::load(&path)?;
+
+        Ok(self.models.insert(model, Some(path)))
+    }
+
+    /// Returns right away with a handle to the built-in checker texture, while the real one is read and
+    /// decoded on a worker thread. It's swapped in by `update` once ready, so bind groups using the handle
+    /// have to be recreated for the ids `update` returns. If loading fails, the placeholder stays.
+    pub fn load_texture_async(&mut self, path: impl AsRef<Path>, device: &Device, queue: &Queue) -> CandleResult<Handle<Texture>> {
+
+        let path = self.resolve(path);
+
+        if let Some(handle) = self.textures.find_by_path(&path) {
+            return Ok(handle);
+        }
+
+        let placeholder = match &self.placeholder {
+            Some(placeholder) => placeholder,
+            None => self.placeholder.insert(TextureData::placeholder()?),
+        };
+
+        let mut placeholder = placeholder.clone();
+        placeholder.name = path
+            .file_stem()
+            .map(|stem| format!("{} (loading)", stem.to_string_lossy()))
+            .unwrap_or_default();
+
+        let handle = self.textures.insert(Texture::new(placeholder, device, queue), Some(path.clone()));
+        self.loader.load_texture(handle.id(), path);
+
+        Ok(handle)
+    }
+
+    /// Uploads textures that finished loading in the background and returns their ids.
+    pub fn update(&mut self, device: &Device, queue: &Queue) -> Vec<AssetId> {
+        let finished = self.loader.finished();
+        self.swap_in_loaded(finished, device, queue)
+    }
+
+    /// Like `update`, but blocks until every background load is done.
+    pub fn wait_for_pending(&mut self, device: &Device, queue: &Queue) -> Vec<AssetId> {
+        let finished = self.loader.wait_for_all();
+        self.swap_in_loaded(finished, device, queue)
+    }
+
+    /// How many textures are still loading in the background.
+    pub fn pending_loads(&self) -> usize {
+        self.loader.pending()
+    }
+
+    fn swap_in_loaded(
+        &mut self,
+        finished: Vec<LoadedTexture>,
+        device: &Device,
+        queue: &Queue,
+    ) -> Vec<AssetId> {
+
+        let mut updated = Vec::with_capacity(finished.len());
+
+        for loaded in finished {
+
+            // Every handle was dropped while it was loading
+            let Some(entry) = self.textures.entries.get_mut(&loaded.id) else {
+                continue;
+            };
+
+            match loaded.data {
+                Ok(data) => {
+                    log::debug!("Loaded {} in the background", loaded.path.display());
+                    entry.asset = Texture::new(data, device, queue);
+                    updated.push(loaded.id);
+                }
+                Err(error) => log::error!("{error}, keeping the placeholder texture"),
+            }
+        }
+
+        updated
+    }
+
+    /// Uploads every asset again, e.g. on a new device after the old one was lost. Handles stay valid.
+    pub fn recreate_gpu_resources(&mut self, device: &Device, queue: &Queue) {
+
+        for texture in self.textures.iter_mut() {
+            texture.recreate(device, queue);
+        }
+
+        for mesh in self.meshes.iter_mut() {
+            mesh.recreate(device);
+        }
+    }
+
+    /// Frees every asset without handles left.
+    pub fn free_unused(&mut self) {
+
+        // Materials first, they hold handles to textures
+        let freed = self.materials.free_unused();
+        if freed > 0 {
+            log::debug!("Freed {freed} unused materials");
+        }
+
+        let freed = self.textures.free_unused();
+        if freed > 0 {
+            log::debug!("Freed {freed} unused textures");
+        }
+
+        let freed = self.meshes.free_unused();
+        if freed > 0 {
+            log::debug!("Freed {freed} unused meshes");
+        }
+
+        let freed = self.models.free_unused();
+        if freed > 0 {
+            log::debug!("Freed {freed} unused models");
+        }
+    }
+}
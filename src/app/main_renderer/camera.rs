@@ -0,0 +1,216 @@
+use egui_wgpu::wgpu::{self, util::DeviceExt, BindGroup, BindGroupLayout, Buffer, Device, Queue};
+use glam::{Mat3, Mat4, Quat, Vec3};
+use serde::{Deserialize, Serialize};
+
+use super::mesh::Aabb;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Projection {
+    #[default]
+    Perspective,
+    /// Parallel projection without foreshortening, for technical views.
+    Orthographic,
+}
+
+/// Camera looking down its local -Z axis with +Y up like in glTF.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Camera {
+    pub position: Vec3,
+    pub orientation: Quat,
+    pub projection: Projection,
+    /// Vertical field of view in radians, when perspective.
+    pub fov_y: f32,
+    /// Height of the view in world units, when orthographic.
+    pub ortho_height: f32,
+    pub near: f32,
+    pub far: f32,
+    /// Width divided by height, kept in sync with the surface by `MainRenderer::resize_surface`.
+    /// Not saved, it always follows the window.
+    #[serde(skip)]
+    pub aspect: f32,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            position: Vec3::new(0.0, 0.0, 2.0),
+            orientation: Quat::IDENTITY,
+            projection: Projection::Perspective,
+            fov_y: 45_f32.to_radians(),
+            ortho_height: 2.0,
+            near: 0.1,
+            far: 100.0,
+            aspect: 1.0,
+        }
+    }
+}
+
+impl Camera {
+
+    pub fn forward(&self) -> Vec3 {
+        self.orientation * Vec3::NEG_Z
+    }
+
+    pub fn right(&self) -> Vec3 {
+        self.orientation * Vec3::X
+    }
+
+    pub fn up(&self) -> Vec3 {
+        self.orientation * Vec3::Y
+    }
+
+    /// Turns the camera towards `target`, keeping the horizon level.
+    pub fn look_at(&mut self, target: Vec3) {
+
+        let Some(forward) = (target - self.position).try_normalize() else {
+            return;
+        };
+
+        // Looking straight up or down, any horizontal right vector works
+        let right = forward.cross(Vec3::Y).try_normalize().unwrap_or_else(|| self.right());
+        let up = right.cross(forward);
+
+        self.orientation = Quat::from_mat3(&Mat3::from_cols(right, up, -forward)).normalize();
+    }
+
+    /// Sets `aspect` from the size of the render target, ignoring sizes of 0 like those of minimized windows.
+    pub fn set_viewport_size(&mut self, width: u32, height: u32) {
+
+        if width > 0 && height > 0 {
+            self.aspect = width as f32 / height as f32;
+        }
+    }
+
+    /// Moves the camera along its view direction until all of `aabb` is in view and centered,
+    /// and sizes the view to fit when orthographic. Returns the new distance to the center of the box.
+    pub fn frame(&mut self, aabb: &Aabb) -> f32 {
+
+        let center = Vec3::from(aabb.center());
+        // Bounding sphere, so the result doesn't depend on which side the box is seen from
+        let radius = (Vec3::from(aabb.size()).length() / 2.0).max(self.near);
+
+        let half_fov_x = ((self.fov_y / 2.0).tan() * self.aspect).atan();
+        let distance = radius / (self.fov_y / 2.0).min(half_fov_x).sin();
+
+        if self.projection == Projection::Orthographic {
+            self.ortho_height = 2.0 * radius * (1.0 / self.aspect).max(1.0);
+        }
+
+        self.position = center - self.forward() * distance;
+        distance
+    }
+
+    /// Switches the projection, keeping things `focus_distance` ahead the same size on screen.
+    pub fn set_projection(&mut self, projection: Projection, focus_distance: f32) {
+
+        if projection == Projection::Orthographic && self.projection == Projection::Perspective {
+            self.ortho_height = 2.0 * focus_distance * (self.fov_y / 2.0).tan();
+        }
+
+        self.projection = projection;
+    }
+
+    /// Blends towards `other`, rotating along the shortest arc. The projection switches halfway.
+    pub fn lerp(&self, other: &Camera, t: f32) -> Camera {
+
+        Camera {
+            position: self.position.lerp(other.position, t),
+            orientation: self.orientation.slerp(other.orientation, t),
+            projection: if t < 0.5 { self.projection } else { other.projection },
+            fov_y: self.fov_y + (other.fov_y - self.fov_y) * t,
+            ortho_height: self.ortho_height + (other.ortho_height - self.ortho_height) * t,
+            near: self.near + (other.near - self.near) * t,
+            far: self.far + (other.far - self.far) * t,
+            aspect: self.aspect + (other.aspect - self.aspect) * t,
+        }
+    }
+
+    /// World to view space.
+    pub fn view_matrix(&self) -> Mat4 {
+        Mat4::from_quat(self.orientation.conjugate()) * Mat4::from_translation(-self.position)
+    }
+
+    /// View to clip space, with depth from 0 at `near` to 1 at `far` like wgpu expects.
+    pub fn projection_matrix(&self) -> Mat4 {
+
+        match self.projection {
+            Projection::Perspective => Mat4::perspective_rh(self.fov_y, self.aspect, self.near, self.far),
+            Projection::Orthographic => {
+                let (half_width, half_height) = (self.ortho_height * self.aspect / 2.0, self.ortho_height / 2.0);
+                Mat4::orthographic_rh(-half_width, half_width, -half_height, half_height, self.near, self.far)
+            }
+        }
+    }
+
+    pub fn view_projection_matrix(&self) -> Mat4 {
+        self.projection_matrix() * self.view_matrix()
+    }
+}
+
+/// Layout of the `Camera` struct in `shader.wgsl`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct CameraUniform {
+    pub view_projection: [[f32; 4]; 4],
+    /// World space, w is padding.
+    pub position: [f32; 4],
+}
+
+impl From<&Camera> for CameraUniform {
+    fn from(camera: &Camera) -> Self {
+        Self {
+            view_projection: camera.view_projection_matrix().to_cols_array_2d(),
+            position: camera.position.extend(1.0).to_array(),
+        }
+    }
+}
+
+/// Uniform buffer holding the `CameraUniform`, bound to group 0 of the main shader.
+pub struct CameraBuffer {
+    pub buffer: Buffer,
+    pub bind_group_layout: BindGroupLayout,
+    pub bind_group: BindGroup,
+}
+
+impl CameraBuffer {
+
+    pub fn new(device: &Device, camera: &Camera) -> Self {
+
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera buffer"),
+            contents: bytemuck::bytes_of(&CameraUniform::from(camera)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+            label: Some("camera_bind_group_layout"),
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Camera bind group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+
+        Self { buffer, bind_group_layout, bind_group }
+    }
+
+    /// Uploads `camera`, the write lands before the next submitted command buffer runs.
+    pub fn write(&self, queue: &Queue, camera: &Camera) {
+        queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&CameraUniform::from(camera)));
+    }
+}
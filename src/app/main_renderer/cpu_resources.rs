@@ -0,0 +1,43 @@
+use std::path::PathBuf;
+
+use super::model::{MeshData, PrimitiveData};
+use super::shader_preprocessor::ShaderPreprocessor;
+
+use crate::error::CandleResult;
+
+/// CPU-side copy of everything `MainRenderer` uploads to the GPU.
+/// Kept around so pipelines, buffers and textures can be rebuilt after the device is lost.
+pub struct CpuResources {
+    /// Already preprocessed WGSL.
+    pub shader_source: String,
+    /// Added to `MainRenderer::scene` to start with.
+    pub default_mesh: MeshData,
+    /// Relative to the asset root.
+    pub diffuse_texture_path: PathBuf,
+}
+
+impl CpuResources {
+
+    /// The checker textured triangle.
+    pub fn load_default() -> CandleResult<Self> {
+
+        let default_mesh = MeshData {
+            name: "Triangle".to_owned(),
+            primitives: vec![PrimitiveData {
+                positions: vec![[0.0, 0.5, 0.0], [-0.5, -0.5, 0.0], [0.5, -0.5, 0.0]],
+                uvs: vec![[0.5, 0.0], [0.0, 1.0], [1.0, 1.0]],
+                indices: vec![0, 1, 2],
+                ..Default::default()
+            }],
+        };
+
+        let shader = ShaderPreprocessor::builtin().process("shader.wgsl")?;
+        shader.validate()?;
+
+        Ok(Self {
+            shader_source: shader.source,
+            default_mesh,
+            diffuse_texture_path: PathBuf::from("Checker.png"),
+        })
+    }
+}
@@ -0,0 +1,63 @@
+use egui_wgpu::wgpu::{self, Device, TextureView};
+
+/// Depth buffer matching the size of the color target, owned by the renderer and shared by every pass
+/// drawing into that target.
+pub struct DepthTexture {
+    pub texture: wgpu::Texture,
+    pub view: TextureView,
+    pub format: wgpu::TextureFormat,
+}
+
+impl DepthTexture {
+
+    /// Depth only. Use a format with stencil, e.g. `Depth24PlusStencil8`, for passes that need one.
+    pub const DEFAULT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+    /// Panics if `format` has no depth aspect.
+    pub fn new(device: &Device, width: u32, height: u32, format: wgpu::TextureFormat) -> Self {
+
+        assert!(format.has_depth_aspect(), "{format:?} isn't a depth format!");
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Depth Texture"),
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        Self { texture, view, format }
+    }
+
+    /// Clears depth to the far plane and the stencil, if any, to 0.
+    pub fn clear_attachment(&self) -> wgpu::RenderPassDepthStencilAttachment<'_> {
+        self.attachment(wgpu::LoadOp::Clear(1.0), wgpu::LoadOp::Clear(0))
+    }
+
+    /// Keeps what earlier passes drew.
+    pub fn load_attachment(&self) -> wgpu::RenderPassDepthStencilAttachment<'_> {
+        self.attachment(wgpu::LoadOp::Load, wgpu::LoadOp::Load)
+    }
+
+    fn attachment(&self, depth_load: wgpu::LoadOp<f32>, stencil_load: wgpu::LoadOp<u32>) -> wgpu::RenderPassDepthStencilAttachment<'_> {
+
+        // Ops for an aspect the format doesn't have fail validation
+        wgpu::RenderPassDepthStencilAttachment {
+            view: &self.view,
+            depth_ops: Some(wgpu::Operations { load: depth_load, store: wgpu::StoreOp::Store }),
+            stencil_ops: self
+                .format
+                .has_stencil_aspect()
+                .then_some(wgpu::Operations { load: stencil_load, store: wgpu::StoreOp::Store }),
+        }
+    }
+}
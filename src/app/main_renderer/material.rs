@@ -0,0 +1,23 @@
+use super::asset_server::Handle;
+use super::texture::Texture;
+
+/// How a surface looks. Only the base color is drawn so far, the rest of a glTF material
+/// stays in `MaterialData`.
+#[derive(Clone, Debug)]
+pub struct Material {
+    pub name: String,
+    /// Linear RGBA, multiplied with the texture.
+    pub base_color_factor: [f32; 4],
+    /// Plain white when `None`.
+    pub base_color_texture: Option<Handle<Texture>>,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            base_color_factor: [1.0; 4],
+            base_color_texture: None,
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use std::ops::Range;
+
+use egui_wgpu::wgpu::{self, util::DeviceExt, Buffer, Device, IndexFormat, RenderPass};
+use glam::{Mat4, Vec3};
+
+use super::model::MeshData;
+use super::shapes::{ProceduralShape, Shape};
+use super::vertex::{Vertex, VertexLayout, VertexStreams};
+
+/// Axis-aligned bounding box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+impl Aabb {
+
+    /// `None` when there are no points.
+    pub fn from_points(points: impl IntoIterator<Item = [f32; 3]>) -> Option<Self> {
+
+        let mut points = points.into_iter();
+        let first = points.next()?;
+
+        Some(points.fold(Self { min: first, max: first }, |aabb, point| aabb.including(point)))
+    }
+
+    pub fn including(self, point: [f32; 3]) -> Self {
+
+        Self {
+            min: [self.min[0].min(point[0]), self.min[1].min(point[1]), self.min[2].min(point[2])],
+            max: [self.max[0].max(point[0]), self.max[1].max(point[1]), self.max[2].max(point[2])],
+        }
+    }
+
+    pub fn union(self, other: Self) -> Self {
+        self.including(other.min).including(other.max)
+    }
+
+    pub fn center(&self) -> [f32; 3] {
+        [
+            (self.min[0] + self.max[0]) * 0.5,
+            (self.min[1] + self.max[1]) * 0.5,
+            (self.min[2] + self.max[2]) * 0.5,
+        ]
+    }
+
+    pub fn size(&self) -> [f32; 3] {
+        [self.max[0] - self.min[0], self.max[1] - self.min[1], self.max[2] - self.min[2]]
+    }
+
+    /// Box around all eight transformed corners, so it can be larger than the transformed contents.
+    pub fn transformed(&self, matrix: Mat4) -> Self {
+
+        let pick = |corner: usize, axis: usize| if corner & (1 << axis) == 0 { self.min[axis] } else { self.max[axis] };
+        let corners = (0..8).map(|corner| {
+            let point = Vec3::new(pick(corner, 0), pick(corner, 1), pick(corner, 2));
+            matrix.transform_point3(point).to_array()
+        });
+
+        Self::from_points(corners).expect("A box always has corners!")
+    }
+}
+
+/// Part of a mesh drawn with a single material, i.e. one glTF primitive.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubMesh {
+    /// Range in the mesh's index buffer.
+    pub indices: Range<u32>,
+    /// Added to every index, so each sub-mesh's indices can start at 0 and stay small.
+    pub base_vertex: i32,
+    pub material: Option<usize>,
+    pub aabb: Option<Aabb>,
+}
+
+/// Vertex and index buffers of every primitive of a `MeshData`, packed together.
+pub struct Mesh {
+    /// Kept so the buffers can be recreated at any time.
+    pub data: MeshData,
+    pub streams: VertexStreams,
+    /// Generated from `streams`, for the pipelines drawing this mesh.
+    pub vertex_layouts: Vec<VertexLayout>,
+    /// One per stream, in slot order.
+    pub vertex_buffers: Vec<Buffer>,
+    pub index_buffer: Buffer,
+    /// `Uint16` unless an index doesn't fit.
+    pub index_format: IndexFormat,
+    pub index_count: u32,
+    pub vertex_count: u32,
+    pub submeshes: Vec<SubMesh>,
+    /// `None` for meshes without vertices.
+    pub aabb: Option<Aabb>,
+    /// What generated `data`, so scene files can store that instead of the vertices.
+    pub shape: Option<ProceduralShape>,
+}
+
+impl Mesh {
+
+    /// Uses the standard interleaved `Vertex` layout.
+    pub fn new(data: MeshData, device: &Device) -> Self {
+        Self::with_streams(data, Vertex::streams(), device)
+    }
+
+    pub fn from_shape(shape: ProceduralShape, device: &Device) -> Self {
+        Self { shape: Some(shape), ..Self::new(shape.mesh(), device) }
+    }
+
+    pub fn with_streams(data: MeshData, streams: VertexStreams, device: &Device) -> Self {
+
+        let mut vertex_bytes: Vec<Vec<u8>> = vec![Vec::new(); streams.streams().len()];
+        let mut vertex_count = 0;
+        let mut indices: Vec<u32> = Vec::new();
+        let mut submeshes = Vec::with_capacity(data.primitives.len());
+
+        for primitive in &data.primitives {
+
+            let first_index = indices.len() as u32;
+            indices.extend(&primitive.indices);
+
+            submeshes.push(SubMesh {
+                indices: first_index..indices.len() as u32,
+                base_vertex: vertex_count as i32,
+                material: primitive.material,
+                aabb: Aabb::from_points(primitive.positions.iter().copied()),
+            });
+
+            for (bytes, attributes) in vertex_bytes.iter_mut().zip(streams.streams()) {
+                bytes.extend(primitive.vertex_bytes(attributes));
+            }
+            vertex_count += primitive.positions.len();
+        }
+
+        let aabb = submeshes.iter().filter_map(|submesh| submesh.aabb).reduce(Aabb::union);
+
+        // Indices are relative to their sub-mesh, so this usually holds even for large meshes
+        let index_format = match indices.iter().max() {
+            Some(&max) if max > u16::MAX as u32 => IndexFormat::Uint32,
+            _ => IndexFormat::Uint16,
+        };
+
+        let (vertex_buffers, index_buffer) = Self::upload(&data.name, &vertex_bytes, &indices, index_format, device);
+
+        Self {
+            data,
+            vertex_layouts: streams.layouts(),
+            streams,
+            vertex_buffers,
+            index_buffer,
+            index_format,
+            index_count: indices.len() as u32,
+            vertex_count: vertex_count as u32,
+            submeshes,
+            aabb,
+            shape: None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.data.name
+    }
+
+    /// Uploads the retained data again, e.g. on a new device after the old one was lost.
+    pub fn recreate(&mut self, device: &Device) {
+        *self = Self {
+            shape: self.shape,
+            ..Self::with_streams(std::mem::take(&mut self.data), self.streams.clone(), device)
+        };
+    }
+
+    fn upload(
+        name: &str,
+        vertex_bytes: &[Vec<u8>],
+        indices: &[u32],
+        index_format: IndexFormat,
+        device: &Device,
+    ) -> (Vec<Buffer>, Buffer) {
+
+        let vertex_buffers = vertex_bytes
+            .iter()
+            .enumerate()
+            .map(|(slot, bytes)| {
+                device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some(&format!("{name} vertex buffer {slot}")),
+                    contents: bytes,
+                    usage: wgpu::BufferUsages::VERTEX,
+                })
+            })
+            .collect();
+
+        let index_bytes: Vec<u8> = match index_format {
+            IndexFormat::Uint16 => {
+                let indices: Vec<u16> = indices.iter().map(|&index| index as u16).collect();
+                bytemuck::cast_slice(&indices).to_vec()
+            }
+            IndexFormat::Uint32 => bytemuck::cast_slice(indices).to_vec(),
+        };
+
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{name} index buffer")),
+            contents: &index_bytes,
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        (vertex_buffers, index_buffer)
+    }
+
+    /// Draws every sub-mesh with whatever pipeline and bind groups are currently set.
+    /// The pipeline's vertex layouts have to be `vertex_layouts`.
+    pub fn draw(&self, render_pass: &mut RenderPass) {
+
+        if self.index_count == 0 {
+            return;
+        }
+
+        self.set_buffers(render_pass);
+
+        for index in 0..self.submeshes.len() {
+            self.draw_submesh(render_pass, index);
+        }
+    }
+
+    /// Binds the vertex and index buffers, for `draw_submesh`.
+    pub fn set_buffers(&self, render_pass: &mut RenderPass) {
+
+        for (slot, buffer) in self.vertex_buffers.iter().enumerate() {
+            render_pass.set_vertex_buffer(slot as u32, buffer.slice(..));
+        }
+        render_pass.set_index_buffer(self.index_buffer.slice(..), self.index_format);
+    }
+
+    /// Draws one sub-mesh, after `set_buffers` was called for this mesh.
+    pub fn draw_submesh(&self, render_pass: &mut RenderPass, index: usize) {
+
+        let submesh = &self.submeshes[index];
+        if !submesh.indices.is_empty() {
+            render_pass.draw_indexed(submesh.indices.clone(), submesh.base_vertex, 0..1);
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use std::path::Path;
+
+use super::texture::TextureData;
+use super::vertex::{Vertex, VertexAttributeKind};
+use crate::error::{CandleError, CandleResult};
+
+pub mod gltf_loader;
+pub mod obj_loader;
+pub mod tangents;
+
+/// Geometry, materials and node hierarchy loaded from a model file, kept on the CPU.
+#[derive(Clone, Default)]
+pub struct Model {
+    pub meshes: Vec<MeshData>,
+    pub materials: Vec<MaterialData>,
+    pub images: Vec<TextureData>,
+    pub nodes: Vec<ModelNode>,
+    /// Nodes without a parent, i.e. the ones making up the scene.
+    pub root_nodes: Vec<usize>,
+}
+
+impl Model {
+
+    /// Picks the loader based on the file extension: `.gltf`, `.glb` or `.obj`.
+    pub fn load(path: impl AsRef<Path>) -> CandleResult<Self> {
+
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        match extension.as_str() {
+            "gltf" | "glb" => Self::load_gltf(path),
+            "obj" => Self::load_obj(path),
+            _ => Err(CandleError::ModelInvalid {
+                path: path.to_owned(),
+                message: format!("unsupported model format \"{extension}\""),
+            }),
+        }
+    }
+
+    /// Loads a `.gltf` or `.glb` file, together with the buffers and images it references.
+    pub fn load_gltf(path: impl AsRef<Path>) -> CandleResult<Self> {
+        gltf_loader::load(path.as_ref())
+    }
+
+    /// Loads an `.obj` file and its `.mtl` materials. N-gons are triangulated and meshes without
+    /// normals get flat ones.
+    pub fn load_obj(path: impl AsRef<Path>) -> CandleResult<Self> {
+        obj_loader::load(path.as_ref())
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MeshData {
+    pub name: String,
+    pub primitives: Vec<PrimitiveData>,
+}
+
+/// One draw's worth of triangles, sharing a single material.
+/// Every attribute array is either empty or as long as `positions`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PrimitiveData {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    /// xyz is the tangent, w the handedness of the bitangent.
+    pub tangents: Vec<[f32; 4]>,
+    pub uvs: Vec<[f32; 2]>,
+    /// Second UV set, e.g. for lightmaps.
+    pub uvs_1: Vec<[f32; 2]>,
+    /// Linear RGBA.
+    pub colors: Vec<[f32; 4]>,
+    pub joints: Vec<[u16; 4]>,
+    pub weights: Vec<[f32; 4]>,
+    /// Triangle list.
+    pub indices: Vec<u32>,
+    pub material: Option<usize>,
+}
+
+impl PrimitiveData {
+
+    /// Generates MikkTSpace tangents, see `tangents::generate_tangents`.
+    pub fn generate_tangents(&mut self) -> bool {
+        tangents::generate_tangents(self)
+    }
+
+    /// Interleaves the attributes the renderer's standard `Vertex` has room for.
+    pub fn vertices(&self) -> Vec<Vertex> {
+
+        (0..self.positions.len())
+            .map(|index| Vertex {
+                position: self.positions[index],
+                uv: attribute_or(&self.uvs, index, [0.0; 2]),
+                normal: attribute_or(&self.normals, index, DEFAULT_NORMAL),
+                tangent: attribute_or(&self.tangents, index, DEFAULT_TANGENT),
+            })
+            .collect()
+    }
+
+    /// Packs `attributes` of every vertex into one buffer, in the layout `VertexStreams` generates for them.
+    /// Missing attributes are filled with defaults, e.g. white for colors.
+    pub fn vertex_bytes(&self, attributes: &[VertexAttributeKind]) -> Vec<u8> {
+
+        let stride: u64 = attributes.iter().map(|attribute| attribute.format().size()).sum();
+        let mut bytes = Vec::with_capacity(stride as usize * self.positions.len());
+
+        for index in 0..self.positions.len() {
+            for attribute in attributes {
+                match attribute {
+                    VertexAttributeKind::Position => extend_floats(&mut bytes, &self.positions[index]),
+                    VertexAttributeKind::Uv0 => extend_floats(&mut bytes, &attribute_or(&self.uvs, index, [0.0; 2])),
+                    VertexAttributeKind::Normal => {
+                        extend_floats(&mut bytes, &attribute_or(&self.normals, index, DEFAULT_NORMAL))
+                    }
+                    VertexAttributeKind::Tangent => {
+                        extend_floats(&mut bytes, &attribute_or(&self.tangents, index, DEFAULT_TANGENT))
+                    }
+                    VertexAttributeKind::Color => extend_floats(&mut bytes, &attribute_or(&self.colors, index, [1.0; 4])),
+                    VertexAttributeKind::Uv1 => extend_floats(&mut bytes, &attribute_or(&self.uvs_1, index, [0.0; 2])),
+                    VertexAttributeKind::Joints => {
+                        for joint in attribute_or(&self.joints, index, [0; 4]) {
+                            bytes.extend(joint.to_le_bytes());
+                        }
+                    }
+                    VertexAttributeKind::Weights => {
+                        extend_floats(&mut bytes, &attribute_or(&self.weights, index, [1.0, 0.0, 0.0, 0.0]))
+                    }
+                }
+            }
+        }
+
+        bytes
+    }
+}
+
+const DEFAULT_NORMAL: [f32; 3] = [0.0, 0.0, 1.0];
+const DEFAULT_TANGENT: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+
+fn attribute_or<T: Copy>(values: &[T], index: usize, default: T) -> T {
+    values.get(index).copied().unwrap_or(default)
+}
+
+fn extend_floats(bytes: &mut Vec<u8>, values: &[f32]) {
+    for value in values {
+        bytes.extend(value.to_le_bytes());
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum AlphaMode {
+    #[default]
+    Opaque,
+    /// Fully transparent below the cutoff, fully opaque above it.
+    Mask { cutoff: f32 },
+    Blend,
+}
+
+/// Metallic-roughness material. Textures are indices into `Model::images`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MaterialData {
+    pub name: String,
+    pub base_color_factor: [f32; 4],
+    pub base_color_texture: Option<usize>,
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    pub metallic_roughness_texture: Option<usize>,
+    pub normal_texture: Option<usize>,
+    pub emissive_factor: [f32; 3],
+    pub emissive_texture: Option<usize>,
+    pub alpha_mode: AlphaMode,
+    pub double_sided: bool,
+}
+
+impl Default for MaterialData {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            base_color_factor: [1.0; 4],
+            base_color_texture: None,
+            metallic_factor: 1.0,
+            roughness_factor: 1.0,
+            metallic_roughness_texture: None,
+            normal_texture: None,
+            emissive_factor: [0.0; 3],
+            emissive_texture: None,
+            alpha_mode: AlphaMode::Opaque,
+            double_sided: false,
+        }
+    }
+}
+
+/// Node of the model's hierarchy, transformed relative to its parent.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ModelNode {
+    pub name: String,
+    pub translation: [f32; 3],
+    /// Quaternion, xyzw.
+    pub rotation: [f32; 4],
+    pub scale: [f32; 3],
+    pub mesh: Option<usize>,
+    pub children: Vec<usize>,
+}
@@ -0,0 +1,279 @@
+use std::path::Path;
+
+use gltf::image::Format;
+use gltf::mesh::Mode;
+use image::RgbaImage;
+
+use super::{AlphaMode, MaterialData, MeshData, Model, ModelNode, PrimitiveData};
+use crate::app::main_renderer::texture::TextureData;
+use crate::error::{CandleError, CandleResult};
+
+/// Reads a glTF 2.0 file. Buffers and images can be embedded in a `.glb`, stored next to the
+/// file or inlined as data URIs, `gltf::import` resolves all of them.
+pub fn load(path: &Path) -> CandleResult<Model> {
+
+    let (document, buffers, images) =
+        gltf::import(path).map_err(|source| CandleError::GltfImport { path: path.to_owned(), source })?;
+
+    let file_stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let images = images
+        .into_iter()
+        .zip(document.images())
+        .map(|(data, image)| {
+            let name = image
+                .name()
+                .map(str::to_owned)
+                .unwrap_or_else(|| format!("{file_stem} image {}", image.index()));
+            to_texture_data(name, data)
+        })
+        .collect::<Result<Vec<_>, String>>()
+        .map_err(|message| CandleError::ModelInvalid { path: path.to_owned(), message })?;
+
+    let materials = document.materials().map(load_material).collect();
+
+    let meshes = document
+        .meshes()
+        .map(|mesh| {
+
+            let mut primitives = Vec::new();
+
+            for primitive in mesh.primitives() {
+                let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|data| &data.0[..]));
+
+                let Some(positions) = reader.read_positions() else {
+                    return Err(CandleError::ModelInvalid {
+                        path: path.to_owned(),
+                        message: format!("a primitive of mesh {} has no positions", mesh.index()),
+                    });
+                };
+                let positions: Vec<[f32; 3]> = positions.collect();
+
+                let indices: Vec<u32> = match reader.read_indices() {
+                    Some(indices) => indices.into_u32().collect(),
+                    None => (0..positions.len() as u32).collect(),
+                };
+
+                let Some(indices) = triangulate(primitive.mode(), indices) else {
+                    log::warn!(
+                        "Skipping a {:?} primitive of mesh {} in {}, only triangles are supported",
+                        primitive.mode(),
+                        mesh.index(),
+                        path.display()
+                    );
+                    continue;
+                };
+
+                let mut primitive_data = PrimitiveData {
+                    positions,
+                    normals: reader.read_normals().map(Iterator::collect).unwrap_or_default(),
+                    tangents: reader.read_tangents().map(Iterator::collect).unwrap_or_default(),
+                    uvs: reader
+                        .read_tex_coords(0)
+                        .map(|uvs| uvs.into_f32().collect())
+                        .unwrap_or_default(),
+                    uvs_1: reader
+                        .read_tex_coords(1)
+                        .map(|uvs| uvs.into_f32().collect())
+                        .unwrap_or_default(),
+                    colors: reader
+                        .read_colors(0)
+                        .map(|colors| colors.into_rgba_f32().collect())
+                        .unwrap_or_default(),
+                    joints: reader
+                        .read_joints(0)
+                        .map(|joints| joints.into_u16().collect())
+                        .unwrap_or_default(),
+                    weights: reader
+                        .read_weights(0)
+                        .map(|weights| weights.into_f32().collect())
+                        .unwrap_or_default(),
+                    indices,
+                    material: primitive.material().index(),
+                };
+
+                check_counts(&primitive_data).map_err(|message| CandleError::ModelInvalid {
+                    path: path.to_owned(),
+                    message: format!("a primitive of mesh {} {message}", mesh.index()),
+                })?;
+
+                // The glTF spec asks for MikkTSpace when tangents aren't provided
+                if primitive_data.tangents.is_empty() {
+                    primitive_data.generate_tangents();
+                }
+
+                primitives.push(primitive_data);
+            }
+
+            Ok(MeshData {
+                name: mesh.name().map(str::to_owned).unwrap_or_else(|| format!("{file_stem} mesh {}", mesh.index())),
+                primitives,
+            })
+        })
+        .collect::<CandleResult<Vec<_>>>()?;
+
+    let nodes = document
+        .nodes()
+        .map(|node| {
+            let (translation, rotation, scale) = node.transform().decomposed();
+            ModelNode {
+                name: node.name().map(str::to_owned).unwrap_or_default(),
+                translation,
+                rotation,
+                scale,
+                mesh: node.mesh().map(|mesh| mesh.index()),
+                children: node.children().map(|child| child.index()).collect(),
+            }
+        })
+        .collect();
+
+    // Files without scenes are only libraries of meshes, so their parentless nodes are used instead
+    let root_nodes = match document.default_scene().or_else(|| document.scenes().next()) {
+        Some(scene) => scene.nodes().map(|node| node.index()).collect(),
+        None => {
+            let mut has_parent = vec![false; document.nodes().len()];
+            for node in document.nodes() {
+                for child in node.children() {
+                    has_parent[child.index()] = true;
+                }
+            }
+            (0..has_parent.len()).filter(|&index| !has_parent[index]).collect()
+        }
+    };
+
+    Ok(Model {
+        meshes,
+        materials,
+        images,
+        nodes,
+        root_nodes,
+    })
+}
+
+fn load_material(material: gltf::Material) -> MaterialData {
+
+    let pbr = material.pbr_metallic_roughness();
+    let image_of = |texture: gltf::Texture| texture.source().index();
+
+    MaterialData {
+        name: material.name().unwrap_or_default().to_owned(),
+        base_color_factor: pbr.base_color_factor(),
+        base_color_texture: pbr.base_color_texture().map(|info| image_of(info.texture())),
+        metallic_factor: pbr.metallic_factor(),
+        roughness_factor: pbr.roughness_factor(),
+        metallic_roughness_texture: pbr.metallic_roughness_texture().map(|info| image_of(info.texture())),
+        normal_texture: material.normal_texture().map(|info| image_of(info.texture())),
+        emissive_factor: material.emissive_factor(),
+        emissive_texture: material.emissive_texture().map(|info| image_of(info.texture())),
+        alpha_mode: match material.alpha_mode() {
+            gltf::material::AlphaMode::Opaque => AlphaMode::Opaque,
+            gltf::material::AlphaMode::Mask => AlphaMode::Mask { cutoff: material.alpha_cutoff().unwrap_or(0.5) },
+            gltf::material::AlphaMode::Blend => AlphaMode::Blend,
+        },
+        double_sided: material.double_sided(),
+    }
+}
+
+/// Every attribute needs one value per position and every index a position, which the glTF
+/// crate doesn't check when reading a file.
+fn check_counts(primitive: &PrimitiveData) -> Result<(), String> {
+
+    let vertex_count = primitive.positions.len();
+    let attribute_counts = [
+        ("normals", primitive.normals.len()),
+        ("tangents", primitive.tangents.len()),
+        ("UVs", primitive.uvs.len()),
+        ("second UVs", primitive.uvs_1.len()),
+        ("colors", primitive.colors.len()),
+        ("joints", primitive.joints.len()),
+        ("weights", primitive.weights.len()),
+    ];
+
+    for (attribute, count) in attribute_counts {
+        if count != 0 && count != vertex_count {
+            return Err(format!("has {count} {attribute} for {vertex_count} positions"));
+        }
+    }
+
+    match primitive.indices.iter().find(|&&index| index as usize >= vertex_count) {
+        Some(index) => Err(format!("uses vertex {index}, but has only {vertex_count}")),
+        None => Ok(()),
+    }
+}
+
+/// Turns strips and fans into a triangle list. Points and lines can't be, so they return `None`.
+fn triangulate(mode: Mode, indices: Vec<u32>) -> Option<Vec<u32>> {
+
+    match mode {
+        Mode::Triangles => Some(indices),
+        Mode::TriangleStrip => Some(
+            indices
+                .windows(3)
+                .enumerate()
+                // Every other triangle is flipped to keep the winding consistent
+                .flat_map(|(index, triangle)| match index % 2 {
+                    0 => [triangle[0], triangle[1], triangle[2]],
+                    _ => [triangle[1], triangle[0], triangle[2]],
+                })
+                .collect(),
+        ),
+        Mode::TriangleFan => Some(
+            indices
+                .windows(2)
+                .skip(1)
+                .flat_map(|edge| [indices[0], edge[0], edge[1]])
+                .collect(),
+        ),
+        Mode::Points | Mode::Lines | Mode::LineLoop | Mode::LineStrip => None,
+    }
+}
+
+/// Expands whatever channel layout the image was decoded to into 8-bit RGBA.
+fn to_texture_data(name: String, data: gltf::image::Data) -> Result<TextureData, String> {
+
+    let (channels, bytes_per_channel) = match data.format {
+        Format::R8 => (1, 1),
+        Format::R8G8 => (2, 1),
+        Format::R8G8B8 => (3, 1),
+        Format::R8G8B8A8 => (4, 1),
+        Format::R16 => (1, 2),
+        Format::R16G16 => (2, 2),
+        Format::R16G16B16 => (3, 2),
+        Format::R16G16B16A16 => (4, 2),
+        Format::R32G32B32FLOAT => (3, 4),
+        Format::R32G32B32A32FLOAT => (4, 4),
+    };
+
+    let channel = |bytes: &[u8]| -> u8 {
+        match bytes_per_channel {
+            1 => bytes[0],
+            2 => (u16::from_le_bytes([bytes[0], bytes[1]]) >> 8) as u8,
+            _ => (f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]).clamp(0.0, 1.0) * 255.0).round() as u8,
+        }
+    };
+
+    let pixels: Vec<u8> = data
+        .pixels
+        .chunks_exact(channels * bytes_per_channel)
+        .flat_map(|pixel| {
+            let mut rgba = [0, 0, 0, 255];
+            for (index, bytes) in pixel.chunks_exact(bytes_per_channel).enumerate() {
+                rgba[index] = channel(bytes);
+            }
+            // Single channel images are grayscale
+            if channels == 1 {
+                rgba[1] = rgba[0];
+                rgba[2] = rgba[0];
+            }
+            rgba
+        })
+        .collect();
+
+    let image = RgbaImage::from_raw(data.width, data.height, pixels)
+        .ok_or_else(|| format!("image \"{name}\" has fewer pixels than its size says"))?;
+
+    Ok(TextureData { name, image })
+}
@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::{AlphaMode, MaterialData, MeshData, Model, ModelNode, PrimitiveData};
+use crate::app::main_renderer::texture::TextureData;
+use crate::error::{CandleError, CandleResult};
+
+/// Reads a Wavefront OBJ file and the MTL libraries it references.
+/// Every object or group becomes its own mesh and root node.
+pub fn load(path: &Path) -> CandleResult<Model> {
+
+    let options = tobj::LoadOptions {
+        triangulate: true,
+        // Positions, UVs and normals keep their own indices, they're combined in `deindex`
+        single_index: false,
+        ..Default::default()
+    };
+
+    let (objects, materials) =
+        tobj::load_obj(path, &options).map_err(|source| CandleError::ObjImport { path: path.to_owned(), source })?;
+
+    let materials = materials.unwrap_or_else(|error| {
+        log::warn!("Loading {} without materials: {error}", path.display());
+        Vec::new()
+    });
+
+    let directory = path.parent().unwrap_or(Path::new(""));
+    let mut images: Vec<TextureData> = Vec::new();
+    // `None` for files that failed to load, so they're only reported once
+    let mut image_indices: HashMap<String, Option<usize>> = HashMap::new();
+
+    // Like missing material libraries, broken texture references only lose the texture
+    let mut load_image = |file: &Option<String>| -> Option<usize> {
+
+        let file = file.as_ref()?;
+
+        if let Some(&index) = image_indices.get(file) {
+            return index;
+        }
+
+        let index = match TextureData::load(&directory.join(file)) {
+            Ok(image) => {
+                images.push(image);
+                Some(images.len() - 1)
+            }
+            Err(error) => {
+                log::warn!("Loading {} without texture {file}: {error}", path.display());
+                None
+            }
+        };
+        image_indices.insert(file.clone(), index);
+
+        index
+    };
+
+    let materials = materials
+        .iter()
+        .map(|material| {
+            let diffuse = material.diffuse.unwrap_or([1.0; 3]);
+            let opacity = material.dissolve.unwrap_or(1.0);
+
+            MaterialData {
+                name: material.name.clone(),
+                base_color_factor: [diffuse[0], diffuse[1], diffuse[2], opacity],
+                base_color_texture: load_image(&material.diffuse_texture),
+                metallic_factor: 0.0,
+                // Blinn-Phong exponent to roughness, the usual sqrt(2 / (Ns + 2)) approximation
+                roughness_factor: material.shininess.map_or(1.0, |shininess| (2.0 / (shininess.max(0.0) + 2.0)).sqrt()),
+                metallic_roughness_texture: None,
+                normal_texture: load_image(&material.normal_texture),
+                emissive_factor: material.emissive.unwrap_or_default(),
+                emissive_texture: None,
+                alpha_mode: if opacity < 1.0 { AlphaMode::Blend } else { AlphaMode::Opaque },
+                double_sided: false,
+            }
+        })
+        .collect();
+
+    let meshes: Vec<MeshData> = objects
+        .into_iter()
+        .map(|object| {
+            let mut primitive = deindex(&object.mesh).map_err(|message| CandleError::ModelInvalid {
+                path: path.to_owned(),
+                message: format!("object \"{}\" {message}", object.name),
+            })?;
+            primitive.generate_tangents();
+
+            Ok(MeshData {
+                name: object.name,
+                primitives: vec![primitive],
+            })
+        })
+        .collect::<CandleResult<_>>()?;
+
+    let nodes = meshes
+        .iter()
+        .enumerate()
+        .map(|(index, mesh)| ModelNode {
+            name: mesh.name.clone(),
+            translation: [0.0; 3],
+            rotation: [0.0, 0.0, 0.0, 1.0],
+            scale: [1.0; 3],
+            mesh: Some(index),
+            children: Vec::new(),
+        })
+        .collect();
+
+    Ok(Model {
+        root_nodes: (0..meshes.len()).collect(),
+        meshes,
+        materials,
+        images,
+        nodes,
+    })
+}
+
+/// OBJ indexes positions, UVs and normals separately, the GPU needs one index per vertex.
+/// Every distinct combination becomes a vertex. Without normals, each triangle gets its own
+/// vertices with the face normal, so the mesh is flat shaded.
+///
+/// Fails on indices that don't match up, e.g. faces giving UVs for only some of their corners.
+fn deindex(mesh: &tobj::Mesh) -> Result<PrimitiveData, String> {
+
+    let has_uvs = !mesh.texcoord_indices.is_empty();
+    let has_normals = !mesh.normal_indices.is_empty();
+
+    for (attribute, indices, value_count) in [
+        ("position", &mesh.indices, mesh.positions.len() / 3),
+        ("UV", &mesh.texcoord_indices, mesh.texcoords.len() / 2),
+        ("normal", &mesh.normal_indices, mesh.normals.len() / 3),
+    ] {
+        if !indices.is_empty() && indices.len() != mesh.indices.len() {
+            return Err(format!("gives a {attribute} for {} of its {} corners", indices.len(), mesh.indices.len()));
+        }
+        if let Some(index) = indices.iter().find(|&&index| index as usize >= value_count) {
+            return Err(format!("refers to {attribute} {index}, but there are only {value_count}"));
+        }
+    }
+
+    let position = |index: u32| {
+        let index = index as usize * 3;
+        [mesh.positions[index], mesh.positions[index + 1], mesh.positions[index + 2]]
+    };
+    // OBJ puts the UV origin at the bottom left, wgpu at the top left
+    let uv = |index: u32| {
+        let index = index as usize * 2;
+        [mesh.texcoords[index], 1.0 - mesh.texcoords[index + 1]]
+    };
+    let normal = |index: u32| {
+        let index = index as usize * 3;
+        [mesh.normals[index], mesh.normals[index + 1], mesh.normals[index + 2]]
+    };
+
+    let mut primitive = PrimitiveData {
+        material: mesh.material_id,
+        ..Default::default()
+    };
+
+    if !has_normals {
+
+        for triangle in mesh.indices.chunks_exact(3) {
+
+            let corners = [position(triangle[0]), position(triangle[1]), position(triangle[2])];
+            let face_normal = face_normal(corners);
+
+            for corner in corners {
+                primitive.indices.push(primitive.positions.len() as u32);
+                primitive.positions.push(corner);
+                primitive.normals.push(face_normal);
+            }
+        }
+
+        if has_uvs {
+            primitive.uvs = mesh.texcoord_indices.iter().map(|&index| uv(index)).collect();
+        }
+
+        return Ok(primitive);
+    }
+
+    let mut vertices: HashMap<(u32, Option<u32>, u32), u32> = HashMap::new();
+
+    for (corner, &position_index) in mesh.indices.iter().enumerate() {
+
+        let uv_index = has_uvs.then(|| mesh.texcoord_indices[corner]);
+        let normal_index = mesh.normal_indices[corner];
+
+        let index = *vertices.entry((position_index, uv_index, normal_index)).or_insert_with(|| {
+            primitive.positions.push(position(position_index));
+            primitive.normals.push(normal(normal_index));
+            if let Some(uv_index) = uv_index {
+                primitive.uvs.push(uv(uv_index));
+            }
+            primitive.positions.len() as u32 - 1
+        });
+
+        primitive.indices.push(index);
+    }
+
+    Ok(primitive)
+}
+
+/// Normal of the side the triangle's vertices are counter-clockwise on.
+fn face_normal([a, b, c]: [[f32; 3]; 3]) -> [f32; 3] {
+
+    let ab = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+    let ac = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+    let cross = [
+        ab[1] * ac[2] - ab[2] * ac[1],
+        ab[2] * ac[0] - ab[0] * ac[2],
+        ab[0] * ac[1] - ab[1] * ac[0],
+    ];
+
+    let length = (cross[0] * cross[0] + cross[1] * cross[1] + cross[2] * cross[2]).sqrt();
+    if length == 0.0 {
+        return [0.0, 0.0, 1.0];
+    }
+
+    [cross[0] / length, cross[1] / length, cross[2] / length]
+}
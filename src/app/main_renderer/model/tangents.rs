@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+
+use super::PrimitiveData;
+
+/// Adapts an indexed triangle list to what the MikkTSpace generator reads and writes.
+struct MikkTSpaceGeometry<'a> {
+    primitive: &'a PrimitiveData,
+    /// One per corner, i.e. per entry of `indices`.
+    tangents: Vec<[f32; 4]>,
+}
+impl MikkTSpaceGeometry<'_> {
+
+    fn vertex_index(&self, face: usize, vert: usize) -> usize {
+        self.primitive.indices[face * 3 + vert] as usize
+    }
+}
+
+impl bevy_mikktspace::Geometry for MikkTSpaceGeometry<'_> {
+
+    fn num_faces(&self) -> usize {
+        self.primitive.indices.len() / 3
+    }
+
+    fn num_vertices_of_face(&self, _face: usize) -> usize {
+        3
+    }
+
+    fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.primitive.positions[self.vertex_index(face, vert)]
+    }
+
+    fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.primitive.normals[self.vertex_index(face, vert)]
+    }
+
+    fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+        self.primitive.uvs[self.vertex_index(face, vert)]
+    }
+
+    fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize) {
+        self.tangents[face * 3 + vert] = tangent;
+    }
+}
+
+/// Fills `primitive.tangents` following MikkTSpace, the tangent space Blender, Substance and
+/// xNormal bake normal maps in. Needs normals, UVs and indices within the vertices, returns whether
+/// tangents were generated.
+///
+/// Tangents are generated per corner. MikkTSpace can give corners sharing a vertex different tangents,
+/// e.g. where the bitangent flips on a mirrored UV seam, so those vertices are split to keep every
+/// tangent the bake expects. Corners that agree keep sharing their vertex.
+pub fn generate_tangents(primitive: &mut PrimitiveData) -> bool {
+
+    let vertex_count = primitive.positions.len();
+    if primitive.indices.is_empty() || primitive.normals.len() != vertex_count || primitive.uvs.len() != vertex_count {
+        return false;
+    }
+    if primitive.indices.iter().any(|&index| index as usize >= vertex_count) {
+        return false;
+    }
+
+    let mut geometry = MikkTSpaceGeometry {
+        primitive: &*primitive,
+        tangents: vec![[1.0, 0.0, 0.0, 1.0]; primitive.indices.len()],
+    };
+
+    if !bevy_mikktspace::generate_tangents(&mut geometry) {
+        return false;
+    }
+
+    let corner_tangents = geometry.tangents;
+
+    // Each vertex keeps the tangent of its first corner, corners disagreeing with it move to a copy
+    let mut tangents: Vec<Option<[f32; 4]>> = vec![None; vertex_count];
+    let mut copies: HashMap<(u32, [u32; 4]), u32> = HashMap::new();
+    let mut copied_from: Vec<usize> = Vec::new();
+
+    for (index, tangent) in primitive.indices.iter_mut().zip(corner_tangents) {
+
+        let vertex = *index as usize;
+        match tangents[vertex] {
+            None => tangents[vertex] = Some(tangent),
+            Some(first) if first == tangent => {}
+            Some(_) => {
+                *index = *copies.entry((*index, tangent.map(f32::to_bits))).or_insert_with(|| {
+                    copied_from.push(vertex);
+                    tangents.push(Some(tangent));
+                    (vertex_count + copied_from.len() - 1) as u32
+                });
+            }
+        }
+    }
+
+    copy_vertices(&mut primitive.positions, &copied_from);
+    copy_vertices(&mut primitive.normals, &copied_from);
+    copy_vertices(&mut primitive.uvs, &copied_from);
+    copy_vertices(&mut primitive.uvs_1, &copied_from);
+    copy_vertices(&mut primitive.colors, &copied_from);
+    copy_vertices(&mut primitive.joints, &copied_from);
+    copy_vertices(&mut primitive.weights, &copied_from);
+
+    // Vertices no triangle uses still need something
+    primitive.tangents = tangents.into_iter().map(|tangent| tangent.unwrap_or([1.0, 0.0, 0.0, 1.0])).collect();
+
+    true
+}
+
+/// Appends a copy of the given vertices to an attribute, unless the primitive doesn't have it.
+fn copy_vertices<T: Copy>(attribute: &mut Vec<T>, sources: &[usize]) {
+
+    if !attribute.is_empty() {
+        for &source in sources {
+            attribute.push(attribute[source]);
+        }
+    }
+}
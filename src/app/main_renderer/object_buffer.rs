@@ -0,0 +1,99 @@
+use egui_wgpu::wgpu::{self, BindGroup, BindGroupLayout, Buffer, BufferAddress, Device, Queue};
+
+/// Layout of the `Object` struct in `shader.wgsl`, one per draw.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ObjectUniform {
+    /// Local to world space.
+    pub model: [[f32; 4]; 4],
+    /// Linear RGBA, from the material.
+    pub base_color: [f32; 4],
+}
+
+/// Uniform buffer with an `ObjectUniform` for every draw of the frame, bound to group 2 of the
+/// main shader at the dynamic offset of the draw.
+pub struct ObjectBuffer {
+    pub buffer: Buffer,
+    pub bind_group_layout: BindGroupLayout,
+    pub bind_group: BindGroup,
+    /// Distance between objects in the buffer, as the device's uniform offset alignment asks.
+    stride: BufferAddress,
+    capacity: usize,
+}
+
+impl ObjectBuffer {
+
+    const INITIAL_CAPACITY: usize = 64;
+
+    pub fn new(device: &Device) -> Self {
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: wgpu::BufferSize::new(size_of::<ObjectUniform>() as u64),
+                },
+                count: None,
+            }],
+            label: Some("object_bind_group_layout"),
+        });
+
+        let alignment = device.limits().min_uniform_buffer_offset_alignment as BufferAddress;
+        let stride = (size_of::<ObjectUniform>() as BufferAddress).next_multiple_of(alignment);
+
+        let (buffer, bind_group) = Self::allocate(device, &bind_group_layout, stride, Self::INITIAL_CAPACITY);
+
+        Self { buffer, bind_group_layout, bind_group, stride, capacity: Self::INITIAL_CAPACITY }
+    }
+
+    /// Uploads `objects`, growing the buffer if they don't fit. The bind group is replaced when it grows.
+    pub fn write(&mut self, device: &Device, queue: &Queue, objects: &[ObjectUniform]) {
+
+        if objects.len() > self.capacity {
+            self.capacity = objects.len().next_power_of_two();
+            (self.buffer, self.bind_group) = Self::allocate(device, &self.bind_group_layout, self.stride, self.capacity);
+        }
+
+        let mut bytes = vec![0; objects.len() * self.stride as usize];
+        for (chunk, object) in bytes.chunks_exact_mut(self.stride as usize).zip(objects) {
+            chunk[..size_of::<ObjectUniform>()].copy_from_slice(bytemuck::bytes_of(object));
+        }
+
+        if !bytes.is_empty() {
+            queue.write_buffer(&self.buffer, 0, &bytes);
+        }
+    }
+
+    /// Dynamic offset of the object at `index`.
+    pub fn offset(&self, index: usize) -> u32 {
+        (index as BufferAddress * self.stride) as u32
+    }
+
+    fn allocate(device: &Device, layout: &BindGroupLayout, stride: BufferAddress, capacity: usize) -> (Buffer, BindGroup) {
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Object buffer"),
+            size: stride * capacity as BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Object bind group"),
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(size_of::<ObjectUniform>() as u64),
+                }),
+            }],
+        });
+
+        (buffer, bind_group)
+    }
+}
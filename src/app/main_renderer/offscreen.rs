@@ -0,0 +1,100 @@
+use egui_wgpu::wgpu::{self, Device, Queue, SurfaceConfiguration, TextureView};
+
+/// Color texture owned by the renderer, used instead of a swapchain when running headless.
+pub struct OffscreenTarget {
+    pub texture: wgpu::Texture,
+    pub view: TextureView,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl OffscreenTarget {
+
+    pub fn new(device: &Device, config: &SurfaceConfiguration) -> Self {
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen Color Target"),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: config.usage,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        Self {
+            texture,
+            view,
+            width: config.width,
+            height: config.height,
+        }
+    }
+
+    /// Copies the target back to the CPU and returns tightly packed RGBA8 pixels, row by row.
+    pub fn read_pixels(&self, device: &Device, queue: &Queue) -> Vec<u8> {
+
+        const BYTES_PER_PIXEL: u32 = 4;
+
+        let unpadded_bytes_per_row = self.width * BYTES_PER_PIXEL;
+        let padded_bytes_per_row = unpadded_bytes_per_row
+            .div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT) * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Offscreen Readback Buffer"),
+            size: (padded_bytes_per_row * self.height) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Readback Encoder") });
+
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &readback_buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(self.height),
+                },
+            },
+            wgpu::Extent3d {
+                width: self.width,
+                height: self.height,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        queue.submit(Some(encoder.finish()));
+
+        let buffer_slice = readback_buffer.slice(..);
+        buffer_slice.map_async(wgpu::MapMode::Read, |result| {
+            result.expect("Failed to map readback buffer!");
+        });
+        device.poll(wgpu::Maintain::Wait);
+
+        let mut pixels = Vec::with_capacity((unpadded_bytes_per_row * self.height) as usize);
+        {
+            let padded_data = buffer_slice.get_mapped_range();
+            for row in padded_data.chunks(padded_bytes_per_row as usize) {
+                pixels.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+            }
+        }
+        readback_buffer.unmap();
+
+        pixels
+    }
+}
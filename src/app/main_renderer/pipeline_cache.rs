@@ -0,0 +1,271 @@
+use std::collections::{HashMap, HashSet};
+
+use egui_wgpu::wgpu::{self, Device};
+
+use super::vertex::VertexLayout;
+
+/// Identifies a shader registered in a `PipelineCache`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ShaderId(usize);
+
+/// Refers to a pipeline built by a `PipelineCache`. Stays valid when its shader is replaced,
+/// but not once the pipeline is evicted with `PipelineCache::retain`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PipelineId(usize);
+
+/// Everything that makes two render pipelines different.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PipelineKey {
+    pub shader: ShaderId,
+    pub vertex_layouts: Vec<VertexLayout>,
+    pub blend: Option<wgpu::BlendState>,
+    pub cull_mode: Option<wgpu::Face>,
+    pub topology: wgpu::PrimitiveTopology,
+    pub polygon_mode: wgpu::PolygonMode,
+    pub depth_stencil: Option<wgpu::DepthStencilState>,
+    pub sample_count: u32,
+    pub color_formats: Vec<wgpu::TextureFormat>,
+}
+
+impl PipelineKey {
+
+    /// Opaque, back-face culled triangles drawn into a single color target.
+    pub fn new(shader: ShaderId, vertex_layout: VertexLayout, color_format: wgpu::TextureFormat) -> Self {
+
+        Self {
+            shader,
+            vertex_layouts: vec![vertex_layout],
+            blend: Some(wgpu::BlendState::REPLACE),
+            cull_mode: Some(wgpu::Face::Back),
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            depth_stencil: None,
+            sample_count: 1,
+            color_formats: vec![color_format],
+        }
+    }
+
+    /// Depth tested against and written to a depth buffer of `format`, so nearer surfaces win.
+    pub fn with_depth(mut self, format: wgpu::TextureFormat) -> Self {
+
+        self.depth_stencil = Some(wgpu::DepthStencilState {
+            format,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        });
+        self
+    }
+}
+
+struct CachedShader {
+    label: String,
+    module: wgpu::ShaderModule,
+    /// The bind group interface belongs to the shader, so every pipeline using it shares the layout.
+    layout: wgpu::PipelineLayout,
+}
+
+/// Builds render pipelines on demand and keeps them around, keyed by the state they were built with.
+#[derive(Default)]
+pub struct PipelineCache {
+    shaders: Vec<CachedShader>,
+    /// `None` for evicted pipelines, so the ids of the others stay the same.
+    pipelines: Vec<Option<wgpu::RenderPipeline>>,
+    ids: HashMap<PipelineKey, PipelineId>,
+    /// Keys whose pipeline failed validation, not tried again until their shader is replaced.
+    failed: HashSet<PipelineKey>,
+}
+
+impl PipelineCache {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_shader(
+        &mut self,
+        device: &Device,
+        label: &str,
+        source: &str,
+        layout: wgpu::PipelineLayout,
+    ) -> ShaderId {
+
+        let module = Self::create_shader_module(device, label, source);
+
+        self.shaders.push(CachedShader {
+            label: label.to_owned(),
+            module,
+            layout,
+        });
+
+        ShaderId(self.shaders.len() - 1)
+    }
+
+    /// Recompiles a shader and rebuilds every cached pipeline using it. If anything fails validation,
+    /// the error is returned and the old shader and pipelines stay in place.
+    pub fn try_replace_shader(&mut self, device: &Device, shader: ShaderId, source: &str) -> Result<(), String> {
+
+        // Validation errors are caught by the scope instead of going to wgpu's default handler, which panics
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+
+        let module = Self::create_shader_module(device, &self.shaders[shader.0].label, source);
+        let pipelines: Vec<(PipelineId, wgpu::RenderPipeline)> = self
+            .ids
+            .iter()
+            .filter(|(key, _)| key.shader == shader)
+            .map(|(key, &id)| {
+                let layout = &self.shaders[shader.0].layout;
+                (id, Self::create_pipeline(device, key, &module, layout))
+            })
+            .collect();
+
+        if let Some(error) = pollster::block_on(device.pop_error_scope()) {
+            return Err(error.to_string());
+        }
+
+        self.shaders[shader.0].module = module;
+        for (id, pipeline) in pipelines {
+            self.pipelines[id.0] = Some(pipeline);
+        }
+        self.failed.retain(|key| key.shader != shader);
+
+        Ok(())
+    }
+
+    /// Builds the pipeline for `key` unless it's already cached, and returns it unless that failed.
+    /// A key the shader doesn't work with, e.g. vertex layouts missing an attribute the shader reads,
+    /// is logged and left out instead of panicking in wgpu's default error handler.
+    pub fn prepare(&mut self, device: &Device, key: &PipelineKey) -> Option<PipelineId> {
+
+        if let Some(&id) = self.ids.get(key) {
+            return Some(id);
+        }
+        if self.failed.contains(key) {
+            return None;
+        }
+
+        let shader = &self.shaders[key.shader.0];
+
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let pipeline = Self::create_pipeline(device, key, &shader.module, &shader.layout);
+
+        if let Some(error) = pollster::block_on(device.pop_error_scope()) {
+            log::error!("Failed to build a \"{}\" pipeline, skipping what uses it: {error}", shader.label);
+            self.failed.insert(key.clone());
+            return None;
+        }
+
+        let id = PipelineId(self.pipelines.len());
+        self.pipelines.push(Some(pipeline));
+        self.ids.insert(key.clone(), id);
+
+        Some(id)
+    }
+
+    pub fn get_or_create(&mut self, device: &Device, key: &PipelineKey) -> Option<&wgpu::RenderPipeline> {
+        let id = self.prepare(device, key)?;
+        Some(self.pipeline(id))
+    }
+
+    /// Returns a pipeline built earlier with `prepare` or `get_or_create`.
+    pub fn get(&self, key: &PipelineKey) -> Option<&wgpu::RenderPipeline> {
+        self.ids.get(key).map(|&id| self.pipeline(id))
+    }
+
+    /// Like `get`, without hashing the key again.
+    pub fn pipeline(&self, id: PipelineId) -> &wgpu::RenderPipeline {
+        self.pipelines[id.0].as_ref().expect("Pipeline ids aren't used after their pipeline is evicted!")
+    }
+
+    /// Evicts the pipelines and remembered failures whose key `keep` returns false for, e.g. variants
+    /// nothing draws with anymore, so replacing their shader doesn't rebuild them.
+    pub fn retain(&mut self, mut keep: impl FnMut(&PipelineKey) -> bool) {
+
+        let pipelines = &mut self.pipelines;
+        self.ids.retain(|key, id| {
+            let kept = keep(key);
+            if !kept {
+                pipelines[id.0] = None;
+            }
+            kept
+        });
+        self.failed.retain(|key| keep(key));
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    fn create_shader_module(device: &Device, label: &str, source: &str) -> wgpu::ShaderModule {
+
+        device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(label),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        })
+    }
+
+    fn create_pipeline(
+        device: &Device,
+        key: &PipelineKey,
+        module: &wgpu::ShaderModule,
+        layout: &wgpu::PipelineLayout,
+    ) -> wgpu::RenderPipeline {
+
+        let vertex_buffers: Vec<wgpu::VertexBufferLayout> = key
+            .vertex_layouts
+            .iter()
+            .map(VertexLayout::as_buffer_layout)
+            .collect();
+
+        let targets: Vec<Option<wgpu::ColorTargetState>> = key
+            .color_formats
+            .iter()
+            .map(|&format| {
+                Some(wgpu::ColorTargetState {
+                    format,
+                    blend: key.blend,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })
+            })
+            .collect();
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Render Pipeline"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module,
+                entry_point: "vertex",
+                buffers: &vertex_buffers,
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module,
+                entry_point: "fragment",
+                targets: &targets,
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: key.topology,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: key.cull_mode,
+                polygon_mode: key.polygon_mode,
+                unclipped_depth: false,
+                conservative: false
+            },
+            depth_stencil: key.depth_stencil.clone(),
+            multisample: wgpu::MultisampleState {
+                count: key.sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false
+            },
+            multiview: None,
+            cache: None
+        })
+    }
+}
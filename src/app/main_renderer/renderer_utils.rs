@@ -1,16 +1,22 @@
 use egui_wgpu::wgpu::{self, Adapter, Device, Queue, Surface, SurfaceConfiguration, TextureFormat};
 
-pub async fn get_device(instance: &wgpu::Instance, surface: &wgpu::Surface<'static>) -> (Adapter, Device, Queue) {
-    
+use crate::error::{CandleError, CandleResult};
+
+pub async fn get_device(
+    instance: &wgpu::Instance,
+    compatible_surface: Option<&wgpu::Surface<'static>>,
+    force_fallback_adapter: bool,
+) -> CandleResult<(Adapter, Device, Queue)> {
+
     let power_pref = wgpu::PowerPreference::HighPerformance;
     let adapter = instance
         .request_adapter(&wgpu::RequestAdapterOptions {
             power_preference: power_pref,
-            force_fallback_adapter: false,
-            compatible_surface: Some(&surface),
+            force_fallback_adapter,
+            compatible_surface,
         })
         .await
-        .expect("Failed to find an appropriate adapter");
+        .ok_or(CandleError::AdapterNotFound { force_fallback_adapter })?;
 
     let features = wgpu::Features::default();
 
@@ -25,34 +31,65 @@ pub async fn get_device(instance: &wgpu::Instance, surface: &wgpu::Surface<'stat
             None,
         )
         .await
-        .expect("Failed to create device");
+        .map_err(CandleError::DeviceRequest)?;
 
-    return (adapter, device, queue);
+    Ok((adapter, device, queue))
 }
 
 
-pub fn configure_surface(surface: &Surface, width: u32, height: u32, device: &Device, adapter: &Adapter) -> SurfaceConfiguration {
+pub fn configure_surface(
+    surface: &Surface,
+    width: u32,
+    height: u32,
+    present_mode: wgpu::PresentMode,
+    device: &Device,
+    adapter: &Adapter,
+) -> CandleResult<SurfaceConfiguration> {
 
-    let surface_caps = surface.get_capabilities(&adapter);
+    let surface_caps = surface.get_capabilities(adapter);
         let surface_format = surface_caps
             .formats
             .iter()
             .find(|f| f.is_srgb())
             .copied()
-            .expect("Failed to select proper surface texture format!");
+            .ok_or_else(|| CandleError::NoSrgbSurfaceFormat { available: surface_caps.formats.clone() })?;
+
+        let present_mode = if surface_caps.present_modes.contains(&present_mode) {
+            present_mode
+        } else {
+            log::warn!("Present mode {present_mode:?} isn't supported by the surface, falling back to Fifo");
+            wgpu::PresentMode::Fifo
+        };
 
         let surface_config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
             format: surface_format,
             width,
             height,
-            present_mode: wgpu::PresentMode::Immediate,
+            present_mode,
             desired_maximum_frame_latency: 0,
             alpha_mode: surface_caps.alpha_modes[0],
             view_formats: vec![],
         };
 
-        surface.configure(&device, &surface_config);
+        surface.configure(device, &surface_config);
 
-        return surface_config;
-}
\ No newline at end of file
+        Ok(surface_config)
+}
+
+
+/// Describes the offscreen color target the same way a surface would be described,
+/// so the rest of the renderer doesn't have to care whether it's headless or not.
+pub fn configure_offscreen(width: u32, height: u32, format: TextureFormat) -> SurfaceConfiguration {
+
+    wgpu::SurfaceConfiguration {
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        format,
+        width,
+        height,
+        present_mode: wgpu::PresentMode::Fifo,
+        desired_maximum_frame_latency: 0,
+        alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+        view_formats: vec![],
+    }
+}
@@ -0,0 +1,405 @@
+use std::path::PathBuf;
+
+use glam::{Mat4, Quat, Vec3};
+use serde::{Deserialize, Serialize};
+
+use super::asset_server::{Assets, Handle};
+use super::material::Material;
+use super::mesh::{Aabb, Mesh};
+
+/// Position, rotation and scale relative to the parent node, applied in that order: scale first.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Transform {
+
+    pub const IDENTITY: Self = Self { translation: Vec3::ZERO, rotation: Quat::IDENTITY, scale: Vec3::ONE };
+
+    pub fn from_translation(translation: Vec3) -> Self {
+        Self { translation, ..Self::IDENTITY }
+    }
+
+    pub fn from_rotation(rotation: Quat) -> Self {
+        Self { rotation, ..Self::IDENTITY }
+    }
+
+    pub fn from_scale(scale: Vec3) -> Self {
+        Self { scale, ..Self::IDENTITY }
+    }
+
+    /// Splits a matrix made of translation, rotation and non-negative scale back up.
+    pub fn from_matrix(matrix: Mat4) -> Self {
+
+        let (scale, rotation, translation) = matrix.to_scale_rotation_translation();
+        Self { translation, rotation, scale }
+    }
+
+    pub fn matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum LightKind {
+    /// Shines along the node's -Z axis from infinitely far away, like the sun.
+    Directional,
+    /// Shines in every direction from the node's position. Without a range the light never fully fades out.
+    Point { range: Option<f32> },
+    /// Shines in a cone along the node's -Z axis. Angles are in radians from the axis.
+    Spot { range: Option<f32>, inner_cone_angle: f32, outer_cone_angle: f32 },
+}
+
+/// Punctual light, modelled after glTF's `KHR_lights_punctual`. Positioned and aimed by its node.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Light {
+    pub kind: LightKind,
+    /// Linear RGB.
+    pub color: [f32; 3],
+    /// Lux for directional lights, candela for the others.
+    pub intensity: f32,
+}
+
+impl Default for Light {
+    fn default() -> Self {
+        Self {
+            kind: LightKind::Point { range: None },
+            color: [1.0; 3],
+            intensity: 1.0,
+        }
+    }
+}
+
+/// Refers to a node of a `Scene`. Stays invalid once the node is removed, even if its slot is reused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NodeId {
+    index: u32,
+    generation: u32,
+}
+
+/// Something placed in the scene, with whatever is attached to it.
+#[derive(Clone, Debug)]
+pub struct Node {
+    pub name: String,
+    pub mesh: Option<Handle<Mesh>>,
+    /// One per sub-mesh of `mesh`. Sub-meshes without one use `MainRenderer::default_material`.
+    pub materials: Vec<Handle<Material>>,
+    pub light: Option<Light>,
+    /// Model file the children were added from by `MainRenderer::load_model`, relative to the asset root.
+    pub model: Option<PathBuf>,
+
+    transform: Transform,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    world_matrix: Mat4,
+    /// Whether `transform` or the parent changed since `world_matrix` was computed.
+    dirty: bool,
+}
+
+impl Node {
+
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            mesh: None,
+            materials: Vec::new(),
+            light: None,
+            model: None,
+            transform: Transform::IDENTITY,
+            parent: None,
+            children: Vec::new(),
+            world_matrix: Mat4::IDENTITY,
+            dirty: true,
+        }
+    }
+
+    pub fn with_transform(mut self, transform: Transform) -> Self {
+        self.set_transform(transform);
+        self
+    }
+
+    pub fn with_mesh(mut self, mesh: Handle<Mesh>) -> Self {
+        self.mesh = Some(mesh);
+        self
+    }
+
+    pub fn with_materials(mut self, materials: Vec<Handle<Material>>) -> Self {
+        self.materials = materials;
+        self
+    }
+
+    pub fn with_light(mut self, light: Light) -> Self {
+        self.light = Some(light);
+        self
+    }
+
+    /// Relative to the parent.
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+        self.dirty = true;
+    }
+
+    /// Marks the node for `Scene::update_world_matrices`, whether or not anything gets changed.
+    pub fn transform_mut(&mut self) -> &mut Transform {
+        self.dirty = true;
+        &mut self.transform
+    }
+
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    pub fn children(&self) -> &[NodeId] {
+        &self.children
+    }
+
+    /// Local to world space, as of the last `Scene::update_world_matrices`.
+    pub fn world_matrix(&self) -> Mat4 {
+        self.world_matrix
+    }
+}
+
+struct Slot {
+    generation: u32,
+    node: Option<Node>,
+}
+
+/// Hierarchy of nodes, each transformed relative to its parent.
+///
+/// World matrices are cached: changing a transform only marks the node, and `update_world_matrices`
+/// recomputes the marked nodes and everything below them.
+#[derive(Default)]
+pub struct Scene {
+    slots: Vec<Slot>,
+    free_slots: Vec<u32>,
+    roots: Vec<NodeId>,
+}
+
+impl Scene {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `node` as the last child of `parent`, or as a root. Children already set on `node` are ignored.
+    ///
+    /// Panics if `parent` was removed.
+    pub fn add(&mut self, mut node: Node, parent: Option<NodeId>) -> NodeId {
+
+        if let Some(parent) = parent {
+            assert!(self.contains(parent), "Parent node {parent:?} isn't in the scene!");
+        }
+
+        node.parent = parent;
+        node.children.clear();
+        node.dirty = true;
+
+        let id = match self.free_slots.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                slot.node = Some(node);
+                NodeId { index, generation: slot.generation }
+            }
+            None => {
+                self.slots.push(Slot { generation: 0, node: Some(node) });
+                NodeId { index: self.slots.len() as u32 - 1, generation: 0 }
+            }
+        };
+
+        match parent {
+            Some(parent) => self.node_mut(parent).children.push(id),
+            None => self.roots.push(id),
+        }
+
+        id
+    }
+
+    /// Removes the node and everything below it, returning the node itself.
+    pub fn remove(&mut self, id: NodeId) -> Option<Node> {
+
+        if !self.contains(id) {
+            return None;
+        }
+
+        self.detach(id);
+
+        let mut removed = None;
+        let mut stack = vec![id];
+
+        while let Some(id) = stack.pop() {
+            let slot = &mut self.slots[id.index as usize];
+            let node = slot.node.take().expect("Children are always alive!");
+            slot.generation = slot.generation.wrapping_add(1);
+            self.free_slots.push(id.index);
+
+            stack.extend(&node.children);
+            removed.get_or_insert(node);
+        }
+
+        removed
+    }
+
+    /// Removes every node.
+    pub fn clear(&mut self) {
+
+        for (index, slot) in self.slots.iter_mut().enumerate() {
+            if slot.node.take().is_some() {
+                slot.generation = slot.generation.wrapping_add(1);
+                self.free_slots.push(index as u32);
+            }
+        }
+
+        self.roots.clear();
+    }
+
+    /// Moves the node under `parent`, or makes it a root. Its local transform is kept, so it moves
+    /// along with its new parent. Returns `false`, changing nothing, if either node isn't in the
+    /// scene or `parent` is below the node.
+    pub fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>) -> bool {
+
+        if !self.contains(id) {
+            return false;
+        }
+
+        if let Some(parent) = parent
+            && (!self.contains(parent) || parent == id || self.ancestors(parent).any(|ancestor| ancestor == id))
+        {
+            return false;
+        }
+
+        self.detach(id);
+
+        let node = self.node_mut(id);
+        node.parent = parent;
+        node.dirty = true;
+
+        match parent {
+            Some(parent) => self.node_mut(parent).children.push(id),
+            None => self.roots.push(id),
+        }
+
+        true
+    }
+
+    pub fn contains(&self, id: NodeId) -> bool {
+        self.get(id).is_some()
+    }
+
+    pub fn get(&self, id: NodeId) -> Option<&Node> {
+
+        self.slots
+            .get(id.index as usize)
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.node.as_ref())
+    }
+
+    pub fn get_mut(&mut self, id: NodeId) -> Option<&mut Node> {
+
+        self.slots
+            .get_mut(id.index as usize)
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.node.as_mut())
+    }
+
+    /// The first node called `name`, depth first.
+    pub fn find(&self, name: &str) -> Option<NodeId> {
+        self.iter_depth_first().find(|(_, node)| node.name == name).map(|(id, _)| id)
+    }
+
+    /// Nodes without a parent, in the order they were added.
+    pub fn roots(&self) -> &[NodeId] {
+        &self.roots
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.free_slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Every node, parents before their children and siblings in order.
+    pub fn iter_depth_first(&self) -> impl Iterator<Item = (NodeId, &Node)> {
+
+        let mut stack: Vec<NodeId> = self.roots.iter().rev().copied().collect();
+
+        std::iter::from_fn(move || {
+            let id = stack.pop()?;
+            let node = self.get(id).expect("The hierarchy only refers to live nodes!");
+            stack.extend(node.children.iter().rev());
+            Some((id, node))
+        })
+    }
+
+    /// The node's parent, its parent and so on up to the root.
+    pub fn ancestors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        std::iter::successors(self.get(id).and_then(Node::parent), |&id| self.get(id).and_then(Node::parent))
+    }
+
+    /// Recomputes the world matrices of nodes whose transform or parent changed, and of everything below
+    /// them. Returns how many were recomputed.
+    pub fn update_world_matrices(&mut self) -> usize {
+
+        let mut updated = 0;
+        let mut stack: Vec<(NodeId, Mat4, bool)> = self.roots.iter().map(|&id| (id, Mat4::IDENTITY, false)).collect();
+
+        while let Some((id, parent_matrix, parent_changed)) = stack.pop() {
+
+            let node = self.node_mut(id);
+            let changed = parent_changed || node.dirty;
+
+            if changed {
+                node.world_matrix = parent_matrix * node.transform.matrix();
+                node.dirty = false;
+                updated += 1;
+            }
+
+            let world_matrix = node.world_matrix;
+            stack.extend(node.children.iter().map(|&child| (child, world_matrix, changed)));
+        }
+
+        updated
+    }
+
+    /// World space bounds of every node's mesh, `None` when there's nothing with vertices.
+    /// Uses the world matrices of the last `update_world_matrices`.
+    pub fn bounds(&self, meshes: &Assets<Mesh>) -> Option<Aabb> {
+
+        self.iter_depth_first()
+            .filter_map(|(_, node)| {
+                let aabb = meshes.get(node.mesh.as_ref()?)?.aabb?;
+                Some(aabb.transformed(node.world_matrix))
+            })
+            .reduce(Aabb::union)
+    }
+
+    fn node_mut(&mut self, id: NodeId) -> &mut Node {
+        self.get_mut(id).expect("The hierarchy only refers to live nodes!")
+    }
+
+    /// Unlinks the node from its parent or the roots, leaving it dangling.
+    fn detach(&mut self, id: NodeId) {
+
+        let siblings = match self.get(id).and_then(Node::parent) {
+            Some(parent) => &mut self.node_mut(parent).children,
+            None => &mut self.roots,
+        };
+        siblings.retain(|&sibling| sibling != id);
+    }
+}
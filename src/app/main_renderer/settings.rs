@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+/// Renderer options that are saved along with a scene.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RendererSettings {
+    /// Linear RGBA the frame starts out as, visible wherever nothing is drawn.
+    pub clear_color: [f32; 4],
+}
+
+impl Default for RendererSettings {
+    fn default() -> Self {
+        Self { clear_color: [0.5, 0.5, 0.5, 1.0] }
+    }
+}
@@ -0,0 +1,302 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use egui_wgpu::wgpu::naga;
+
+use crate::error::{CandleError, CandleResult};
+
+/// Shaders compiled into the binary, so the renderer works without the source tree.
+/// Includes are looked up here first and on disk second.
+pub const EMBEDDED_SHADERS: &[(&str, &str)] = &[
+    ("shader.wgsl", include_str!("../../shaders/shader.wgsl")),
+];
+
+/// Where a line of preprocessed output originally came from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SourceLocation {
+    pub file: PathBuf,
+    /// 1-based, like in editors.
+    pub line: usize,
+}
+
+pub struct PreprocessedShader {
+    pub source: String,
+    /// Every file that ended up in `source`, starting with the entry point.
+    pub files: Vec<PathBuf>,
+    line_map: Vec<SourceLocation>,
+}
+
+impl PreprocessedShader {
+
+    /// Maps a 1-based line of the preprocessed source back to its original file and line.
+    pub fn locate(&self, line: usize) -> Option<&SourceLocation> {
+        self.line_map.get(line.checked_sub(1)?)
+    }
+
+    /// Parses and validates the shader with naga, reporting errors at their original file and line.
+    pub fn validate(&self) -> CandleResult<naga::Module> {
+
+        let module = naga::front::wgsl::parse_str(&self.source).map_err(|error| {
+            let labels = error.labels().map(|(span, label)| (span, label.to_owned())).collect::<Vec<_>>();
+            CandleError::ShaderCompile(self.format_error(error.message(), &labels))
+        })?;
+
+        naga::valid::Validator::new(naga::valid::ValidationFlags::all(), naga::valid::Capabilities::all())
+            .validate(&module)
+            .map_err(|error| {
+                let labels = error.spans().cloned().collect::<Vec<_>>();
+                let mut message = error.as_inner().to_string();
+                let mut source: &dyn std::error::Error = error.as_inner();
+                while let Some(next) = source.source() {
+                    let _ = write!(message, ": {next}");
+                    source = next;
+                }
+                CandleError::ShaderCompile(self.format_error(&message, &labels))
+            })?;
+
+        Ok(module)
+    }
+
+    fn format_error(&self, message: &str, labels: &[(naga::Span, String)]) -> String {
+
+        let mut output = message.to_owned();
+
+        for (span, label) in labels {
+
+            let location = span.location(&self.source);
+            let position = match self.locate(location.line_number as usize) {
+                Some(original) => format!("{}:{}:{}", original.file.display(), original.line, location.line_position),
+                None => format!("<preprocessed>:{}:{}", location.line_number, location.line_position),
+            };
+
+            let _ = write!(output, "\n  --> {position}");
+            if !label.is_empty() {
+                let _ = write!(output, ": {label}");
+            }
+        }
+
+        output
+    }
+}
+
+/// Expands `#include "file.wgsl"`, `#define`/`#undef` and `#ifdef`/`#ifndef`/`#else`/`#endif`
+/// before handing WGSL to naga, keeping track of where every output line came from.
+///
+/// Every file is included at most once, so shared structs can be included from several places.
+/// Defines with a value are substituted wherever their name appears as a whole word.
+pub struct ShaderPreprocessor {
+    /// Directory includes are resolved against when they aren't embedded or relative to the including file.
+    root: PathBuf,
+    defines: HashMap<String, String>,
+    prefer_disk: bool,
+}
+
+struct ConditionalBlock {
+    parent_active: bool,
+    condition: bool,
+    in_else: bool,
+}
+
+impl ConditionalBlock {
+    fn is_active(&self) -> bool {
+        self.parent_active && (self.condition != self.in_else)
+    }
+}
+
+impl ShaderPreprocessor {
+
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+
+        Self {
+            root: root.into(),
+            defines: HashMap::new(),
+            prefer_disk: false,
+        }
+    }
+
+    /// Preprocessor for the shaders shipped in `src/shaders`.
+    pub fn builtin() -> Self {
+        Self::new(concat!(env!("CARGO_MANIFEST_DIR"), "/src/shaders"))
+    }
+
+    /// Reads files from disk even when an embedded copy exists, e.g. for hot reloading.
+    pub fn prefer_disk(mut self, prefer_disk: bool) -> Self {
+        self.prefer_disk = prefer_disk;
+        self
+    }
+
+    pub fn define(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.defines.insert(name.into(), value.into());
+        self
+    }
+
+    pub fn process(&self, entry: impl AsRef<Path>) -> CandleResult<PreprocessedShader> {
+
+        let mut output = PreprocessedShader {
+            source: String::new(),
+            files: Vec::new(),
+            line_map: Vec::new(),
+        };
+
+        let mut defines = self.defines.clone();
+        let mut included = HashSet::new();
+
+        let entry = self.root.join(entry);
+        self.process_file(&entry, &mut defines, &mut included, &mut output)?;
+
+        Ok(output)
+    }
+
+    fn read(&self, path: &Path) -> CandleResult<String> {
+
+        let embedded = path
+            .strip_prefix(&self.root)
+            .ok()
+            .and_then(|relative| EMBEDDED_SHADERS.iter().find(|(name, _)| Path::new(name) == relative))
+            .map(|(_, source)| source.to_string());
+
+        match embedded {
+            Some(source) if !self.prefer_disk || !path.exists() => Ok(source),
+            _ => std::fs::read_to_string(path)
+                .map_err(|source| CandleError::ShaderRead { path: path.to_owned(), source }),
+        }
+    }
+
+    fn process_file(
+        &self,
+        path: &Path,
+        defines: &mut HashMap<String, String>,
+        included: &mut HashSet<PathBuf>,
+        output: &mut PreprocessedShader,
+    ) -> CandleResult<()> {
+
+        if !included.insert(path.to_owned()) {
+            return Ok(());
+        }
+
+        let source = self.read(path)?;
+        output.files.push(path.to_owned());
+
+        let error = |line: usize, message: String| CandleError::ShaderPreprocess {
+            path: path.to_owned(),
+            line,
+            message,
+        };
+
+        let mut conditionals: Vec<ConditionalBlock> = Vec::new();
+
+        for (index, line) in source.lines().enumerate() {
+
+            let line_number = index + 1;
+            let is_active = conditionals.last().is_none_or(ConditionalBlock::is_active);
+            let trimmed = line.trim_start();
+
+            if let Some(directive) = trimmed.strip_prefix('#') {
+
+                let mut parts = directive.splitn(2, char::is_whitespace);
+                let keyword = parts.next().unwrap_or_default();
+                let argument = parts.next().unwrap_or_default().trim();
+
+                match keyword {
+                    "ifdef" | "ifndef" => {
+                        let is_defined = defines.contains_key(argument);
+                        conditionals.push(ConditionalBlock {
+                            parent_active: is_active,
+                            condition: is_defined == (keyword == "ifdef"),
+                            in_else: false,
+                        });
+                    }
+                    "else" => match conditionals.last_mut() {
+                        Some(block) if !block.in_else => block.in_else = true,
+                        _ => return Err(error(line_number, "#else without a matching #ifdef".to_owned())),
+                    },
+                    "endif" => {
+                        if conditionals.pop().is_none() {
+                            return Err(error(line_number, "#endif without a matching #ifdef".to_owned()));
+                        }
+                    }
+                    _ if !is_active => {}
+                    "define" => {
+                        let mut parts = argument.splitn(2, char::is_whitespace);
+                        let name = parts.next().unwrap_or_default();
+                        if name.is_empty() {
+                            return Err(error(line_number, "#define needs a name".to_owned()));
+                        }
+                        defines.insert(name.to_owned(), parts.next().unwrap_or_default().trim().to_owned());
+                    }
+                    "undef" => {
+                        defines.remove(argument);
+                    }
+                    "include" => {
+                        let Some(file) = argument.strip_prefix('"').and_then(|rest| rest.strip_suffix('"')) else {
+                            return Err(error(line_number, format!("expected #include \"file.wgsl\", found #include {argument}")));
+                        };
+
+                        // Relative to the including file first, then to the root
+                        let relative = path.parent().unwrap_or(&self.root).join(file);
+                        let include_path = if relative.exists() || self.is_embedded(&relative) {
+                            relative
+                        } else {
+                            self.root.join(file)
+                        };
+
+                        self.process_file(&include_path, defines, included, output)?;
+                    }
+                    _ => return Err(error(line_number, format!("unknown directive #{keyword}"))),
+                }
+
+                continue;
+            }
+
+            if !is_active {
+                continue;
+            }
+
+            output.source.push_str(&substitute_defines(line, defines));
+            output.source.push('\n');
+            output.line_map.push(SourceLocation { file: path.to_owned(), line: line_number });
+        }
+
+        if !conditionals.is_empty() {
+            return Err(error(source.lines().count(), "#ifdef without a matching #endif".to_owned()));
+        }
+
+        Ok(())
+    }
+
+    fn is_embedded(&self, path: &Path) -> bool {
+        path.strip_prefix(&self.root)
+            .is_ok_and(|relative| EMBEDDED_SHADERS.iter().any(|(name, _)| Path::new(name) == relative))
+    }
+}
+
+fn substitute_defines(line: &str, defines: &HashMap<String, String>) -> String {
+
+    if defines.values().all(String::is_empty) {
+        return line.to_owned();
+    }
+
+    fn flush(word: &mut String, output: &mut String, defines: &HashMap<String, String>) {
+        match defines.get(word.as_str()) {
+            Some(value) if !value.is_empty() => output.push_str(value),
+            _ => output.push_str(word),
+        }
+        word.clear();
+    }
+
+    let mut output = String::with_capacity(line.len());
+    let mut word = String::new();
+
+    for character in line.chars() {
+        if character.is_alphanumeric() || character == '_' {
+            word.push(character);
+        } else {
+            flush(&mut word, &mut output, defines);
+            output.push(character);
+        }
+    }
+    flush(&mut word, &mut output, defines);
+
+    output
+}
@@ -0,0 +1,66 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Polls the modification times of a shader and the files it includes.
+pub struct ShaderWatcher {
+    entry: PathBuf,
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+    last_check: Option<Instant>,
+}
+
+impl ShaderWatcher {
+
+    const CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+    pub fn new(entry: PathBuf) -> Self {
+
+        Self {
+            files: vec![(entry.clone(), None)],
+            entry,
+            last_check: None,
+        }
+    }
+
+    pub fn entry(&self) -> &Path {
+        &self.entry
+    }
+
+    /// Replaces the watched files, e.g. after the shader's includes changed.
+    pub fn watch(&mut self, files: Vec<PathBuf>) {
+
+        self.files = files
+            .into_iter()
+            .map(|path| {
+                let modified = Self::modified(&path);
+                (path, modified)
+            })
+            .collect();
+    }
+
+    /// Whether any watched file changed since the last call. The first call always returns true.
+    pub fn poll(&mut self) -> bool {
+
+        if let Some(last_check) = self.last_check
+            && last_check.elapsed() < Self::CHECK_INTERVAL
+        {
+            return false;
+        }
+        let is_first_check = self.last_check.is_none();
+        self.last_check = Some(Instant::now());
+
+        let mut changed = is_first_check;
+        for (path, last_modified) in self.files.iter_mut() {
+            let modified = Self::modified(path);
+            if modified != *last_modified {
+                *last_modified = modified;
+                changed = true;
+            }
+        }
+
+        changed
+    }
+
+    fn modified(path: &Path) -> Option<SystemTime> {
+        std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+    }
+}
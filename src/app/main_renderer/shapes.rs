@@ -0,0 +1,573 @@
+//! Procedurally generated meshes, centered on the origin with +Y up, for trying out lighting and materials.
+//!
+//! Every shape has normals, MikkTSpace tangents and UVs with the origin at the top left.
+//! Vertices are duplicated along UV seams and hard edges, so the index buffers are only
+//! watertight once vertices at the same position are welded.
+
+use std::collections::HashMap;
+use std::f32::consts::{PI, TAU};
+
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+use super::model::{MeshData, PrimitiveData};
+
+/// A procedural mesh. The fields of each implementor configure its size and subdivisions.
+pub trait Shape {
+
+    fn name(&self) -> &'static str;
+
+    fn primitive(&self) -> PrimitiveData;
+
+    fn mesh(&self) -> MeshData {
+
+        MeshData {
+            name: self.name().to_owned(),
+            primitives: vec![self.primitive()],
+        }
+    }
+}
+
+/// Square in the XZ plane facing +Y.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Plane {
+    /// Along X and Z.
+    pub size: [f32; 2],
+    /// Cuts along each side, 0 gives a single quad.
+    pub subdivisions: u32,
+}
+
+impl Default for Plane {
+    fn default() -> Self {
+        Self { size: [1.0; 2], subdivisions: 0 }
+    }
+}
+
+impl Shape for Plane {
+
+    fn name(&self) -> &'static str {
+        "Plane"
+    }
+
+    fn primitive(&self) -> PrimitiveData {
+
+        let quads = self.subdivisions + 1;
+        let [width, depth] = self.size;
+
+        let mut primitive = PrimitiveData::default();
+        add_surface(&mut primitive, quads, quads, |u, v| {
+            (Vec3::new((u - 0.5) * width, 0.0, (v - 0.5) * depth), Vec3::Y, [u, v])
+        });
+
+        finish(primitive)
+    }
+}
+
+/// Axis-aligned box, every face mapped to the whole texture.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Cube {
+    pub size: [f32; 3],
+    /// Cuts along each edge of every face.
+    pub subdivisions: u32,
+}
+
+impl Default for Cube {
+    fn default() -> Self {
+        Self { size: [1.0; 3], subdivisions: 0 }
+    }
+}
+
+impl Shape for Cube {
+
+    fn name(&self) -> &'static str {
+        "Cube"
+    }
+
+    fn primitive(&self) -> PrimitiveData {
+
+        // Normal, then the directions of the texture's right and up on that face
+        const FACES: [(Vec3, Vec3, Vec3); 6] = [
+            (Vec3::X, Vec3::NEG_Z, Vec3::Y),
+            (Vec3::NEG_X, Vec3::Z, Vec3::Y),
+            (Vec3::Y, Vec3::X, Vec3::NEG_Z),
+            (Vec3::NEG_Y, Vec3::X, Vec3::Z),
+            (Vec3::Z, Vec3::X, Vec3::Y),
+            (Vec3::NEG_Z, Vec3::NEG_X, Vec3::Y),
+        ];
+
+        let quads = self.subdivisions + 1;
+        let mut primitive = PrimitiveData::default();
+
+        for (normal, right, up) in FACES {
+            add_surface(&mut primitive, quads, quads, |u, v| {
+                let position = (0.5 * normal + (u - 0.5) * right + (0.5 - v) * up) * Vec3::from(self.size);
+                (position, normal, [u, v])
+            });
+        }
+
+        finish(primitive)
+    }
+}
+
+/// Sphere made of latitude and longitude lines, with the texture wrapped around it equirectangularly.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UvSphere {
+    pub radius: f32,
+    /// Slices around the Y axis.
+    pub sectors: u32,
+    /// Slices from pole to pole.
+    pub stacks: u32,
+}
+
+impl Default for UvSphere {
+    fn default() -> Self {
+        Self { radius: 0.5, sectors: 32, stacks: 16 }
+    }
+}
+
+impl Shape for UvSphere {
+
+    fn name(&self) -> &'static str {
+        "UV Sphere"
+    }
+
+    fn primitive(&self) -> PrimitiveData {
+
+        let mut primitive = PrimitiveData::default();
+        add_surface(&mut primitive, self.sectors.max(3), self.stacks.max(2), |u, v| {
+            let normal = sphere_normal(u, v);
+            (normal * self.radius, normal, [u, v])
+        });
+
+        finish(primitive)
+    }
+}
+
+/// Subdivided icosahedron, its triangles are much more even than a `UvSphere`'s.
+/// UVs are the same equirectangular projection, except that U goes a little past 1 on triangles
+/// crossing the seam, so textures on it need to repeat.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Icosphere {
+    pub radius: f32,
+    /// How often every triangle is split into four, each one multiplies the triangle count by 4.
+    pub subdivisions: u32,
+}
+
+impl Default for Icosphere {
+    fn default() -> Self {
+        Self { radius: 0.5, subdivisions: 3 }
+    }
+}
+
+impl Shape for Icosphere {
+
+    fn name(&self) -> &'static str {
+        "Icosphere"
+    }
+
+    fn primitive(&self) -> PrimitiveData {
+
+        let t = (1.0 + 5.0_f32.sqrt()) / 2.0;
+        let mut positions: Vec<Vec3> = [
+            Vec3::new(-1.0, t, 0.0),
+            Vec3::new(1.0, t, 0.0),
+            Vec3::new(-1.0, -t, 0.0),
+            Vec3::new(1.0, -t, 0.0),
+            Vec3::new(0.0, -1.0, t),
+            Vec3::new(0.0, 1.0, t),
+            Vec3::new(0.0, -1.0, -t),
+            Vec3::new(0.0, 1.0, -t),
+            Vec3::new(t, 0.0, -1.0),
+            Vec3::new(t, 0.0, 1.0),
+            Vec3::new(-t, 0.0, -1.0),
+            Vec3::new(-t, 0.0, 1.0),
+        ]
+        .map(Vec3::normalize)
+        .to_vec();
+
+        let mut triangles: Vec<[u32; 3]> = vec![
+            [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+            [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+            [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+            [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+        ];
+
+        for _ in 0..self.subdivisions {
+
+            let mut midpoints: HashMap<(u32, u32), u32> = HashMap::new();
+            let mut midpoint = |a: u32, b: u32| {
+                *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                    positions.push((positions[a as usize] + positions[b as usize]).normalize());
+                    positions.len() as u32 - 1
+                })
+            };
+
+            triangles = triangles
+                .iter()
+                .flat_map(|&[a, b, c]| {
+                    let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+                    [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+                })
+                .collect();
+        }
+
+        // Vertices along the seam and at the poles need different UVs depending on the triangle
+        let mut primitive = PrimitiveData::default();
+        let mut welded: HashMap<[u32; 5], u32> = HashMap::new();
+
+        for triangle in triangles {
+
+            let normals = triangle.map(|index| positions[index as usize]);
+            let mut uvs = normals.map(sphere_uv);
+
+            // Facing outwards, triangles are clockwise in UV space unless they wrap around the seam.
+            // The U at the poles is arbitrary, but triangles there are small.
+            let pole = normals.iter().position(|normal| normal.x.abs() < 1e-6 && normal.z.abs() < 1e-6);
+            let crosses_seam = match pole {
+                Some(pole) => (uvs[(pole + 1) % 3][0] - uvs[(pole + 2) % 3][0]).abs() > 0.5,
+                None => {
+                    let [a, b, c] = uvs;
+                    (b[0] - a[0]) * (c[1] - a[1]) - (c[0] - a[0]) * (b[1] - a[1]) > 0.0
+                }
+            };
+
+            if crosses_seam {
+                for uv in &mut uvs {
+                    if uv[0] < 0.5 {
+                        uv[0] += 1.0;
+                    }
+                }
+            }
+
+            if let Some(pole) = pole {
+                uvs[pole][0] = (uvs[(pole + 1) % 3][0] + uvs[(pole + 2) % 3][0]) / 2.0;
+            }
+
+            for (normal, uv) in normals.into_iter().zip(uvs) {
+
+                let key = [normal.x, normal.y, normal.z, uv[0], uv[1]].map(f32::to_bits);
+                let index = *welded.entry(key).or_insert_with(|| {
+                    primitive.positions.push((normal * self.radius).to_array());
+                    primitive.normals.push(normal.to_array());
+                    primitive.uvs.push(uv);
+                    primitive.positions.len() as u32 - 1
+                });
+                primitive.indices.push(index);
+            }
+        }
+
+        finish(primitive)
+    }
+}
+
+/// Closed cylinder around the Y axis.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Cylinder {
+    pub radius: f32,
+    pub height: f32,
+    /// Slices around the Y axis.
+    pub sectors: u32,
+    /// Slices along the height.
+    pub stacks: u32,
+}
+
+impl Default for Cylinder {
+    fn default() -> Self {
+        Self { radius: 0.5, height: 1.0, sectors: 32, stacks: 1 }
+    }
+}
+
+impl Shape for Cylinder {
+
+    fn name(&self) -> &'static str {
+        "Cylinder"
+    }
+
+    fn primitive(&self) -> PrimitiveData {
+
+        let (sectors, half_height) = (self.sectors.max(3), self.height / 2.0);
+
+        let mut primitive = PrimitiveData::default();
+        add_surface(&mut primitive, sectors, self.stacks.max(1), |u, v| {
+            let normal = ring_direction(u);
+            let position = normal * self.radius + Vec3::Y * (half_height - v * self.height);
+            (position, normal, [u, v])
+        });
+        add_cap(&mut primitive, self.radius, half_height, sectors, true);
+        add_cap(&mut primitive, self.radius, -half_height, sectors, false);
+
+        finish(primitive)
+    }
+}
+
+/// Cone around the Y axis, with its tip pointing up.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Cone {
+    /// Of the base.
+    pub radius: f32,
+    pub height: f32,
+    /// Slices around the Y axis.
+    pub sectors: u32,
+    /// Slices from the tip to the base.
+    pub stacks: u32,
+}
+
+impl Default for Cone {
+    fn default() -> Self {
+        Self { radius: 0.5, height: 1.0, sectors: 32, stacks: 1 }
+    }
+}
+
+impl Shape for Cone {
+
+    fn name(&self) -> &'static str {
+        "Cone"
+    }
+
+    fn primitive(&self) -> PrimitiveData {
+
+        let (sectors, half_height) = (self.sectors.max(3), self.height / 2.0);
+
+        let mut primitive = PrimitiveData::default();
+        add_surface(&mut primitive, sectors, self.stacks.max(1), |u, v| {
+            let outwards = ring_direction(u);
+            let position = outwards * self.radius * v + Vec3::Y * (half_height - v * self.height);
+            let normal = (outwards * self.height + Vec3::Y * self.radius).normalize();
+            (position, normal, [u, v])
+        });
+        add_cap(&mut primitive, self.radius, -half_height, sectors, false);
+
+        finish(primitive)
+    }
+}
+
+/// Cylinder with hemispheres at both ends, around the Y axis.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Capsule {
+    pub radius: f32,
+    /// Of the cylinder in between the hemispheres, the whole capsule is `length + 2 * radius` high.
+    pub length: f32,
+    /// Slices around the Y axis.
+    pub sectors: u32,
+    /// Slices from the pole to the equator of each hemisphere.
+    pub rings: u32,
+}
+
+impl Default for Capsule {
+    fn default() -> Self {
+        Self { radius: 0.25, length: 0.5, sectors: 32, rings: 8 }
+    }
+}
+
+impl Shape for Capsule {
+
+    fn name(&self) -> &'static str {
+        "Capsule"
+    }
+
+    fn primitive(&self) -> PrimitiveData {
+
+        let (sectors, rings, half_length) = (self.sectors.max(3), self.rings.max(1), self.length / 2.0);
+
+        // V runs along the outline, evenly spread by length
+        let hemisphere_length = self.radius * PI / 2.0;
+        let total_length = 2.0 * hemisphere_length + self.length;
+        let hemisphere_v = hemisphere_length / total_length;
+
+        let mut primitive = PrimitiveData::default();
+
+        add_surface(&mut primitive, sectors, rings, |u, v| {
+            let normal = sphere_normal(u, v / 2.0);
+            let position = normal * self.radius + Vec3::Y * half_length;
+            (position, normal, [u, v * hemisphere_v])
+        });
+        add_surface(&mut primitive, sectors, 1, |u, v| {
+            let normal = ring_direction(u);
+            let position = normal * self.radius + Vec3::Y * (half_length - v * self.length);
+            (position, normal, [u, hemisphere_v + v * (1.0 - 2.0 * hemisphere_v)])
+        });
+        add_surface(&mut primitive, sectors, rings, |u, v| {
+            let normal = sphere_normal(u, 0.5 + v / 2.0);
+            let position = normal * self.radius - Vec3::Y * half_length;
+            (position, normal, [u, 1.0 - hemisphere_v + v * hemisphere_v])
+        });
+
+        finish(primitive)
+    }
+}
+
+/// Ring lying in the XZ plane.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Torus {
+    /// From the center to the middle of the tube.
+    pub major_radius: f32,
+    /// Of the tube.
+    pub minor_radius: f32,
+    /// Slices around the Y axis.
+    pub major_segments: u32,
+    /// Slices around the tube.
+    pub minor_segments: u32,
+}
+
+impl Default for Torus {
+    fn default() -> Self {
+        Self { major_radius: 0.375, minor_radius: 0.125, major_segments: 48, minor_segments: 24 }
+    }
+}
+
+impl Shape for Torus {
+
+    fn name(&self) -> &'static str {
+        "Torus"
+    }
+
+    fn primitive(&self) -> PrimitiveData {
+
+        let mut primitive = PrimitiveData::default();
+
+        // V starts at the outside of the tube and goes down first
+        add_surface(&mut primitive, self.major_segments.max(3), self.minor_segments.max(3), |u, v| {
+            let outwards = ring_direction(u);
+            let (sin, cos) = (v * TAU).sin_cos();
+            let normal = outwards * cos - Vec3::Y * sin;
+            let position = outwards * self.major_radius + normal * self.minor_radius;
+            (position, normal, [u, v])
+        });
+
+        finish(primitive)
+    }
+}
+
+/// Any one of the shapes, e.g. to remember what generated a mesh.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ProceduralShape {
+    Plane(Plane),
+    Cube(Cube),
+    UvSphere(UvSphere),
+    Icosphere(Icosphere),
+    Cylinder(Cylinder),
+    Cone(Cone),
+    Capsule(Capsule),
+    Torus(Torus),
+}
+
+impl ProceduralShape {
+
+    fn shape(&self) -> &dyn Shape {
+
+        match self {
+            Self::Plane(shape) => shape,
+            Self::Cube(shape) => shape,
+            Self::UvSphere(shape) => shape,
+            Self::Icosphere(shape) => shape,
+            Self::Cylinder(shape) => shape,
+            Self::Cone(shape) => shape,
+            Self::Capsule(shape) => shape,
+            Self::Torus(shape) => shape,
+        }
+    }
+}
+
+impl Shape for ProceduralShape {
+
+    fn name(&self) -> &'static str {
+        self.shape().name()
+    }
+
+    fn primitive(&self) -> PrimitiveData {
+        self.shape().primitive()
+    }
+}
+
+/// Adds a grid of `columns` by `rows` quads, with `vertex(u, v)` returning the position, normal and UV
+/// at the given grid coordinates between 0 and 1. The quads face the side of `∂p/∂v × ∂p/∂u`.
+///
+/// Triangles collapsed to a line or a point, like the ones around a sphere's poles, are left out.
+fn add_surface(
+    primitive: &mut PrimitiveData,
+    columns: u32,
+    rows: u32,
+    vertex: impl Fn(f32, f32) -> (Vec3, Vec3, [f32; 2]),
+) {
+
+    let first = primitive.positions.len() as u32;
+
+    for row in 0..=rows {
+        for column in 0..=columns {
+            let (position, normal, uv) = vertex(column as f32 / columns as f32, row as f32 / rows as f32);
+            primitive.positions.push(position.to_array());
+            primitive.normals.push(normal.to_array());
+            primitive.uvs.push(uv);
+        }
+    }
+
+    let index = |column: u32, row: u32| first + row * (columns + 1) + column;
+
+    for row in 0..rows {
+        for column in 0..columns {
+
+            let (top_left, top_right) = (index(column, row), index(column + 1, row));
+            let (bottom_left, bottom_right) = (index(column, row + 1), index(column + 1, row + 1));
+
+            for triangle in [[top_left, bottom_left, bottom_right], [top_left, bottom_right, top_right]] {
+                if !is_degenerate(primitive, triangle) {
+                    primitive.indices.extend(triangle);
+                }
+            }
+        }
+    }
+}
+
+/// Disc closing off a `Cylinder` or `Cone` at `height`, facing up or down, with the texture projected onto it from above.
+fn add_cap(primitive: &mut PrimitiveData, radius: f32, height: f32, sectors: u32, facing_up: bool) {
+
+    let normal = if facing_up { Vec3::Y } else { Vec3::NEG_Y };
+
+    add_surface(primitive, sectors, 1, |u, v| {
+        let distance = if facing_up { v } else { 1.0 - v };
+        let outwards = ring_direction(u);
+        let position = outwards * radius * distance + Vec3::Y * height;
+        (position, normal, [(outwards.x * distance + 1.0) / 2.0, (outwards.z * distance + 1.0) / 2.0])
+    });
+}
+
+fn is_degenerate(primitive: &PrimitiveData, [a, b, c]: [u32; 3]) -> bool {
+
+    let [a, b, c] = [a, b, c].map(|index| Vec3::from(primitive.positions[index as usize]));
+    let (edge_1, edge_2) = (b - a, c - a);
+
+    edge_1.cross(edge_2).length_squared() <= 1e-10 * edge_1.length_squared() * edge_2.length_squared()
+}
+
+fn finish(mut primitive: PrimitiveData) -> PrimitiveData {
+
+    primitive.generate_tangents();
+    primitive
+}
+
+/// Direction from the center of a ring around the Y axis, starting at +Z at `u = 0` and turning towards +X.
+fn ring_direction(u: f32) -> Vec3 {
+
+    let (sin, cos) = (u * TAU).sin_cos();
+    Vec3::new(sin, 0.0, cos)
+}
+
+/// Point on the unit sphere, `u` around the Y axis like `ring_direction` and `v` from the north to the south pole.
+fn sphere_normal(u: f32, v: f32) -> Vec3 {
+
+    let (sin, cos) = (v * PI).sin_cos();
+    ring_direction(u) * sin + Vec3::Y * cos
+}
+
+/// Inverse of `sphere_normal`, with `u` in `[0, 1)`.
+fn sphere_uv(normal: Vec3) -> [f32; 2] {
+    [(normal.x.atan2(normal.z) / TAU).rem_euclid(1.0), normal.y.clamp(-1.0, 1.0).acos() / PI]
+}
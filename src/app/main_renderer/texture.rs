@@ -1,26 +1,94 @@
-use std::env;
+use std::path::Path;
 
 use egui_wgpu::wgpu::{self, BindGroup, BindGroupLayout, Device, Sampler, TextureView};
-use image::GenericImageView;
+use image::RgbaImage;
+
+use crate::error::{CandleError, CandleResult};
+
+/// Built into the binary, used while the real texture is still loading.
+const PLACEHOLDER_PNG: &[u8] = include_bytes!("../../resources/Checker.png");
+
+/// Decoded image kept on the CPU, so its GPU texture can be recreated at any time.
+#[derive(Clone)]
+pub struct TextureData {
+    pub name: String,
+    pub image: RgbaImage,
+}
+
+impl TextureData {
+
+    pub fn load(path: &Path) -> CandleResult<Self> {
+
+        let bytes = std::fs::read(path)
+            .map_err(|source| CandleError::TextureRead { path: path.to_owned(), source })?;
+
+        Self::decode(&bytes, path)
+    }
+
+    /// The built-in checker texture.
+    pub fn placeholder() -> CandleResult<Self> {
+        Self::decode(PLACEHOLDER_PNG, Path::new("Placeholder.png"))
+    }
+
+    /// A single pixel of `color`, e.g. white for materials without a texture.
+    pub fn solid(name: impl Into<String>, color: [u8; 4]) -> Self {
+        Self {
+            name: name.into(),
+            image: RgbaImage::from_pixel(1, 1, image::Rgba(color)),
+        }
+    }
+
+    fn decode(bytes: &[u8], path: &Path) -> CandleResult<Self> {
+
+        let image = image::load_from_memory(bytes)
+            .map_err(|source| CandleError::TextureDecode { path: path.to_owned(), source })?;
+
+        Ok(Self {
+            name: Self::name_from_path(path),
+            image: image.to_rgba8(),
+        })
+    }
+
+    fn name_from_path(path: &Path) -> String {
+        path.file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+}
 
 pub struct Texture {
-    pub name: &'static str,
+    pub data: TextureData,
     pub view: TextureView,
     pub sampler: Sampler,
-    pub bind_group_layout: Option<BindGroupLayout>,
 }
 
 impl Texture {
 
-    pub fn new(path: &'static str, texture_name: &'static str, device: &Device, queue: &wgpu::Queue) -> Self {
+    pub fn new(data: TextureData, device: &Device, queue: &wgpu::Queue) -> Self {
+
+        let (view, sampler) = Self::upload(&data, device, queue);
 
-        let path = env!("CARGO_MANIFEST_DIR").to_owned() + "/src/resources/" + path;
+        Self {
+            data,
+            view,
+            sampler,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.data.name
+    }
 
-        let bytes = std::fs::read(path).unwrap();
-        let image = image::load_from_memory(bytemuck::cast_slice(&bytes)).unwrap();
-        let rgba = image.to_rgba8();
+    /// Uploads the retained image again, e.g. on a new device after the old one was lost.
+    pub fn recreate(&mut self, device: &Device, queue: &wgpu::Queue) {
+        (self.view, self.sampler) = Self::upload(&self.data, device, queue);
+    }
+
+    fn upload(data: &TextureData, device: &Device, queue: &wgpu::Queue) -> (TextureView, Sampler) {
+
+        let rgba = &data.image;
 
-        let dimensions = image.dimensions();
+        let dimensions = rgba.dimensions();
 
         let texture_size = wgpu::Extent3d{
             width: dimensions.0,
@@ -29,7 +97,7 @@ impl Texture {
         };
 
         let texture_descriptor = &wgpu::TextureDescriptor {
-            label: Some(texture_name),
+            label: Some(data.name.as_str()),
             size: texture_size,
             mip_level_count: 1,
             sample_count: 1,
@@ -61,7 +129,7 @@ impl Texture {
                 origin: wgpu::Origin3d::ZERO,
                 aspect: wgpu::TextureAspect::All,
             },
-            &rgba,
+            rgba,
             wgpu::ImageDataLayout {
                 offset: 0,
                 bytes_per_row: Some(4 * dimensions.0),
@@ -70,49 +138,43 @@ impl Texture {
             texture_size,
         );
 
-        Self {
-            name: texture_name,
-            view: texture_view,
-            sampler,
-            bind_group_layout: None,
-        }
-       
+        (texture_view, sampler)
     }
 
-    pub fn create_bind_group(&mut self, device: &Device) -> BindGroup {
-
-        let texture_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Texture {
-                            multisampled: false,
-                            view_dimension: wgpu::TextureViewDimension::D2,
-                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                        count: None,
+    /// Layout shared by every texture bind group: the texture at binding 0 and its sampler at binding 1.
+    pub fn create_bind_group_layout(device: &Device) -> BindGroupLayout {
+
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
                     },
-                ],
-                label: Some("texture_bind_group_layout"),
-            });
-        
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+            label: Some("texture_bind_group_layout"),
+        })
+    }
 
-        
+    pub fn create_bind_group(&self, device: &Device, layout: &BindGroupLayout) -> BindGroup {
 
-        let bind_group_name = format!("{} bind group", self.name);
+        let bind_group_name = format!("{} bind group", self.name());
 
         let diffuse_bind_group_descriptor = &wgpu::BindGroupDescriptor {
 
             label: Some(bind_group_name.as_str()),
-            layout: &texture_bind_group_layout,
+            layout,
             entries: &[
                 wgpu::BindGroupEntry {
                     binding: 0,
@@ -125,9 +187,6 @@ impl Texture {
             ],
         };
 
-        let bind_group = device.create_bind_group(diffuse_bind_group_descriptor);
-        self.bind_group_layout = Some(texture_bind_group_layout);
-
-        return bind_group;
+        device.create_bind_group(diffuse_bind_group_descriptor)
     }
-}
\ No newline at end of file
+}
@@ -1,33 +1,250 @@
-use egui_wgpu::wgpu::{VertexAttribute, VertexBufferLayout, VertexStepMode};
+use std::fmt::Write;
 
+use egui_wgpu::wgpu::{BufferAddress, VertexAttribute, VertexBufferLayout, VertexFormat, VertexStepMode};
 
+
+/// Owned version of `VertexBufferLayout`, so layouts can be stored and compared, e.g. in pipeline keys.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct VertexLayout {
+    pub array_stride: BufferAddress,
+    pub step_mode: VertexStepMode,
+    pub attributes: Vec<VertexAttribute>,
+}
+
+impl VertexLayout {
+
+    pub fn as_buffer_layout(&self) -> VertexBufferLayout<'_> {
+
+        VertexBufferLayout {
+            array_stride: self.array_stride,
+            step_mode: self.step_mode,
+            attributes: &self.attributes,
+        }
+    }
+}
+
+impl From<VertexBufferLayout<'_>> for VertexLayout {
+
+    fn from(layout: VertexBufferLayout<'_>) -> Self {
+
+        Self {
+            array_stride: layout.array_stride,
+            step_mode: layout.step_mode,
+            attributes: layout.attributes.to_vec(),
+        }
+    }
+}
+
+
+/// What a vertex attribute means. Each one always has the same format and shader location,
+/// so a shader reads e.g. normals from the same `@location` whichever other attributes a mesh has.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum VertexAttributeKind {
+    Position,
+    Uv0,
+    Normal,
+    /// xyz is the tangent, w the handedness of the bitangent.
+    Tangent,
+    /// Linear RGBA.
+    Color,
+    Uv1,
+    /// Indices of the four joints influencing the vertex.
+    Joints,
+    Weights,
+}
+
+impl VertexAttributeKind {
+
+    pub const ALL: [Self; 8] = [
+        Self::Position,
+        Self::Uv0,
+        Self::Normal,
+        Self::Tangent,
+        Self::Color,
+        Self::Uv1,
+        Self::Joints,
+        Self::Weights,
+    ];
+
+    pub fn format(self) -> VertexFormat {
+
+        match self {
+            Self::Position | Self::Normal => VertexFormat::Float32x3,
+            Self::Uv0 | Self::Uv1 => VertexFormat::Float32x2,
+            Self::Tangent | Self::Color | Self::Weights => VertexFormat::Float32x4,
+            Self::Joints => VertexFormat::Uint16x4,
+        }
+    }
+
+    pub fn shader_location(self) -> u32 {
+
+        match self {
+            Self::Position => 0,
+            Self::Uv0 => 1,
+            Self::Normal => 2,
+            Self::Tangent => 3,
+            Self::Color => 4,
+            Self::Uv1 => 5,
+            Self::Joints => 6,
+            Self::Weights => 7,
+        }
+    }
+
+    /// Field name in the struct generated by `VertexStreams::wgsl_input`.
+    pub fn wgsl_name(self) -> &'static str {
+
+        match self {
+            Self::Position => "position",
+            Self::Uv0 => "uv",
+            Self::Normal => "normal",
+            Self::Tangent => "tangent",
+            Self::Color => "color",
+            Self::Uv1 => "uv_1",
+            Self::Joints => "joints",
+            Self::Weights => "weights",
+        }
+    }
+
+    pub fn wgsl_type(self) -> &'static str {
+
+        match self.format() {
+            VertexFormat::Float32x2 => "vec2<f32>",
+            VertexFormat::Float32x3 => "vec3<f32>",
+            VertexFormat::Uint16x4 => "vec4<u32>",
+            _ => "vec4<f32>",
+        }
+    }
+}
+
+/// Which attributes a mesh has and how they're split across vertex buffers.
+///
+/// ```
+/// use candle::app::main_renderer::vertex::{VertexAttributeKind::*, VertexStreams};
+///
+/// // Positions in their own buffer, e.g. for depth-only passes, everything else interleaved
+/// let streams = VertexStreams::new()
+///     .interleaved(&[Position])
+///     .interleaved(&[Uv0, Normal, Tangent]);
+///
+/// let layouts = streams.layouts();
+/// assert_eq!(layouts.len(), 2);
+/// assert_eq!(layouts[1].array_stride, 4 * (2 + 3 + 4));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct VertexStreams {
+    streams: Vec<Vec<VertexAttributeKind>>,
+}
+
+impl VertexStreams {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a vertex buffer holding all of `attributes`, one vertex after another.
+    pub fn interleaved(mut self, attributes: &[VertexAttributeKind]) -> Self {
+
+        debug_assert!(
+            attributes.iter().all(|attribute| !self.contains(*attribute)),
+            "Every vertex attribute can only be in one stream!"
+        );
+
+        self.streams.push(attributes.to_vec());
+        self
+    }
+
+    /// Adds a separate vertex buffer for each of `attributes`.
+    pub fn deinterleaved(self, attributes: &[VertexAttributeKind]) -> Self {
+        attributes.iter().fold(self, |streams, attribute| streams.interleaved(&[*attribute]))
+    }
+
+    /// Attributes of every vertex buffer, in buffer slot order.
+    pub fn streams(&self) -> &[Vec<VertexAttributeKind>] {
+        &self.streams
+    }
+
+    pub fn contains(&self, attribute: VertexAttributeKind) -> bool {
+        self.streams.iter().any(|stream| stream.contains(&attribute))
+    }
+
+    /// One layout per vertex buffer, with offsets packed tightly in the order attributes were added.
+    pub fn layouts(&self) -> Vec<VertexLayout> {
+
+        self.streams
+            .iter()
+            .map(|stream| {
+
+                let mut offset = 0;
+                let attributes = stream
+                    .iter()
+                    .map(|kind| {
+                        let attribute = VertexAttribute {
+                            format: kind.format(),
+                            offset,
+                            shader_location: kind.shader_location(),
+                        };
+                        offset += kind.format().size();
+                        attribute
+                    })
+                    .collect();
+
+                VertexLayout {
+                    array_stride: offset,
+                    step_mode: VertexStepMode::Vertex,
+                    attributes,
+                }
+            })
+            .collect()
+    }
+
+    /// WGSL struct with a field for every attribute at its shader location, to use as vertex shader input.
+    pub fn wgsl_input(&self, struct_name: &str) -> String {
+
+        let mut attributes: Vec<VertexAttributeKind> = self.streams.iter().flatten().copied().collect();
+        attributes.sort_by_key(|attribute| attribute.shader_location());
+
+        let mut wgsl = format!("struct {struct_name} {{\n");
+        for attribute in attributes {
+            let _ = writeln!(
+                wgsl,
+                "    @location({}) {}: {},",
+                attribute.shader_location(),
+                attribute.wgsl_name(),
+                attribute.wgsl_type()
+            );
+        }
+        wgsl.push_str("}\n");
+
+        wgsl
+    }
+}
+
+
+/// The renderer's standard vertex, everything interleaved in one buffer.
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Vertex {
     pub position: [f32; 3],
     pub uv: [f32; 2],
+    pub normal: [f32; 3],
+    pub tangent: [f32; 4],
 }
 
 impl Vertex {
-   pub fn get_buffer_layout() -> VertexBufferLayout<'static> {
 
-        VertexBufferLayout {
+    /// In field order.
+    pub const ATTRIBUTES: [VertexAttributeKind; 4] = [
+        VertexAttributeKind::Position,
+        VertexAttributeKind::Uv0,
+        VertexAttributeKind::Normal,
+        VertexAttributeKind::Tangent,
+    ];
 
-            array_stride: std::mem::size_of::<Self>() as u64,
-            step_mode: VertexStepMode::Vertex,
-            attributes: &[
-                VertexAttribute {
-
-                    format: egui_wgpu::wgpu::VertexFormat::Float32x3,
-                    offset: 0,
-                    shader_location: 0
-                },
-                VertexAttribute {
-                    format: egui_wgpu::wgpu::VertexFormat::Float32x2,
-                    offset: std::mem::size_of::<[f32; 3]>() as u64,
-                    shader_location: 1
-                }
-            ]
-        }
-   }
-}
\ No newline at end of file
+    pub fn streams() -> VertexStreams {
+        VertexStreams::new().interleaved(&Self::ATTRIBUTES)
+    }
+
+    pub fn layout() -> VertexLayout {
+        Self::streams().layouts().remove(0)
+    }
+}
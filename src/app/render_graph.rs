@@ -0,0 +1,313 @@
+use std::collections::HashMap;
+
+use egui_wgpu::wgpu::{self, CommandEncoder, Device, TextureView};
+
+use crate::error::{CandleError, CandleResult};
+
+/// Name of the graph's final color target, i.e. the swapchain texture or the offscreen target.
+pub const OUTPUT: &str = "output";
+
+pub type PassFn<'a> = Box<dyn FnOnce(&mut CommandEncoder, &GraphResources) + 'a>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextureSize {
+    /// Same size as `OUTPUT`.
+    Output,
+    /// `OUTPUT` size multiplied by a factor, e.g. 0.5 for half resolution effects.
+    Scaled(f32),
+    Fixed(u32, u32),
+}
+
+/// Texture created and owned by the graph, living only as long as the passes using it need it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TransientTextureDesc {
+    pub format: wgpu::TextureFormat,
+    pub size: TextureSize,
+}
+
+impl TransientTextureDesc {
+
+    fn extent(&self, output_width: u32, output_height: u32) -> (u32, u32) {
+
+        match self.size {
+            TextureSize::Output => (output_width, output_height),
+            TextureSize::Scaled(factor) => (
+                ((output_width as f32 * factor) as u32).max(1),
+                ((output_height as f32 * factor) as u32).max(1),
+            ),
+            TextureSize::Fixed(width, height) => (width, height),
+        }
+    }
+}
+
+struct PassNode<'a> {
+    name: &'static str,
+    reads: Vec<&'static str>,
+    writes: Vec<&'static str>,
+    execute: PassFn<'a>,
+}
+
+/// Views of every attachment available to a pass while it's recorded.
+pub struct GraphResources<'r> {
+    views: HashMap<&'static str, &'r TextureView>,
+}
+
+impl GraphResources<'_> {
+
+    pub fn view(&self, name: &str) -> &TextureView {
+        self.views
+            .get(name)
+            .unwrap_or_else(|| panic!("Render graph resource \"{name}\" doesn't exist!"))
+    }
+}
+
+/// Keeps transient textures alive between frames, so they're only recreated when their size or format change.
+#[derive(Default)]
+pub struct TransientTexturePool {
+    textures: HashMap<&'static str, (TransientTextureDesc, (u32, u32), TextureView)>,
+}
+
+impl TransientTexturePool {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn prepare(&mut self, device: &Device, name: &'static str, desc: TransientTextureDesc, extent: (u32, u32)) {
+
+        if let Some((cached_desc, cached_extent, _)) = self.textures.get(name)
+            && *cached_desc == desc
+            && *cached_extent == extent
+        {
+            return;
+        }
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(name),
+            size: wgpu::Extent3d {
+                width: extent.0,
+                height: extent.1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: desc.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        self.textures.insert(name, (desc, extent, view));
+    }
+}
+
+/// Describes one frame as a set of passes and the attachments they read and write.
+///
+/// Passes can be added in any order: a pass reading a resource sees what the last pass added before it
+/// wrote, or if there's none what every pass writing it wrote. Passes writing the same resource run in
+/// the order they were added, after the passes reading what was there before, and passes that don't
+/// contribute to `OUTPUT` are culled. The composite pass, if any, always runs last on top of `OUTPUT`.
+#[derive(Default)]
+pub struct RenderGraph<'a> {
+    passes: Vec<PassNode<'a>>,
+    transients: Vec<(&'static str, TransientTextureDesc)>,
+    composite: Option<(&'static str, PassFn<'a>)>,
+}
+
+impl<'a> RenderGraph<'a> {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn create_texture(&mut self, name: &'static str, desc: TransientTextureDesc) {
+        self.transients.push((name, desc));
+    }
+
+    pub fn add_pass(
+        &mut self,
+        name: &'static str,
+        reads: &[&'static str],
+        writes: &[&'static str],
+        execute: impl FnOnce(&mut CommandEncoder, &GraphResources) + 'a,
+    ) {
+        self.passes.push(PassNode {
+            name,
+            reads: reads.to_vec(),
+            writes: writes.to_vec(),
+            execute: Box::new(execute),
+        });
+    }
+
+    /// Sets the pass drawn over `OUTPUT` after everything else, e.g. the GUI.
+    pub fn set_composite_pass(
+        &mut self,
+        name: &'static str,
+        execute: impl FnOnce(&mut CommandEncoder, &GraphResources) + 'a,
+    ) {
+        self.composite = Some((name, Box::new(execute)));
+    }
+
+    /// Names of the passes that will run, in the order they will run.
+    pub fn execution_order(&self) -> CandleResult<Vec<&'static str>> {
+
+        let mut order: Vec<&'static str> = self
+            .sorted_pass_indices()?
+            .into_iter()
+            .map(|index| self.passes[index].name)
+            .collect();
+
+        if let Some((name, _)) = &self.composite {
+            order.push(name);
+        }
+
+        Ok(order)
+    }
+
+    /// Records every pass into `encoder`, with `output_view` standing in for `OUTPUT`.
+    pub fn execute(
+        mut self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        output_view: &TextureView,
+        output_size: (u32, u32),
+        pool: &mut TransientTexturePool,
+    ) -> CandleResult<()> {
+
+        let order = self.sorted_pass_indices()?;
+
+        for (name, desc) in &self.transients {
+            pool.prepare(device, name, *desc, desc.extent(output_size.0, output_size.1));
+        }
+
+        let mut views = HashMap::new();
+        views.insert(OUTPUT, output_view);
+        for (name, _) in &self.transients {
+            views.insert(*name, &pool.textures[name].2);
+        }
+        let resources = GraphResources { views };
+
+        let mut passes: Vec<Option<PassNode>> = self.passes.drain(..).map(Some).collect();
+        for index in order {
+            let pass = passes[index].take().unwrap();
+            (pass.execute)(encoder, &resources);
+        }
+
+        if let Some((_, execute)) = self.composite {
+            execute(encoder, &resources);
+        }
+
+        Ok(())
+    }
+
+    fn sorted_pass_indices(&self) -> CandleResult<Vec<usize>> {
+
+        let pass_count = self.passes.len();
+        let mut dependencies: Vec<Vec<usize>> = vec![Vec::new(); pass_count];
+
+        for (index, pass) in self.passes.iter().enumerate() {
+
+            for &resource in &pass.reads {
+
+                // What was written last before this pass, so post-processing can read `OUTPUT` and write it back later
+                let writers: Vec<usize> = match self.writers_of(resource).take_while(|&writer| writer < index).last() {
+                    Some(previous_writer) => vec![previous_writer],
+                    None => self.writers_of(resource).filter(|&writer| writer != index).collect(),
+                };
+
+                if writers.is_empty() {
+                    return Err(CandleError::RenderGraph(format!(
+                        "pass \"{}\" reads \"{resource}\", which no pass writes",
+                        pass.name
+                    )));
+                }
+
+                dependencies[index].extend(writers);
+            }
+
+            for &resource in &pass.writes {
+
+                let is_known = resource == OUTPUT || self.transients.iter().any(|(name, _)| *name == resource);
+                if !is_known {
+                    return Err(CandleError::RenderGraph(format!(
+                        "pass \"{}\" writes \"{resource}\", which was never created",
+                        pass.name
+                    )));
+                }
+
+                // Writers of the same resource keep the order they were added in, and don't overwrite
+                // it before the passes in between read it
+                if let Some(previous_writer) = self.writers_of(resource).take_while(|&writer| writer < index).last() {
+                    dependencies[index].push(previous_writer);
+                    dependencies[index].extend(
+                        (previous_writer + 1..index).filter(|&reader| self.passes[reader].reads.contains(&resource)),
+                    );
+                }
+            }
+        }
+
+        // Only keep passes that end up contributing to the output
+        let mut is_needed = vec![false; pass_count];
+        let mut stack: Vec<usize> = self.writers_of(OUTPUT).collect();
+        while let Some(index) = stack.pop() {
+            if !is_needed[index] {
+                is_needed[index] = true;
+                stack.extend(dependencies[index].iter().copied());
+            }
+        }
+
+        for (index, pass) in self.passes.iter().enumerate() {
+            if !is_needed[index] {
+                log::debug!("Culling render pass \"{}\", it doesn't contribute to the output", pass.name);
+            }
+        }
+
+        // Kahn's algorithm, always picking the earliest added pass that's ready to keep the order stable
+        let mut remaining_dependencies: Vec<usize> = dependencies
+            .iter()
+            .map(|deps| {
+                let mut deps = deps.clone();
+                deps.sort_unstable();
+                deps.dedup();
+                deps.len()
+            })
+            .collect();
+
+        let mut order = Vec::with_capacity(pass_count);
+        let mut is_scheduled = vec![false; pass_count];
+
+        while let Some(next) = (0..pass_count).find(|&index| !is_scheduled[index] && remaining_dependencies[index] == 0) {
+
+            is_scheduled[next] = true;
+            if is_needed[next] {
+                order.push(next);
+            }
+
+            for (index, deps) in dependencies.iter().enumerate() {
+                if !is_scheduled[index] && deps.contains(&next) {
+                    remaining_dependencies[index] -= 1;
+                }
+            }
+        }
+
+        if is_scheduled.iter().any(|scheduled| !scheduled) {
+            let cycle: Vec<&str> = (0..pass_count)
+                .filter(|&index| !is_scheduled[index])
+                .map(|index| self.passes[index].name)
+                .collect();
+
+            return Err(CandleError::RenderGraph(format!("passes {cycle:?} depend on each other")));
+        }
+
+        Ok(order)
+    }
+
+    fn writers_of(&self, resource: &'static str) -> impl Iterator<Item = usize> + '_ {
+        self.passes
+            .iter()
+            .enumerate()
+            .filter(move |(_, pass)| pass.writes.contains(&resource))
+            .map(|(index, _)| index)
+    }
+}
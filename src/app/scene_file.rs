@@ -0,0 +1,358 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::camera_controller::{CameraBookmark, CameraControls, CameraMode, OrbitCameraController};
+use super::main_renderer::asset_server::{AssetId, Handle};
+use super::main_renderer::camera::Camera;
+use super::main_renderer::material::Material;
+use super::main_renderer::scene::{Light, Node, NodeId, Transform};
+use super::main_renderer::settings::RendererSettings;
+use super::main_renderer::shapes::ProceduralShape;
+use super::main_renderer::MainRenderer;
+use crate::error::{CandleError, CandleResult};
+
+/// Written into every saved file. Bumped when the meaning of existing fields changes, not for new ones.
+pub const SCENE_FILE_VERSION: u32 = 1;
+
+/// Everything needed to put a scene back together, as stored in a JSON scene file.
+///
+/// Missing fields get their defaults and unknown ones are ignored, so files from older and newer
+/// versions both load. Model and texture paths are relative to the asset root.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SceneFile {
+    /// The only required field.
+    pub version: u32,
+    /// Left as it is on load when `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub camera: Option<SceneCamera>,
+    #[serde(default)]
+    pub renderer: RendererSettings,
+    /// Referred to by index from `NodeDesc::materials`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub materials: Vec<MaterialDesc>,
+    /// The root nodes.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nodes: Vec<NodeDesc>,
+}
+
+/// The view and camera controls.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SceneCamera {
+    /// The aspect isn't stored, it always follows the window.
+    pub camera: Camera,
+    pub mode: CameraMode,
+    pub orbit_distance: f32,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub bookmarks: Vec<CameraBookmark>,
+}
+
+impl Default for SceneCamera {
+    fn default() -> Self {
+        Self {
+            camera: Camera::default(),
+            mode: CameraMode::default(),
+            orbit_distance: OrbitCameraController::default().distance,
+            bookmarks: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaterialDesc {
+    pub name: String,
+    /// Linear RGBA, multiplied with the texture.
+    pub base_color_factor: [f32; 4],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_color_texture: Option<PathBuf>,
+}
+
+impl Default for MaterialDesc {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            base_color_factor: [1.0; 4],
+            base_color_texture: None,
+        }
+    }
+}
+
+/// A scene node and everything below it.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NodeDesc {
+    pub name: String,
+    #[serde(skip_serializing_if = "is_identity")]
+    pub transform: Transform,
+    /// Generated again on load.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mesh: Option<ProceduralShape>,
+    /// Model file whose nodes are added below this one on load, see `MainRenderer::load_model`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<PathBuf>,
+    /// Indices into `SceneFile::materials`, one per sub-mesh.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub materials: Vec<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub light: Option<Light>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<NodeDesc>,
+}
+
+fn is_identity(transform: &Transform) -> bool {
+    *transform == Transform::IDENTITY
+}
+
+impl Default for SceneFile {
+    fn default() -> Self {
+        Self {
+            version: SCENE_FILE_VERSION,
+            camera: None,
+            renderer: RendererSettings::default(),
+            materials: Vec::new(),
+            nodes: Vec::new(),
+        }
+    }
+}
+
+impl SceneFile {
+
+    /// Describes the renderer's scene, camera and settings.
+    ///
+    /// Only meshes made with `MainRenderer::add_shape` and models added with `MainRenderer::load_model`
+    /// can be stored, other meshes are left out with a warning. Nodes below a model node come from
+    /// the model file, so changes to them aren't stored either.
+    pub fn capture(renderer: &MainRenderer, camera_controls: &CameraControls) -> Self {
+
+        let mut materials = MaterialTable::default();
+        let nodes = renderer
+            .scene
+            .roots()
+            .iter()
+            .map(|&id| Self::capture_node(renderer, id, &mut materials))
+            .collect();
+
+        Self {
+            version: SCENE_FILE_VERSION,
+            camera: Some(SceneCamera {
+                camera: renderer.camera,
+                mode: camera_controls.mode(),
+                orbit_distance: camera_controls.orbit.distance,
+                bookmarks: camera_controls.bookmarks.clone(),
+            }),
+            renderer: renderer.settings,
+            materials: materials.descs,
+            nodes,
+        }
+    }
+
+    fn capture_node(renderer: &MainRenderer, id: NodeId, materials: &mut MaterialTable) -> NodeDesc {
+
+        let node = renderer.scene.get(id).expect("The hierarchy only refers to live nodes!");
+
+        let mesh = node.mesh.as_ref().and_then(|mesh| {
+            let shape = renderer.assets.meshes.get(mesh)?.shape;
+            if shape.is_none() {
+                log::warn!("The mesh of node \"{}\" isn't a procedural shape, it's left out of the scene file", node.name);
+            }
+            shape
+        });
+
+        let node_materials = match mesh {
+            Some(_) => node.materials.iter().map(|material| materials.index_of(renderer, material)).collect(),
+            None => Vec::new(),
+        };
+
+        let children = match node.model {
+            Some(_) => Vec::new(),
+            None => node
+                .children()
+                .iter()
+                .map(|&child| Self::capture_node(renderer, child, materials))
+                .collect(),
+        };
+
+        NodeDesc {
+            name: node.name.clone(),
+            transform: *node.transform(),
+            mesh,
+            model: node.model.clone(),
+            materials: node_materials,
+            light: node.light,
+            children,
+        }
+    }
+
+    /// Replaces the renderer's scene and settings, and the camera when the file has one.
+    ///
+    /// Models and textures are loaded first, if any of them fails the scene is left untouched.
+    pub fn apply(&self, renderer: &mut MainRenderer, camera_controls: &mut CameraControls) -> CandleResult<()> {
+
+        for node in self.iter_nodes() {
+            if let Some(&index) = node.materials.iter().find(|&&index| index >= self.materials.len()) {
+                return Err(CandleError::SceneInvalid(format!(
+                    "node \"{}\" uses material {index}, but there are only {}",
+                    node.name,
+                    self.materials.len(),
+                )));
+            }
+        }
+
+        let materials = self
+            .materials
+            .iter()
+            .map(|desc| {
+                let texture = desc
+                    .base_color_texture
+                    .as_ref()
+                    .map(|path| renderer.assets.load_texture(path, &renderer.device, &renderer.queue))
+                    .transpose()?;
+
+                Ok(renderer.assets.materials.add(Material {
+                    name: desc.name.clone(),
+                    base_color_factor: desc.base_color_factor,
+                    base_color_texture: texture,
+                }))
+            })
+            .collect::<CandleResult<Vec<_>>>()?;
+
+        // Kept alive so placing the nodes below finds them already loaded
+        let _models = self
+            .iter_nodes()
+            .filter_map(|node| node.model.as_ref())
+            .map(|path| renderer.assets.load_model(path))
+            .collect::<CandleResult<Vec<_>>>()?;
+
+        renderer.scene.clear();
+
+        let mut stack: Vec<(&NodeDesc, Option<NodeId>)> = self.nodes.iter().rev().map(|desc| (desc, None)).collect();
+
+        while let Some((desc, parent)) = stack.pop() {
+
+            let id = match &desc.model {
+                Some(path) => renderer.load_model(path, parent)?,
+                None => renderer.scene.add(Node::new(""), parent),
+            };
+            let mesh = desc.mesh.map(|shape| renderer.add_shape(shape));
+
+            let node = renderer.scene.get_mut(id).expect("Just added");
+            node.name = desc.name.clone();
+            node.set_transform(desc.transform);
+            node.mesh = mesh;
+            node.materials = desc.materials.iter().map(|&index| materials[index].clone()).collect();
+            node.light = desc.light;
+
+            stack.extend(desc.children.iter().rev().map(|child| (child, Some(id))));
+        }
+
+        renderer.scene.update_world_matrices();
+        renderer.settings = self.renderer;
+
+        if let Some(camera) = &self.camera {
+            renderer.camera = Camera { aspect: renderer.camera.aspect, ..camera.camera };
+            camera_controls.cancel_pending();
+            camera_controls.set_mode(camera.mode);
+            camera_controls.orbit.distance = camera.orbit_distance;
+            camera_controls.bookmarks = camera
+                .bookmarks
+                .iter()
+                .map(|bookmark| CameraBookmark {
+                    camera: Camera { aspect: renderer.camera.aspect, ..bookmark.camera },
+                    ..bookmark.clone()
+                })
+                .collect();
+        }
+
+        Ok(())
+    }
+
+    /// Every node in the file, parents before their children.
+    fn iter_nodes(&self) -> impl Iterator<Item = &NodeDesc> {
+
+        let mut stack: Vec<&NodeDesc> = self.nodes.iter().rev().collect();
+
+        std::iter::from_fn(move || {
+            let node = stack.pop()?;
+            stack.extend(node.children.iter().rev());
+            Some(node)
+        })
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+
+        let file: Self = serde_json::from_str(json)?;
+
+        if file.version > SCENE_FILE_VERSION {
+            log::warn!(
+                "Scene file version {} is newer than the supported {SCENE_FILE_VERSION}, loading what's understood",
+                file.version,
+            );
+        }
+
+        Ok(file)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Scene files always serialize")
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> CandleResult<Self> {
+
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .map_err(|source| CandleError::SceneRead { path: path.to_owned(), source })?;
+
+        Self::from_json(&json).map_err(|source| CandleError::SceneParse { path: path.to_owned(), source })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> CandleResult<()> {
+
+        let path = path.as_ref();
+        std::fs::write(path, self.to_json())
+            .map_err(|source| CandleError::SceneWrite { path: path.to_owned(), source })
+    }
+}
+
+/// Materials in the order `SceneFile::capture` first meets them.
+#[derive(Default)]
+struct MaterialTable {
+    descs: Vec<MaterialDesc>,
+    indices: HashMap<AssetId, usize>,
+}
+
+impl MaterialTable {
+
+    fn index_of(&mut self, renderer: &MainRenderer, handle: &Handle<Material>) -> usize {
+
+        if let Some(&index) = self.indices.get(&handle.id()) {
+            return index;
+        }
+
+        let desc = match renderer.assets.materials.get(handle) {
+            Some(material) => {
+                let texture = material.base_color_texture.as_ref().and_then(|texture| {
+                    let path = renderer.assets.textures.path(texture);
+                    if path.is_none() {
+                        log::warn!("The texture of material \"{}\" wasn't loaded from a file, it's left out", material.name);
+                    }
+                    path
+                });
+
+                MaterialDesc {
+                    name: material.name.clone(),
+                    base_color_factor: material.base_color_factor,
+                    base_color_texture: texture.map(|path| renderer.assets.relative(path).to_owned()),
+                }
+            }
+            None => MaterialDesc::default(),
+        };
+
+        self.descs.push(desc);
+        self.indices.insert(handle.id(), self.descs.len() - 1);
+
+        self.descs.len() - 1
+    }
+}
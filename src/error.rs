@@ -0,0 +1,148 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use egui_wgpu::wgpu;
+
+/// Everything that can go wrong while bringing up the renderer.
+#[derive(Debug)]
+pub enum CandleError {
+    EventLoop(winit::error::EventLoopError),
+    WindowCreation(winit::error::OsError),
+    SurfaceCreation(wgpu::CreateSurfaceError),
+    AdapterNotFound {
+        force_fallback_adapter: bool,
+    },
+    DeviceRequest(wgpu::RequestDeviceError),
+    NoSrgbSurfaceFormat {
+        available: Vec<wgpu::TextureFormat>,
+    },
+    TextureRead {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    TextureDecode {
+        path: PathBuf,
+        source: image::ImageError,
+    },
+    RenderGraph(String),
+    ShaderRead {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    ShaderPreprocess {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+    /// naga's parse or validation error, already mapped back to the original files.
+    ShaderCompile(String),
+    GltfImport {
+        path: PathBuf,
+        source: gltf::Error,
+    },
+    ObjImport {
+        path: PathBuf,
+        source: tobj::LoadError,
+    },
+    /// The model file was read fine, but its contents can't be used.
+    ModelInvalid {
+        path: PathBuf,
+        message: String,
+    },
+    InputBindingsRead {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    InputBindingsParse {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    InputBindingsWrite {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    SceneRead {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    SceneParse {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    SceneWrite {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// The scene file parsed fine, but refers to something that doesn't exist.
+    SceneInvalid(String),
+}
+
+impl fmt::Display for CandleError {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+
+        match self {
+            CandleError::EventLoop(error) => write!(f, "Event loop error: {error}"),
+            CandleError::WindowCreation(error) => write!(f, "Failed to create a window: {error}"),
+            CandleError::SurfaceCreation(error) => write!(f, "Failed to create a wgpu surface: {error}"),
+            CandleError::AdapterNotFound { force_fallback_adapter: true } => {
+                write!(f, "No fallback (software) graphics adapter is available")
+            }
+            CandleError::AdapterNotFound { force_fallback_adapter: false } => {
+                write!(f, "No graphics adapter compatible with this window was found")
+            }
+            CandleError::DeviceRequest(error) => write!(f, "Failed to create a graphics device: {error}"),
+            CandleError::NoSrgbSurfaceFormat { available } => write!(
+                f,
+                "The surface doesn't support any sRGB texture format (available formats: {available:?})"
+            ),
+            CandleError::TextureRead { path, source } => {
+                write!(f, "Failed to read texture file {}: {source}", path.display())
+            }
+            CandleError::TextureDecode { path, source } => {
+                write!(f, "Failed to decode texture file {}: {source}", path.display())
+            }
+            CandleError::RenderGraph(message) => write!(f, "Invalid render graph: {message}"),
+            CandleError::ShaderRead { path, source } => {
+                write!(f, "Failed to read shader file {}: {source}", path.display())
+            }
+            CandleError::ShaderPreprocess { path, line, message } => {
+                write!(f, "{}:{line}: {message}", path.display())
+            }
+            CandleError::ShaderCompile(message) => write!(f, "Shader compilation failed: {message}"),
+            CandleError::GltfImport { path, source } => {
+                write!(f, "Failed to import glTF file {}: {source}", path.display())
+            }
+            CandleError::ObjImport { path, source } => {
+                write!(f, "Failed to import OBJ file {}: {source}", path.display())
+            }
+            CandleError::ModelInvalid { path, message } => {
+                write!(f, "Invalid model file {}: {message}", path.display())
+            }
+            CandleError::InputBindingsRead { path, source } => {
+                write!(f, "Failed to read input bindings {}: {source}", path.display())
+            }
+            CandleError::InputBindingsParse { path, source } => {
+                write!(f, "Invalid input bindings {}: {source}", path.display())
+            }
+            CandleError::InputBindingsWrite { path, source } => {
+                write!(f, "Failed to write input bindings {}: {source}", path.display())
+            }
+            CandleError::SceneRead { path, source } => {
+                write!(f, "Failed to read scene file {}: {source}", path.display())
+            }
+            CandleError::SceneParse { path, source } => {
+                write!(f, "Invalid scene file {}: {source}", path.display())
+            }
+            CandleError::SceneWrite { path, source } => {
+                write!(f, "Failed to write scene file {}: {source}", path.display())
+            }
+            CandleError::SceneInvalid(message) => write!(f, "Invalid scene: {message}"),
+        }
+    }
+}
+
+// Display already includes the underlying error, so `source` is left empty to avoid printing it twice
+impl std::error::Error for CandleError {}
+
+pub type CandleResult<T> = Result<T, CandleError>;
@@ -0,0 +1,31 @@
+//! Candle, a toy real-time renderer built on wgpu and egui.
+//!
+//! Start with [`App::builder`] to open a window with the renderer running in it.
+
+pub mod app;
+pub mod error;
+pub mod utilities;
+
+pub use app::app_builder::{AppBuilder, AppConfig, FrameContext};
+pub use app::gui_renderer::{GUIRenderer, SceneRequest};
+pub use app::input::{Input, InputBindings};
+pub use app::main_renderer::asset_server::{AssetServer, Handle};
+pub use app::main_renderer::camera::{Camera, Projection};
+pub use app::main_renderer::material::Material;
+pub use app::main_renderer::mesh::{Aabb, Mesh};
+pub use app::main_renderer::model::Model;
+pub use app::main_renderer::scene::{Light, Node, NodeId, Scene, Transform};
+pub use app::main_renderer::settings::RendererSettings;
+pub use app::main_renderer::shapes::ProceduralShape;
+pub use app::main_renderer::texture::Texture;
+pub use app::main_renderer::vertex::Vertex;
+pub use app::main_renderer::MainRenderer;
+pub use app::scene_file::SceneFile;
+pub use app::App;
+pub use error::{CandleError, CandleResult};
+
+// Re-exported so users build against the same versions Candle does
+pub use egui;
+pub use glam;
+pub use egui_wgpu::wgpu;
+pub use winit;
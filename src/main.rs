@@ -1,24 +1,16 @@
-use winit::event_loop::{ControlFlow, EventLoop};
-
-pub mod app;
-pub mod utilities;
-
-
 fn main() -> anyhow::Result<()> {
-
-    pollster::block_on(run())?;
-    Ok(())
-}
-
-
-async fn run() -> anyhow::Result<()> {
     env_logger::init();
 
-    let event_loop = EventLoop::new().unwrap();
-    event_loop.set_control_flow(ControlFlow::Poll);
+    let mut builder = candle::App::builder()
+        .with_title("Candle")
+        .with_input_bindings("input_bindings.json");
+
+    // `candle path/to/scene.json` opens that scene
+    if let Some(scene) = std::env::args_os().nth(1) {
+        builder = builder.with_scene(scene);
+    }
 
-    let mut app = app::App::new();
-    event_loop.run_app(&mut app)?;
+    builder.run()?;
 
     Ok(())
 }
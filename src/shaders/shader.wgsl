@@ -1,3 +1,19 @@
+struct Camera {
+    view_projection: mat4x4<f32>,
+    position: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+struct Object {
+    model: mat4x4<f32>,
+    base_color: vec4<f32>,
+}
+
+@group(2) @binding(0)
+var<uniform> object: Object;
+
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) uv: vec2<f32>,
@@ -16,21 +32,19 @@ fn vertex(
     var out: VertexOutput;
 
     out.uv = input.uv;
-    out.clip_position = vec4<f32>(input.position, 1.0);
-   
-
+    out.clip_position = camera.view_projection * object.model * vec4<f32>(input.position, 1.0);
 
     return out;
 }
 
-@group(0) @binding(0)
+@group(1) @binding(0)
 var t_diffuse: texture_2d<f32>;
-@group(0) @binding(1)
+@group(1) @binding(1)
 var s_diffuse: sampler;
 
 @fragment
 fn fragment(input: VertexOutput) -> @location(0) vec4<f32> {
 
-    return textureSample(t_diffuse, s_diffuse, input.uv);
+    return textureSample(t_diffuse, s_diffuse, input.uv) * object.base_color;
 }
  
\ No newline at end of file
@@ -5,7 +5,9 @@ pub struct FPSCounter {
     fps_samples: [f32; 60],
     sample_index: usize,
 
-    pub fps: f32
+    pub fps: f32,
+    /// Seconds between the last two frames.
+    pub delta_time: f32,
 }
 
 impl FPSCounter {
@@ -17,6 +19,7 @@ impl FPSCounter {
             fps_samples: [0.0; 60],
             sample_index: 0,
             fps: 0.0,
+            delta_time: 0.0,
         }
     }
 
@@ -25,6 +28,7 @@ impl FPSCounter {
         let now = Instant::now();
         let delta_time = now.duration_since(self.last_frame_time).as_secs_f32();
         self.last_frame_time = now;
+        self.delta_time = delta_time;
 
         if delta_time > 0.0 {
             let current_fps = 1.0 / delta_time;
@@ -35,4 +39,10 @@ impl FPSCounter {
         self.fps = self.fps_samples.iter().sum::<f32>() / self.fps_samples.len() as f32;
 
     }
-}
\ No newline at end of file
+}
+
+impl Default for FPSCounter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,85 @@
+use std::path::Path;
+
+use candle::{wgpu, AssetServer, MainRenderer};
+
+#[test]
+fn textures_are_deduplicated_by_path_and_freed_when_unused() {
+
+    let root = Path::new(env!("CARGO_TARGET_TMPDIR")).join("asset_server");
+    std::fs::create_dir_all(&root).unwrap();
+    image::RgbaImage::from_pixel(4, 4, image::Rgba([255, 0, 0, 255])).save(root.join("Red.png")).unwrap();
+    std::fs::copy(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/src/resources/Checker.png"),
+        root.join("Checker.png"),
+    ).unwrap();
+
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+    let mut renderer = pollster::block_on(MainRenderer::new_headless(&instance, 64, 64, true, AssetServer::new(&root)))
+        .expect("Failed to create a headless renderer");
+
+    let MainRenderer { assets, device, queue, diffuse_texture, .. } = &mut renderer;
+
+    let checker = assets.load_texture("Checker.png", device, queue).unwrap();
+    assert_eq!(&checker, diffuse_texture, "the renderer already loaded the checker texture");
+
+    let red = assets.load_texture("Red.png", device, queue).unwrap();
+    let red_again = assets.load_texture("Red.png", device, queue).unwrap();
+    assert_eq!(red, red_again);
+    assert_eq!(assets.textures.len(), 3, "the checker, the renderer's white texture and red");
+    assert_eq!(assets.textures.get(&red).unwrap().name(), "Red");
+
+    drop((checker, red, red_again));
+    assets.free_unused();
+
+    assert_eq!(assets.textures.len(), 2, "the renderer still holds the checker and white textures");
+    assert!(assets.load_texture("Missing.png", device, queue).is_err());
+}
+
+#[test]
+fn async_textures_show_a_placeholder_until_loaded() {
+
+    let root = Path::new(env!("CARGO_TARGET_TMPDIR")).join("asset_server_async");
+    std::fs::create_dir_all(&root).unwrap();
+    image::RgbaImage::from_pixel(4, 4, image::Rgba([0, 0, 255, 255])).save(root.join("Blue.png")).unwrap();
+
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+    let mut renderer = pollster::block_on(MainRenderer::new_headless(&instance, 64, 64, true, AssetServer::new(&root)))
+        .expect("Failed to create a headless renderer");
+
+    let MainRenderer { assets, device, queue, .. } = &mut renderer;
+
+    let blue = assets.load_texture_async("Blue.png", device, queue).unwrap();
+    let missing = assets.load_texture_async("Missing.png", device, queue).unwrap();
+    assert_eq!(assets.load_texture_async("Blue.png", device, queue).unwrap(), blue);
+    assert_eq!(assets.textures.get(&blue).unwrap().name(), "Blue (loading)");
+
+    let loaded = assets.wait_for_pending(device, queue);
+    assert_eq!(assets.pending_loads(), 0);
+
+    assert!(loaded.contains(&blue.id()));
+    assert_eq!(assets.textures.get(&blue).unwrap().name(), "Blue");
+    assert_eq!(assets.textures.get(&blue).unwrap().data.image.dimensions(), (4, 4));
+
+    assert!(!loaded.contains(&missing.id()), "failed loads keep their placeholder");
+    assert_eq!(assets.textures.get(&missing).unwrap().name(), "Missing (loading)");
+}
+
+#[test]
+fn reloading_before_freeing_keeps_the_new_copy_findable() {
+
+    let root = Path::new(env!("CARGO_TARGET_TMPDIR")).join("asset_server_reload");
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(root.join("triangle.obj"), "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+
+    let mut assets = AssetServer::new(&root);
+
+    drop(assets.load_model("triangle.obj").unwrap());
+    let reloaded = assets.load_model("triangle.obj").unwrap();
+
+    // Frees the first copy, the path has to keep pointing to the second
+    assert_eq!(assets.models.free_unused(), 1);
+
+    let again = assets.load_model("triangle.obj").unwrap();
+    assert_eq!(again.id(), reloaded.id());
+    assert_eq!(assets.models.len(), 1);
+}
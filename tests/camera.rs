@@ -0,0 +1,119 @@
+use candle::glam::{Vec3, Vec4Swizzles};
+use candle::app::main_renderer::camera::Projection;
+use candle::{Aabb, AssetServer, Camera, MainRenderer};
+use egui_wgpu::wgpu;
+
+fn project(camera: &Camera, point: Vec3) -> Vec3 {
+
+    let clip = camera.view_projection_matrix() * point.extend(1.0);
+    clip.xyz() / clip.w
+}
+
+fn assert_close(actual: Vec3, expected: Vec3) {
+    assert!(actual.abs_diff_eq(expected, 1e-4), "expected {expected}, got {actual}");
+}
+
+#[test]
+fn points_in_front_of_the_camera_land_in_clip_space() {
+
+    let camera = Camera::default();
+
+    let center = project(&camera, Vec3::ZERO);
+    assert_close(center.with_z(0.0), Vec3::ZERO);
+    assert!(center.z > 0.0 && center.z < 1.0);
+
+    // Right and up on screen are +X and +Y
+    assert!(project(&camera, Vec3::X * 0.1).x > 0.0);
+    assert!(project(&camera, Vec3::Y * 0.1).y > 0.0);
+
+    assert!((project(&camera, camera.position + camera.forward() * camera.near).z).abs() < 1e-4);
+    assert!((project(&camera, camera.position + camera.forward() * camera.far).z - 1.0).abs() < 1e-4);
+}
+
+#[test]
+fn field_of_view_and_aspect_frame_the_view() {
+
+    let mut camera = Camera { fov_y: 90_f32.to_radians(), ..Default::default() };
+    camera.set_viewport_size(200, 100);
+    assert_eq!(camera.aspect, 2.0);
+
+    // At distance 2 a 90° field of view sees 2 units up and, twice as wide, 4 units to the side
+    let distance = camera.position.z;
+    assert_close(project(&camera, Vec3::new(0.0, distance, 0.0)).with_z(0.0), Vec3::Y);
+    assert_close(project(&camera, Vec3::new(2.0 * distance, 0.0, 0.0)).with_z(0.0), Vec3::X);
+
+    camera.set_viewport_size(0, 100);
+    assert_eq!(camera.aspect, 2.0);
+}
+
+#[test]
+fn look_at_centers_the_target_and_keeps_the_horizon_level() {
+
+    let mut camera = Camera { position: Vec3::new(3.0, 2.0, -1.0), ..Default::default() };
+    let target = Vec3::new(-1.0, 0.5, 2.0);
+    camera.look_at(target);
+
+    assert_close(camera.forward(), (target - camera.position).normalize());
+    assert!(camera.right().y.abs() < 1e-5);
+    assert!(camera.up().y > 0.0);
+    assert_close(project(&camera, target).with_z(0.0), Vec3::ZERO);
+
+    // Straight down still gives a usable orientation
+    camera.look_at(camera.position - Vec3::Y);
+    assert_close(camera.forward(), Vec3::NEG_Y);
+    assert!(camera.orientation.is_normalized());
+}
+
+#[test]
+fn orthographic_projection_has_no_foreshortening() {
+
+    let camera = Camera { projection: Projection::Orthographic, ortho_height: 4.0, aspect: 2.0, ..Default::default() };
+
+    assert_close(project(&camera, Vec3::new(4.0, 2.0, 0.0)).with_z(0.0), Vec3::new(1.0, 1.0, 0.0));
+    assert_close(project(&camera, Vec3::new(4.0, 2.0, -10.0)).with_z(0.0), Vec3::new(1.0, 1.0, 0.0));
+
+    assert!((project(&camera, camera.position + camera.forward() * camera.near).z).abs() < 1e-4);
+    assert!((project(&camera, camera.position + camera.forward() * camera.far).z - 1.0).abs() < 1e-4);
+}
+
+#[test]
+fn framing_fits_every_corner_of_the_box_in_view() {
+
+    let bounds = Aabb { min: [-3.0, -0.5, -1.0], max: [5.0, 0.5, 1.0] };
+
+    for (aspect, projection) in [0.5, 1.0, 2.0].into_iter().flat_map(|aspect| {
+        [(aspect, Projection::Perspective), (aspect, Projection::Orthographic)]
+    }) {
+
+        let mut camera = Camera { aspect, projection, ..Default::default() };
+        camera.look_at(Vec3::new(1.0, -1.0, -1.0));
+        let orientation = camera.orientation;
+        camera.frame(&bounds);
+
+        assert_eq!(camera.orientation, orientation);
+        assert_close(project(&camera, Vec3::new(1.0, 0.0, 0.0)).with_z(0.0), Vec3::ZERO);
+
+        for corner in 0..8 {
+            let point = Vec3::new(
+                if corner & 1 == 0 { bounds.min[0] } else { bounds.max[0] },
+                if corner & 2 == 0 { bounds.min[1] } else { bounds.max[1] },
+                if corner & 4 == 0 { bounds.min[2] } else { bounds.max[2] },
+            );
+            let projected = project(&camera, point);
+            assert!(projected.x.abs() <= 1.0 && projected.y.abs() <= 1.0, "{point} isn't in view at {projected}");
+        }
+    }
+}
+
+#[test]
+fn resizing_the_surface_updates_the_aspect() {
+
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+    let mut renderer = pollster::block_on(MainRenderer::new_headless(&instance, 300, 150, true, AssetServer::default()))
+        .expect("Failed to create a headless renderer");
+
+    assert_eq!(renderer.camera.aspect, 2.0);
+
+    renderer.resize_surface(100, 400);
+    assert_eq!(renderer.camera.aspect, 0.25);
+}
@@ -0,0 +1,356 @@
+use candle::app::camera_controller::{
+    AxisView, CameraControls, CameraMode, CameraRequest, FlyCameraController, OrbitCameraController,
+};
+use candle::app::input::{Button, Input};
+use candle::app::main_renderer::camera::Projection;
+use candle::glam::{Vec2, Vec3};
+use candle::{Aabb, Camera};
+use winit::event::{MouseButton, WindowEvent};
+use winit::keyboard::KeyCode;
+
+fn assert_close(actual: Vec3, expected: Vec3) {
+    assert!(actual.abs_diff_eq(expected, 1e-4), "expected {expected}, got {actual}");
+}
+
+#[test]
+fn movement_is_frame_rate_independent() {
+
+    let mut controller = FlyCameraController::new();
+    let mut input = Input::default();
+    input.press(Button::Key(KeyCode::KeyW));
+
+    let mut one_frame = Camera::default();
+    controller.update(&mut one_frame, &input, 1.0);
+
+    let mut many_frames = Camera::default();
+    for _ in 0..60 {
+        controller.update(&mut many_frames, &input, 1.0 / 60.0);
+        input.end_frame();
+    }
+
+    assert_close(one_frame.position, Camera::default().position + Vec3::NEG_Z * controller.speed);
+    assert_close(many_frames.position, one_frame.position);
+}
+
+#[test]
+fn keys_move_relative_to_the_view_and_modifiers_change_speed() {
+
+    let mut controller = FlyCameraController::new();
+    controller.speed = 1.0;
+    let mut input = Input::default();
+    let mut camera = Camera { position: Vec3::ZERO, ..Default::default() };
+    camera.look_at(Vec3::X);
+
+    input.press(Button::Key(KeyCode::KeyA));
+    controller.update(&mut camera, &input, 1.0);
+    assert_close(camera.position, Vec3::NEG_Z);
+
+    input.release(Button::Key(KeyCode::KeyA));
+    input.press(Button::Key(KeyCode::KeyE));
+    input.press(Button::Key(KeyCode::ShiftLeft));
+    controller.update(&mut camera, &input, 1.0);
+    assert_close(camera.position, Vec3::new(0.0, controller.fast_multiplier, -1.0));
+
+    input.release(Button::Key(KeyCode::ShiftLeft));
+    input.press(Button::Key(KeyCode::ControlRight));
+    controller.update(&mut camera, &input, 1.0);
+    assert_close(camera.position, Vec3::new(0.0, controller.fast_multiplier + controller.slow_multiplier, -1.0));
+
+    // Opposite keys cancel out
+    input.press(Button::Key(KeyCode::KeyQ));
+    let before = camera.position;
+    controller.update(&mut camera, &input, 1.0);
+    assert_close(camera.position, before);
+}
+
+#[test]
+fn scrolling_changes_the_speed_within_limits() {
+
+    let mut controller = FlyCameraController::new();
+    let mut input = Input::default();
+    let mut camera = Camera::default();
+    let speed = controller.speed;
+
+    let mut scroll = |steps| {
+        input.add_scroll(steps);
+        controller.update(&mut camera, &input, 1.0 / 60.0);
+        input.end_frame();
+        controller.speed
+    };
+
+    assert!(scroll(1.0) > speed);
+    assert!((scroll(-1.0) - speed).abs() < 1e-5);
+    assert!(scroll(-1000.0) > 0.0);
+}
+
+#[test]
+fn mouse_looks_around_only_while_the_right_button_is_held() {
+
+    let mut controller = FlyCameraController::new();
+    let mut input = Input::default();
+    let mut camera = Camera::default();
+
+    input.add_mouse_motion(Vec2::new(100.0, 0.0));
+    controller.update(&mut camera, &input, 1.0 / 60.0);
+    input.end_frame();
+    assert_eq!(camera.orientation, Camera::default().orientation);
+
+    input.press(Button::Mouse(MouseButton::Right));
+    assert!(controller.is_looking(&input));
+
+    // Moving the mouse right turns right
+    input.add_mouse_motion(Vec2::new(100.0, 0.0));
+    controller.update(&mut camera, &input, 1.0 / 60.0);
+    input.end_frame();
+    assert!(camera.forward().x > 0.0);
+    assert!(camera.right().y.abs() < 1e-5, "looking around must not roll the camera");
+
+    // Pitch stops short of straight down
+    input.add_mouse_motion(Vec2::new(0.0, 1e6));
+    controller.update(&mut camera, &input, 1.0 / 60.0);
+    input.end_frame();
+    assert!(camera.forward().y < -0.99 && camera.forward().y > -1.0);
+
+    input.release(Button::Mouse(MouseButton::Right));
+    assert!(!controller.is_looking(&input));
+}
+
+#[test]
+fn losing_focus_releases_everything() {
+
+    let mut controller = FlyCameraController::new();
+    let mut input = Input::default();
+    input.press(Button::Key(KeyCode::KeyW));
+    input.press(Button::Mouse(MouseButton::Right));
+
+    input.handle_window_event(&WindowEvent::Focused(false), &egui::Context::default());
+    assert!(!controller.is_looking(&input));
+
+    let mut camera = Camera::default();
+    controller.update(&mut camera, &input, 1.0);
+    assert_eq!(camera, Camera::default());
+}
+
+#[test]
+fn orbiting_keeps_the_target_and_distance() {
+
+    let mut controller = OrbitCameraController::new();
+    let mut input = Input::default();
+    let mut camera = Camera::default();
+    assert_close(controller.target(&camera), Vec3::ZERO);
+
+    input.press(Button::Mouse(MouseButton::Left));
+    assert!(controller.is_dragging(&input));
+    input.add_mouse_motion(Vec2::new(150.0, -80.0));
+    controller.update(&mut camera, &input);
+
+    assert_close(controller.target(&camera), Vec3::ZERO);
+    assert!((camera.position.length() - controller.distance).abs() < 1e-4);
+    // Like grabbing the scene: dragging right spins it right and dragging up shows it from below
+    assert!(camera.position.x < 0.0 && camera.position.y < 0.0);
+}
+
+#[test]
+fn panning_moves_the_target_and_zooming_the_distance() {
+
+    let mut controller = OrbitCameraController::new();
+    let mut input = Input::default();
+    let mut camera = Camera::default();
+
+    input.press(Button::Key(KeyCode::ShiftLeft));
+    input.press(Button::Mouse(MouseButton::Left));
+    input.add_mouse_motion(Vec2::new(-100.0, 0.0));
+    controller.update(&mut camera, &input);
+    input.end_frame();
+    input.release(Button::Mouse(MouseButton::Left));
+    assert!(!controller.is_dragging(&input));
+
+    let target = controller.target(&camera);
+    assert!(target.x > 0.0 && target.y.abs() < 1e-5 && target.z.abs() < 1e-5);
+    assert_eq!(camera.orientation, Camera::default().orientation);
+
+    input.add_scroll(3.0);
+    controller.update(&mut camera, &input);
+    assert!(controller.distance < 2.0);
+    assert_close(controller.target(&camera), target);
+}
+
+#[test]
+fn releasing_shift_mid_drag_keeps_panning() {
+
+    let mut controller = OrbitCameraController::new();
+    let mut input = Input::default();
+    let mut camera = Camera::default();
+
+    input.press(Button::Key(KeyCode::ShiftLeft));
+    input.press(Button::Mouse(MouseButton::Left));
+    input.add_mouse_motion(Vec2::new(-100.0, 0.0));
+    controller.update(&mut camera, &input);
+    input.end_frame();
+
+    input.release(Button::Key(KeyCode::ShiftLeft));
+    input.add_mouse_motion(Vec2::new(-100.0, 0.0));
+    controller.update(&mut camera, &input);
+    input.end_frame();
+
+    assert_eq!(camera.orientation, Camera::default().orientation);
+    assert!(controller.target(&camera).x > 0.0);
+
+    // The next drag starts afresh
+    input.release(Button::Mouse(MouseButton::Left));
+    controller.update(&mut camera, &input);
+    input.end_frame();
+    input.press(Button::Mouse(MouseButton::Left));
+    input.add_mouse_motion(Vec2::new(100.0, 0.0));
+    controller.update(&mut camera, &input);
+    assert_ne!(camera.orientation, Camera::default().orientation);
+}
+
+#[test]
+fn switching_modes_keeps_the_view() {
+
+    let mut controls = CameraControls::new();
+    let input = Input::default();
+    let mut camera = Camera { position: Vec3::new(1.0, 2.0, 3.0), ..Default::default() };
+    camera.look_at(Vec3::new(-1.0, 0.0, 0.0));
+    let before = camera;
+
+    controls.set_mode(CameraMode::Orbit);
+    controls.update(&mut camera, &input, None, 1.0);
+    assert_eq!(camera, before);
+
+    controls.set_mode(CameraMode::Fly);
+    controls.update(&mut camera, &input, None, 1.0);
+    assert_eq!(camera, before);
+}
+
+#[test]
+fn focusing_frames_the_scene_and_orbits_around_it() {
+
+    let bounds = Aabb { min: [1.0, 1.0, -6.0], max: [3.0, 2.0, -4.0] };
+
+    let mut controls = CameraControls::new();
+    controls.set_mode(CameraMode::Orbit);
+    let input = Input::default();
+    let mut camera = Camera::default();
+
+    controls.update(&mut camera, &input, Some(bounds), 1.0 / 60.0);
+    assert_eq!(camera, Camera::default(), "only framed on request");
+
+    controls.request(CameraRequest::Focus);
+    controls.update(&mut camera, &input, Some(bounds), 1.0);
+    assert!(!controls.is_transitioning());
+    assert_close(controls.orbit.target(&camera), Vec3::new(2.0, 1.5, -5.0));
+}
+
+#[test]
+fn axis_views_look_at_the_orbit_target_from_along_an_axis() {
+
+    let mut controls = CameraControls::new();
+    let mut input = Input::default();
+    let mut camera = Camera { position: Vec3::new(1.0, 0.0, 2.0), ..Default::default() };
+    let target = controls.orbit.target(&camera);
+
+    input.press(Button::Key(KeyCode::Numpad7));
+    controls.update(&mut camera, &input, None, 1.0);
+    input.end_frame();
+
+    assert_close(camera.forward(), Vec3::NEG_Y);
+    assert_close(camera.up(), Vec3::NEG_Z);
+    assert_close(controls.orbit.target(&camera), target);
+    assert_eq!(camera.projection, Projection::Orthographic);
+
+    input.press(Button::Key(KeyCode::ControlLeft));
+    input.press(Button::Key(KeyCode::Numpad1));
+    controls.update(&mut camera, &input, None, 1.0);
+    assert_close(camera.forward(), Vec3::Z);
+    assert_close(controls.orbit.target(&camera), target);
+
+    for view in AxisView::ALL {
+        let camera = Camera { orientation: view.orientation(), ..Default::default() };
+        assert!(camera.right().y.abs() < 1e-5, "{} view is rolled", view.name());
+    }
+}
+
+#[test]
+fn turning_from_the_top_and_bottom_views_keeps_the_heading() {
+
+    let mut controller = FlyCameraController::new();
+    let mut input = Input::default();
+    input.press(Button::Mouse(MouseButton::Right));
+
+    for view in [AxisView::Top, AxisView::Bottom] {
+
+        let mut camera = Camera { orientation: view.orientation(), ..Default::default() };
+        let right = camera.right();
+
+        input.add_mouse_motion(Vec2::new(0.0, 1.0));
+        controller.update(&mut camera, &input, 1.0 / 60.0);
+        input.end_frame();
+
+        assert_close(camera.right(), right);
+        assert!(camera.forward().y.abs() > 0.99, "{} view turned too far", view.name());
+    }
+}
+
+#[test]
+fn switching_projection_keeps_the_orbit_target_the_same_size() {
+
+    let mut controls = CameraControls::new();
+    let mut input = Input::default();
+    let mut camera = Camera { aspect: 1.5, ..Default::default() };
+
+    let screen_height = |camera: &Camera, controls: &CameraControls| {
+        let point = controls.orbit.target(camera) + camera.up() * 0.5;
+        let clip = camera.view_projection_matrix() * point.extend(1.0);
+        clip.y / clip.w
+    };
+    let perspective = screen_height(&camera, &controls);
+
+    input.press(Button::Key(KeyCode::Numpad5));
+    controls.update(&mut camera, &input, None, 1.0 / 60.0);
+    input.end_frame();
+    assert_eq!(camera.projection, Projection::Orthographic);
+    assert!((screen_height(&camera, &controls) - perspective).abs() < 1e-4);
+
+    // Zooming without perspective shrinks the view instead
+    controls.set_mode(CameraMode::Orbit);
+    input.add_scroll(2.0);
+    controls.update(&mut camera, &input, None, 1.0 / 60.0);
+    input.end_frame();
+    let zoomed = screen_height(&camera, &controls);
+    assert!(zoomed > perspective);
+
+    controls.request(CameraRequest::SetProjection(Projection::Perspective));
+    controls.update(&mut camera, &input, None, 1.0 / 60.0);
+    assert_eq!(camera.projection, Projection::Perspective);
+    assert!((screen_height(&camera, &controls) - zoomed).abs() < 1e-4);
+}
+
+#[test]
+fn bookmarks_are_restored_smoothly() {
+
+    let mut controls = CameraControls::new();
+    controls.transition_duration = 1.0;
+    let input = Input::default();
+    let mut camera = Camera::default();
+
+    controls.request(CameraRequest::SaveBookmark("Start".to_owned()));
+    controls.update(&mut camera, &input, None, 0.0);
+    assert_eq!(controls.bookmarks[0].name, "Start");
+
+    camera.position = Vec3::new(4.0, 2.0, 2.0);
+    camera.look_at(Vec3::ZERO);
+    camera.aspect = 2.0;
+    let moved = camera;
+
+    controls.request(CameraRequest::RestoreBookmark(0));
+    controls.update(&mut camera, &input, None, 0.5);
+    assert!(controls.is_transitioning());
+    assert_close(camera.position, (moved.position + Camera::default().position) / 2.0);
+    assert_eq!(camera.aspect, 2.0, "the aspect follows the window, not the bookmark");
+
+    controls.update(&mut camera, &input, None, 0.5);
+    assert!(!controls.is_transitioning());
+    assert_eq!(camera, Camera { aspect: 2.0, ..Default::default() });
+}
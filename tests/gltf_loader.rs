@@ -0,0 +1,232 @@
+use std::path::{Path, PathBuf};
+
+use candle::app::main_renderer::model::AlphaMode;
+use candle::{AssetServer, CandleError, Model};
+
+/// A mesh with two primitives: a triangle with u32 indices and UVs, and a non-indexed quad strip.
+fn geometry() -> Vec<u8> {
+
+    let triangle_positions: [f32; 9] = [0.0, 0.5, 0.0, -0.5, -0.5, 0.0, 0.5, -0.5, 0.0];
+    let triangle_uvs: [f32; 6] = [0.5, 0.0, 0.0, 1.0, 1.0, 1.0];
+    let triangle_indices: [u32; 3] = [0, 1, 2];
+    let quad_positions: [f32; 12] = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0];
+
+    let mut bytes = Vec::new();
+    bytes.extend(triangle_positions.iter().flat_map(|value| value.to_le_bytes()));
+    bytes.extend(triangle_uvs.iter().flat_map(|value| value.to_le_bytes()));
+    bytes.extend(triangle_indices.iter().flat_map(|value| value.to_le_bytes()));
+    bytes.extend(quad_positions.iter().flat_map(|value| value.to_le_bytes()));
+    bytes
+}
+
+fn png() -> Vec<u8> {
+
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    image::RgbaImage::from_pixel(2, 2, image::Rgba([255, 0, 0, 255]))
+        .write_to(&mut bytes, image::ImageFormat::Png)
+        .unwrap();
+    bytes.into_inner()
+}
+
+/// `buffer` and `image` are the JSON objects of the only buffer and image.
+fn document(buffer: &str, image: &str, extra_buffer_views: &str) -> String {
+
+    format!(r#"{{
+        "asset": {{ "version": "2.0" }},
+        "scene": 0,
+        "scenes": [{{ "nodes": [0] }}],
+        "nodes": [
+            {{ "name": "Root", "translation": [1, 2, 3], "children": [1] }},
+            {{ "name": "Child", "mesh": 0, "scale": [2, 2, 2] }}
+        ],
+        "meshes": [{{
+            "name": "Shapes",
+            "primitives": [
+                {{ "attributes": {{ "POSITION": 0, "TEXCOORD_0": 1 }}, "indices": 2, "material": 0 }},
+                {{ "attributes": {{ "POSITION": 3 }}, "mode": 5, "material": 1 }}
+            ]
+        }}],
+        "materials": [
+            {{ "name": "Red", "pbrMetallicRoughness": {{ "baseColorFactor": [1, 0, 0, 1] }} }},
+            {{
+                "name": "Textured",
+                "pbrMetallicRoughness": {{ "baseColorTexture": {{ "index": 0 }}, "roughnessFactor": 0.5 }},
+                "alphaMode": "MASK",
+                "alphaCutoff": 0.25,
+                "doubleSided": true
+            }}
+        ],
+        "textures": [{{ "source": 0 }}],
+        "images": [{image}],
+        "accessors": [
+            {{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [-0.5, -0.5, 0], "max": [0.5, 0.5, 0] }},
+            {{ "bufferView": 1, "componentType": 5126, "count": 3, "type": "VEC2" }},
+            {{ "bufferView": 2, "componentType": 5125, "count": 3, "type": "SCALAR" }},
+            {{ "bufferView": 3, "componentType": 5126, "count": 4, "type": "VEC3", "min": [0, 0, 0], "max": [1, 1, 0] }}
+        ],
+        "bufferViews": [
+            {{ "buffer": 0, "byteOffset": 0, "byteLength": 36 }},
+            {{ "buffer": 0, "byteOffset": 36, "byteLength": 24 }},
+            {{ "buffer": 0, "byteOffset": 60, "byteLength": 12 }},
+            {{ "buffer": 0, "byteOffset": 72, "byteLength": 48 }}{extra_buffer_views}
+        ],
+        "buffers": [{buffer}]
+    }}"#)
+}
+
+fn base64(bytes: &[u8]) -> String {
+
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let value = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(ALPHABET[(value >> (18 - 6 * index) & 63) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+fn test_dir(name: &str) -> PathBuf {
+
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("gltf_loader").join(name);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn assert_loaded_correctly(model: &Model) {
+
+    assert_eq!(model.meshes.len(), 1);
+    let mesh = &model.meshes[0];
+    assert_eq!(mesh.name, "Shapes");
+    assert_eq!(mesh.primitives.len(), 2);
+
+    let triangle = &mesh.primitives[0];
+    assert_eq!(triangle.positions.len(), 3);
+    assert_eq!(triangle.uvs, vec![[0.5, 0.0], [0.0, 1.0], [1.0, 1.0]]);
+    assert_eq!(triangle.indices, vec![0, 1, 2]);
+    assert_eq!(triangle.material, Some(0));
+    assert_eq!(triangle.vertices()[1].uv, [0.0, 1.0]);
+
+    let quad = &mesh.primitives[1];
+    assert!(quad.uvs.is_empty());
+    assert_eq!(quad.indices, vec![0, 1, 2, 2, 1, 3], "the strip is turned into a triangle list");
+
+    assert_eq!(model.materials.len(), 2);
+    assert_eq!(model.materials[0].base_color_factor, [1.0, 0.0, 0.0, 1.0]);
+    assert_eq!(model.materials[0].base_color_texture, None);
+    assert_eq!(model.materials[1].base_color_texture, Some(0));
+    assert_eq!(model.materials[1].roughness_factor, 0.5);
+    assert_eq!(model.materials[1].alpha_mode, AlphaMode::Mask { cutoff: 0.25 });
+    assert!(model.materials[1].double_sided);
+
+    assert_eq!(model.images.len(), 1);
+    assert_eq!(model.images[0].image.get_pixel(1, 1).0, [255, 0, 0, 255]);
+
+    assert_eq!(model.root_nodes, vec![0]);
+    assert_eq!(model.nodes[0].translation, [1.0, 2.0, 3.0]);
+    assert_eq!(model.nodes[0].children, vec![1]);
+    assert_eq!(model.nodes[1].mesh, Some(0));
+    assert_eq!(model.nodes[1].scale, [2.0, 2.0, 2.0]);
+}
+
+#[test]
+fn loads_gltf_with_external_files() {
+
+    let dir = test_dir("external");
+    std::fs::write(dir.join("shapes.bin"), geometry()).unwrap();
+    std::fs::write(dir.join("Red.png"), png()).unwrap();
+
+    let json = document(r#"{ "uri": "shapes.bin", "byteLength": 120 }"#, r#"{ "uri": "Red.png" }"#, "");
+    std::fs::write(dir.join("shapes.gltf"), json).unwrap();
+
+    assert_loaded_correctly(&Model::load_gltf(dir.join("shapes.gltf")).unwrap());
+}
+
+#[test]
+fn loads_gltf_with_data_uris() {
+
+    let dir = test_dir("data_uri");
+
+    let buffer = format!(
+        r#"{{ "uri": "data:application/octet-stream;base64,{}", "byteLength": 120 }}"#,
+        base64(&geometry())
+    );
+    let image = format!(r#"{{ "uri": "data:image/png;base64,{}" }}"#, base64(&png()));
+    std::fs::write(dir.join("shapes.gltf"), document(&buffer, &image, "")).unwrap();
+
+    assert_loaded_correctly(&Model::load_gltf(dir.join("shapes.gltf")).unwrap());
+}
+
+#[test]
+fn loads_glb_with_embedded_buffer_and_image() {
+
+    let dir = test_dir("glb");
+
+    let mut binary = geometry();
+    let png = png();
+    let image_view = format!(r#", {{ "buffer": 0, "byteOffset": {}, "byteLength": {} }}"#, binary.len(), png.len());
+    binary.extend(&png);
+    while !binary.len().is_multiple_of(4) {
+        binary.push(0);
+    }
+
+    let buffer = format!(r#"{{ "byteLength": {} }}"#, binary.len());
+    let mut json = document(&buffer, r#"{ "bufferView": 4, "mimeType": "image/png" }"#, &image_view).into_bytes();
+    while !json.len().is_multiple_of(4) {
+        json.push(b' ');
+    }
+
+    let mut glb = Vec::new();
+    glb.extend(b"glTF");
+    glb.extend(2u32.to_le_bytes());
+    glb.extend(((12 + 8 + json.len() + 8 + binary.len()) as u32).to_le_bytes());
+    glb.extend((json.len() as u32).to_le_bytes());
+    glb.extend(b"JSON");
+    glb.extend(&json);
+    glb.extend((binary.len() as u32).to_le_bytes());
+    glb.extend(b"BIN\0");
+    glb.extend(&binary);
+    std::fs::write(dir.join("shapes.glb"), glb).unwrap();
+
+    let mut assets = AssetServer::new(&dir);
+    let model = assets.load_model("shapes.glb").unwrap();
+    assert_eq!(assets.load_model("shapes.glb").unwrap(), model);
+
+    assert_loaded_correctly(assets.models.get(&model).unwrap());
+}
+
+#[test]
+fn missing_buffers_are_reported() {
+
+    let dir = test_dir("missing");
+    let json = document(r#"{ "uri": "missing.bin", "byteLength": 120 }"#, r#"{ "uri": "Red.png" }"#, "");
+    std::fs::write(dir.join("shapes.gltf"), json).unwrap();
+
+    let error = Model::load_gltf(dir.join("shapes.gltf")).err().unwrap();
+    assert!(error.to_string().contains("shapes.gltf"), "{error}");
+}
+
+#[test]
+fn out_of_range_indices_are_reported() {
+
+    let dir = test_dir("out_of_range");
+    let mut geometry = geometry();
+    geometry[68..72].copy_from_slice(&7u32.to_le_bytes());
+    std::fs::write(dir.join("shapes.bin"), geometry).unwrap();
+    std::fs::write(dir.join("Red.png"), png()).unwrap();
+
+    let json = document(r#"{ "uri": "shapes.bin", "byteLength": 120 }"#, r#"{ "uri": "Red.png" }"#, "");
+    std::fs::write(dir.join("shapes.gltf"), json).unwrap();
+
+    let error = Model::load_gltf(dir.join("shapes.gltf")).err().unwrap();
+    assert!(matches!(error, CandleError::ModelInvalid { .. }), "{error}");
+    assert!(error.to_string().contains("vertex 7"), "{error}");
+}
@@ -0,0 +1,184 @@
+//! Golden-image harness: renders scenes headlessly and compares them against reference PNGs.
+
+use std::path::{Path, PathBuf};
+
+use candle::{AssetServer, MainRenderer};
+use egui_wgpu::wgpu;
+use image::{Rgba, RgbaImage};
+
+/// Set to re-record reference images instead of comparing against them.
+const UPDATE_ENV: &str = "CANDLE_UPDATE_GOLDEN";
+
+/// Overrides `Tolerance::max_pixel_delta` for every test, e.g. for noisier software rasterizers.
+const PIXEL_DELTA_ENV: &str = "CANDLE_GOLDEN_PIXEL_DELTA";
+
+/// Overrides `Tolerance::max_mismatched_fraction` for every test.
+const MISMATCH_FRACTION_ENV: &str = "CANDLE_GOLDEN_MISMATCH_FRACTION";
+
+pub struct Tolerance {
+    /// Largest perceptual difference (0.0 - 1.0) a pixel may have before it counts as mismatched.
+    pub max_pixel_delta: f32,
+    /// Fraction of pixels (0.0 - 1.0) allowed to mismatch before the comparison fails.
+    pub max_mismatched_fraction: f32,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self {
+            max_pixel_delta: 0.02,
+            max_mismatched_fraction: 0.001,
+        }
+    }
+}
+
+impl Tolerance {
+
+    fn with_env_overrides(self) -> Self {
+
+        let read = |name: &str| std::env::var(name).ok().and_then(|value| value.parse::<f32>().ok());
+
+        Self {
+            max_pixel_delta: read(PIXEL_DELTA_ENV).unwrap_or(self.max_pixel_delta),
+            max_mismatched_fraction: read(MISMATCH_FRACTION_ENV).unwrap_or(self.max_mismatched_fraction),
+        }
+    }
+}
+
+pub fn references_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden/references")
+}
+
+pub fn failures_dir() -> PathBuf {
+    Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden-failures")
+}
+
+/// Creates a headless renderer on the fallback (software) adapter, so results don't depend on the GPU.
+pub fn headless_renderer(width: u32, height: u32) -> MainRenderer {
+
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+    pollster::block_on(MainRenderer::new_headless(&instance, width, height, true, AssetServer::default()))
+        .expect("Failed to create a headless renderer")
+}
+
+pub fn render_to_image(renderer: &mut MainRenderer) -> RgbaImage {
+
+    renderer.finish_loading();
+
+    renderer.render_offscreen().expect("Failed to render offscreen");
+
+    let pixels = renderer.read_pixels();
+    RgbaImage::from_raw(renderer.surface_config.width, renderer.surface_config.height, pixels)
+        .expect("Readback size doesn't match the render target!")
+}
+
+/// Compares `actual` against the reference image `name`.png, writing actual/expected/diff images
+/// to the failures directory and panicking if they differ by more than `tolerance`.
+pub fn assert_matches_reference(name: &str, actual: &RgbaImage, tolerance: Tolerance) {
+
+    let tolerance = tolerance.with_env_overrides();
+    let reference_path = references_dir().join(format!("{name}.png"));
+
+    if std::env::var_os(UPDATE_ENV).is_some() {
+        std::fs::create_dir_all(references_dir()).unwrap();
+        actual.save(&reference_path).unwrap();
+        println!("Updated reference image {}", reference_path.display());
+        return;
+    }
+
+    let expected = match image::open(&reference_path) {
+        Ok(image) => image.to_rgba8(),
+        Err(error) => {
+            let actual_path = write_failure_image(name, "actual", actual);
+            panic!(
+                "Missing reference image {} ({error}). Actual output written to {}; rerun with {UPDATE_ENV}=1 to record it.",
+                reference_path.display(),
+                actual_path.display(),
+            );
+        }
+    };
+
+    if expected.dimensions() != actual.dimensions() {
+        let actual_path = write_failure_image(name, "actual", actual);
+        panic!(
+            "{name}: size mismatch, expected {:?} but rendered {:?}. Actual output written to {}",
+            expected.dimensions(),
+            actual.dimensions(),
+            actual_path.display(),
+        );
+    }
+
+    let mut diff = RgbaImage::new(actual.width(), actual.height());
+    let mut mismatched_pixels = 0usize;
+    let mut worst_delta = 0.0f32;
+
+    for (x, y, actual_pixel) in actual.enumerate_pixels() {
+
+        let expected_pixel = expected.get_pixel(x, y);
+        let delta = perceptual_delta(actual_pixel, expected_pixel);
+        worst_delta = worst_delta.max(delta);
+
+        if delta > tolerance.max_pixel_delta {
+            mismatched_pixels += 1;
+            diff.put_pixel(x, y, Rgba([255, 0, 0, 255]));
+        } else {
+            // Faded copy of the expected image, so mismatches stand out
+            let luma = (0.299 * expected_pixel[0] as f32
+                + 0.587 * expected_pixel[1] as f32
+                + 0.114 * expected_pixel[2] as f32) as u8;
+            let faded = 192 + luma / 4;
+            diff.put_pixel(x, y, Rgba([faded, faded, faded, 255]));
+        }
+    }
+
+    let total_pixels = (actual.width() * actual.height()) as usize;
+    let mismatched_fraction = mismatched_pixels as f32 / total_pixels as f32;
+
+    if mismatched_fraction > tolerance.max_mismatched_fraction {
+        write_failure_image(name, "actual", actual);
+        write_failure_image(name, "expected", &expected);
+        write_failure_image(name, "diff", &diff);
+
+        panic!(
+            "{name}: {mismatched_pixels} of {total_pixels} pixels ({:.3}%) differ by more than {} (worst {worst_delta:.4}), \
+             allowed {:.3}%. Images written to {}",
+            mismatched_fraction * 100.0,
+            tolerance.max_pixel_delta,
+            tolerance.max_mismatched_fraction * 100.0,
+            failures_dir().display(),
+        );
+    }
+}
+
+/// Difference between two sRGB pixels in YIQ space, normalized to 0.0 - 1.0.
+/// Luma differences weigh more than chroma ones, roughly the way the eye perceives them.
+fn perceptual_delta(a: &Rgba<u8>, b: &Rgba<u8>) -> f32 {
+
+    // Blend against white so transparent pixels compare by what would be visible
+    let blend = |pixel: &Rgba<u8>| {
+        let alpha = pixel[3] as f32 / 255.0;
+        [0, 1, 2].map(|channel| 255.0 + (pixel[channel] as f32 - 255.0) * alpha)
+    };
+
+    let [r1, g1, b1] = blend(a);
+    let [r2, g2, b2] = blend(b);
+
+    let (dr, dg, db) = (r1 - r2, g1 - g2, b1 - b2);
+
+    let y = dr * 0.298_895_3 + dg * 0.586_622_5 + db * 0.114_482_23;
+    let i = dr * 0.595_977_99 - dg * 0.274_176_9 - db * 0.321_801_1;
+    let q = dr * 0.211_470_17 - dg * 0.522_617_2 + db * 0.311_147_03;
+
+    // Largest possible value of the weighted sum below, reached between black and white
+    const MAX_DELTA: f32 = 35215.0;
+
+    (0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q) / MAX_DELTA
+}
+
+fn write_failure_image(name: &str, kind: &str, image: &RgbaImage) -> PathBuf {
+
+    std::fs::create_dir_all(failures_dir()).unwrap();
+
+    let path = failures_dir().join(format!("{name}.{kind}.png"));
+    image.save(&path).unwrap();
+    path
+}
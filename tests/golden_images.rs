@@ -0,0 +1,200 @@
+mod golden;
+
+use egui_wgpu::wgpu;
+use golden::Tolerance;
+
+#[test]
+fn checker_triangle() {
+
+    let mut renderer = golden::headless_renderer(256, 256);
+    let image = golden::render_to_image(&mut renderer);
+
+    golden::assert_matches_reference("checker_triangle", &image, Tolerance::default());
+}
+
+#[test]
+fn checker_triangle_after_device_rebuild() {
+
+    let renderer = golden::headless_renderer(256, 256);
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+    let mut renderer = pollster::block_on(renderer.rebuild(&instance)).expect("Failed to rebuild the renderer");
+
+    let image = golden::render_to_image(&mut renderer);
+
+    golden::assert_matches_reference("checker_triangle", &image, Tolerance::default());
+}
+
+#[test]
+fn several_meshes_with_several_primitives() {
+
+    use candle::app::main_renderer::model::{MeshData, PrimitiveData};
+
+    let quad = |x: f32, y: f32| PrimitiveData {
+        positions: vec![[x, y, 0.0], [x + 0.3, y, 0.0], [x + 0.3, y + 0.3, 0.0], [x, y + 0.3, 0.0]],
+        uvs: vec![[0.0, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0]],
+        indices: vec![0, 1, 2, 0, 2, 3],
+        ..Default::default()
+    };
+
+    let mut renderer = golden::headless_renderer(256, 256);
+    // Far enough back for the corners of clip space at z = 0 to be in view
+    renderer.camera.position.z = 2.5;
+    renderer.add_mesh(MeshData {
+        name: "Corners".to_owned(),
+        primitives: vec![quad(-0.9, 0.6), quad(0.6, 0.6)],
+    });
+    renderer.add_mesh(MeshData {
+        name: "Bottom".to_owned(),
+        primitives: vec![quad(-0.15, -0.95)],
+    });
+
+    let image = golden::render_to_image(&mut renderer);
+
+    golden::assert_matches_reference("several_meshes", &image, Tolerance::default());
+}
+
+#[test]
+fn checker_triangle_from_deinterleaved_streams() {
+
+    use candle::app::main_renderer::scene::Node;
+    use candle::app::main_renderer::vertex::{VertexAttributeKind, VertexStreams};
+    use candle::Mesh;
+
+    let mut renderer = golden::headless_renderer(256, 256);
+
+    let data = renderer.cpu_resources.default_mesh.clone();
+    let streams = VertexStreams::new().deinterleaved(&[VertexAttributeKind::Uv0, VertexAttributeKind::Position]);
+    let mesh = renderer.assets.meshes.add(Mesh::with_streams(data, streams, &renderer.device));
+    renderer.scene.clear();
+    renderer.scene.add(Node::new("Deinterleaved").with_mesh(mesh), None);
+
+    let image = golden::render_to_image(&mut renderer);
+
+    golden::assert_matches_reference("checker_triangle", &image, Tolerance::default());
+}
+
+#[test]
+fn scene_hierarchy_with_tinted_materials() {
+
+    use candle::app::main_renderer::model::{MeshData, PrimitiveData};
+    use candle::glam::{Quat, Vec3};
+    use candle::{Material, Mesh, Node, Transform};
+
+    let mut renderer = golden::headless_renderer(256, 256);
+    renderer.scene.clear();
+
+    let quad = renderer.assets.meshes.add(Mesh::new(
+        MeshData {
+            name: "Quad".to_owned(),
+            primitives: vec![PrimitiveData {
+                positions: vec![[-0.3, -0.3, 0.0], [0.3, -0.3, 0.0], [0.3, 0.3, 0.0], [-0.3, 0.3, 0.0]],
+                uvs: vec![[0.0, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0]],
+                indices: vec![0, 1, 2, 0, 2, 3],
+                ..Default::default()
+            }],
+        },
+        &renderer.device,
+    ));
+
+    let red = renderer.assets.materials.add(Material {
+        name: "Red".to_owned(),
+        base_color_factor: [1.0, 0.1, 0.1, 1.0],
+        base_color_texture: None,
+    });
+    let blue_checker = renderer.assets.materials.add(Material {
+        name: "Blue checker".to_owned(),
+        base_color_factor: [0.3, 0.3, 1.0, 1.0],
+        base_color_texture: Some(renderer.diffuse_texture.clone()),
+    });
+
+    // Both quads turn with their parent, the right one is also smaller and turned on its own
+    let pivot = renderer.scene.add(
+        Node::new("Pivot").with_transform(Transform::from_rotation(Quat::from_rotation_z(0.4))),
+        None,
+    );
+    renderer.scene.add(
+        Node::new("Left")
+            .with_transform(Transform::from_translation(Vec3::new(-0.45, 0.0, 0.0)))
+            .with_mesh(quad.clone())
+            .with_materials(vec![red]),
+        Some(pivot),
+    );
+    renderer.scene.add(
+        Node::new("Right")
+            .with_transform(Transform {
+                translation: Vec3::new(0.45, 0.0, 0.0),
+                rotation: Quat::from_rotation_z(0.4),
+                scale: Vec3::splat(0.6),
+            })
+            .with_mesh(quad)
+            .with_materials(vec![blue_checker]),
+        Some(pivot),
+    );
+
+    let image = golden::render_to_image(&mut renderer);
+
+    golden::assert_matches_reference("scene_hierarchy", &image, Tolerance::default());
+}
+
+#[test]
+fn nearer_surfaces_hide_farther_ones_whatever_the_draw_order() {
+
+    use candle::app::main_renderer::model::{MeshData, PrimitiveData};
+    use candle::glam::{Quat, Vec3};
+    use candle::{Material, Mesh, Node, Transform};
+
+    // Same picture with and without stencil, and after the depth buffer was recreated by resizing
+    for depth_format in [wgpu::TextureFormat::Depth32Float, wgpu::TextureFormat::Depth24PlusStencil8] {
+
+        let mut renderer = golden::headless_renderer(256, 256);
+        renderer.set_depth_format(depth_format);
+        renderer.resize_surface(128, 128);
+        renderer.resize_surface(256, 256);
+        renderer.scene.clear();
+
+        let quad = renderer.assets.meshes.add(Mesh::new(
+            MeshData {
+                name: "Quad".to_owned(),
+                primitives: vec![PrimitiveData {
+                    positions: vec![[-0.4, -0.4, 0.0], [0.4, -0.4, 0.0], [0.4, 0.4, 0.0], [-0.4, 0.4, 0.0]],
+                    uvs: vec![[0.0, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0]],
+                    indices: vec![0, 1, 2, 0, 2, 3],
+                    ..Default::default()
+                }],
+            },
+            &renderer.device,
+        ));
+
+        let material = |renderer: &mut candle::MainRenderer, name: &str, base_color_factor| {
+            renderer.assets.materials.add(Material { name: name.to_owned(), base_color_factor, base_color_texture: None })
+        };
+        let red = material(&mut renderer, "Red", [1.0, 0.1, 0.1, 1.0]);
+        let green = material(&mut renderer, "Green", [0.1, 1.0, 0.1, 1.0]);
+        let blue = material(&mut renderer, "Blue", [0.1, 0.1, 1.0, 1.0]);
+
+        // The nearest quad is drawn first, and the blue one cuts through the red one at an angle
+        renderer.scene.add(
+            Node::new("Near")
+                .with_transform(Transform { translation: Vec3::new(-0.3, -0.3, 0.5), scale: Vec3::splat(0.5), ..Default::default() })
+                .with_mesh(quad.clone())
+                .with_materials(vec![green]),
+            None,
+        );
+        renderer.scene.add(Node::new("Flat").with_mesh(quad.clone()).with_materials(vec![red]), None);
+        renderer.scene.add(
+            Node::new("Tilted")
+                .with_transform(Transform {
+                    rotation: Quat::from_rotation_y(1.0),
+                    scale: Vec3::splat(1.4),
+                    ..Default::default()
+                })
+                .with_mesh(quad)
+                .with_materials(vec![blue]),
+            None,
+        );
+
+        let image = golden::render_to_image(&mut renderer);
+
+        golden::assert_matches_reference("depth_occlusion", &image, Tolerance::default());
+    }
+}
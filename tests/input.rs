@@ -0,0 +1,160 @@
+use candle::app::input::{actions, axes, AxisBinding, Binding, Button, Input, InputBindings, Modifiers};
+use candle::glam::Vec2;
+use candle::CandleError;
+use winit::event::MouseButton;
+use winit::keyboard::KeyCode;
+
+#[test]
+fn presses_and_releases_only_last_one_frame() {
+
+    let mut input = Input::default();
+    let w = Button::Key(KeyCode::KeyW);
+
+    input.press(w);
+    assert!(input.was_pressed(w) && input.is_held(w));
+
+    // Key repeats don't count as new presses
+    input.end_frame();
+    input.press(w);
+    assert!(!input.was_pressed(w) && input.is_held(w));
+
+    input.release(w);
+    assert!(input.was_released(w) && !input.is_held(w));
+    input.end_frame();
+    assert!(!input.was_released(w));
+
+    // Tapped within a single frame
+    input.press(w);
+    input.release(w);
+    assert!(input.was_pressed(w) && input.was_released(w) && !input.is_held(w));
+}
+
+#[test]
+fn frames_that_are_not_drawn_still_forget_their_events() {
+
+    let mut input = Input::default();
+    let left = Button::Mouse(MouseButton::Left);
+
+    // Minimized, so the app ends the frame without drawing it
+    input.press(left);
+    input.add_mouse_motion(Vec2::new(300.0, -200.0));
+    input.add_scroll(2.0);
+    input.end_frame();
+
+    // The next drawn frame doesn't see the click or the motion again
+    input.add_mouse_motion(Vec2::new(1.0, 0.0));
+    assert!(!input.was_pressed(left) && input.is_held(left));
+    assert_eq!(input.mouse_delta(), Vec2::new(1.0, 0.0));
+    assert_eq!(input.scroll(), 0.0);
+}
+
+#[test]
+fn pressed_actions_need_exactly_their_modifiers() {
+
+    let mut input = Input::default();
+
+    input.press(Button::Key(KeyCode::Numpad1));
+    assert!(input.action_pressed(actions::CAMERA_VIEW_FRONT));
+    assert!(!input.action_pressed(actions::CAMERA_VIEW_BACK));
+
+    input.release(Button::Key(KeyCode::Numpad1));
+    input.end_frame();
+    input.press(Button::Key(KeyCode::ControlRight));
+    input.press(Button::Key(KeyCode::Numpad1));
+    assert!(!input.action_pressed(actions::CAMERA_VIEW_FRONT));
+    assert!(input.action_pressed(actions::CAMERA_VIEW_BACK));
+
+    // Modifier keys bound on their own don't count themselves as a modifier
+    input.release(Button::Key(KeyCode::ControlRight));
+    input.end_frame();
+    input.press(Button::Key(KeyCode::ShiftLeft));
+    assert!(input.action_pressed(actions::CAMERA_FAST));
+    assert_eq!(input.modifiers(), Modifiers::SHIFT);
+}
+
+#[test]
+fn held_actions_allow_extra_modifiers() {
+
+    let mut input = Input::default();
+
+    input.press(Button::Key(KeyCode::ShiftLeft));
+    input.press(Button::Mouse(MouseButton::Left));
+    assert!(input.action_held(actions::CAMERA_ORBIT));
+    assert!(input.action_held(actions::CAMERA_PAN));
+
+    input.release(Button::Key(KeyCode::ShiftLeft));
+    assert!(!input.action_held(actions::CAMERA_PAN));
+
+    input.release(Button::Mouse(MouseButton::Left));
+    assert!(input.action_released(actions::CAMERA_ORBIT));
+    assert!(!input.action_held("no_such_action") && !input.action_pressed("no_such_action"));
+}
+
+#[test]
+fn axes_add_up_their_bindings() {
+
+    let mut input = Input::default();
+    input.bindings.axes.insert("turn".to_owned(), vec![
+        AxisBinding::Buttons { positive: Button::Key(KeyCode::ArrowRight), negative: Button::Key(KeyCode::ArrowLeft) },
+        AxisBinding::MouseX { scale: 0.5 },
+    ]);
+
+    input.press(Button::Key(KeyCode::ArrowRight));
+    input.add_mouse_motion(Vec2::new(4.0, 7.0));
+    input.add_mouse_motion(Vec2::new(2.0, 0.0));
+    assert_eq!(input.axis("turn"), 4.0);
+    assert_eq!(input.axis(axes::CAMERA_LOOK_Y), 7.0);
+
+    input.press(Button::Key(KeyCode::ArrowLeft));
+    input.add_scroll(-2.0);
+    assert_eq!(input.axis("turn"), 3.0);
+    assert_eq!(input.axis(axes::CAMERA_ZOOM), -2.0);
+
+    // Motion and scroll only last a frame, held buttons don't
+    input.release(Button::Key(KeyCode::ArrowLeft));
+    input.end_frame();
+    assert_eq!(input.axis("turn"), 1.0);
+    assert_eq!(input.axis(axes::CAMERA_ZOOM), 0.0);
+    assert_eq!(input.axis("no_such_axis"), 0.0);
+}
+
+#[test]
+fn bindings_files_only_need_to_list_changes() {
+
+    let json = r#"{
+        "actions": {
+            "camera_focus": [{ "button": { "Key": "KeyG" }, "modifiers": { "alt": true } }],
+            "camera_toggle_projection": [],
+            "paint": [{ "button": { "Mouse": "Left" } }]
+        }
+    }"#;
+
+    let bindings = InputBindings::from_json(json).unwrap();
+    let defaults = InputBindings::default();
+
+    let alt = Modifiers { alt: true, ..Modifiers::NONE };
+    assert_eq!(bindings.actions[actions::CAMERA_FOCUS], vec![Binding::key(KeyCode::KeyG).with_modifiers(alt)]);
+    assert!(bindings.actions[actions::CAMERA_TOGGLE_PROJECTION].is_empty());
+    assert_eq!(bindings.actions["paint"], vec![Binding::mouse(MouseButton::Left)]);
+    assert_eq!(bindings.actions[actions::CAMERA_LOOK], defaults.actions[actions::CAMERA_LOOK]);
+    assert_eq!(bindings.axes, defaults.axes);
+
+    assert_eq!(InputBindings::from_json(&bindings.to_json()).unwrap(), bindings);
+}
+
+#[test]
+fn bindings_round_trip_through_a_file() {
+
+    let path = std::env::temp_dir().join(format!("candle_input_bindings_{}.json", std::process::id()));
+
+    let mut bindings = InputBindings::default();
+    bindings.actions.insert("screenshot".to_owned(), vec![Binding::key(KeyCode::F12)]);
+    bindings.save(&path).unwrap();
+    assert_eq!(InputBindings::load(&path).unwrap(), bindings);
+
+    std::fs::write(&path, "{ \"actions\": 3 }").unwrap();
+    assert!(matches!(InputBindings::load(&path), Err(CandleError::InputBindingsParse { .. })));
+
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(InputBindings::load(&path), Err(CandleError::InputBindingsRead { .. })));
+}
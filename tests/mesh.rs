@@ -0,0 +1,64 @@
+use candle::app::main_renderer::model::{MeshData, PrimitiveData};
+use candle::{wgpu, Aabb, AssetServer, MainRenderer, Mesh};
+
+fn device() -> MainRenderer {
+
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+    pollster::block_on(MainRenderer::new_headless(&instance, 16, 16, true, AssetServer::default()))
+        .expect("Failed to create a headless renderer")
+}
+
+fn triangle(offset: f32) -> PrimitiveData {
+    PrimitiveData {
+        positions: vec![[offset, 0.0, 0.0], [offset + 1.0, 0.0, 0.0], [offset, 1.0, -1.0]],
+        indices: vec![0, 1, 2],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn primitives_become_submeshes_sharing_buffers() {
+
+    let renderer = device();
+
+    let mut second = triangle(2.0);
+    second.material = Some(3);
+
+    let mesh = Mesh::new(
+        MeshData { name: "Pair".to_owned(), primitives: vec![triangle(0.0), second] },
+        &renderer.device,
+    );
+
+    assert_eq!(mesh.index_count, 6, "the index count is what gets drawn, not the vertex count");
+    assert_eq!(mesh.vertex_count, 6);
+    assert_eq!(mesh.index_format, wgpu::IndexFormat::Uint16);
+
+    assert_eq!(mesh.submeshes.len(), 2);
+    assert_eq!(mesh.submeshes[1].indices, 3..6);
+    assert_eq!(mesh.submeshes[1].base_vertex, 3);
+    assert_eq!(mesh.submeshes[1].material, Some(3));
+
+    assert_eq!(mesh.aabb, Some(Aabb { min: [0.0, 0.0, -1.0], max: [3.0, 1.0, 0.0] }));
+    assert_eq!(mesh.aabb.unwrap().center(), [1.5, 0.5, -0.5]);
+}
+
+#[test]
+fn large_indices_switch_to_u32() {
+
+    let renderer = device();
+
+    let vertex_count = u16::MAX as usize + 2;
+    let large = PrimitiveData {
+        positions: vec![[0.0; 3]; vertex_count],
+        indices: vec![0, 1, vertex_count as u32 - 1],
+        ..Default::default()
+    };
+
+    let mesh = Mesh::new(MeshData { name: "Large".to_owned(), primitives: vec![large] }, &renderer.device);
+    assert_eq!(mesh.index_format, wgpu::IndexFormat::Uint32);
+    assert_eq!(mesh.index_buffer.size(), 3 * 4);
+
+    let empty = Mesh::new(MeshData::default(), &renderer.device);
+    assert_eq!(empty.index_count, 0);
+    assert_eq!(empty.aabb, None);
+}
@@ -0,0 +1,121 @@
+use std::path::{Path, PathBuf};
+
+use candle::app::main_renderer::model::AlphaMode;
+use candle::{AssetServer, Model};
+
+fn test_dir(name: &str) -> PathBuf {
+
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("obj_loader").join(name);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn ngons_are_triangulated_and_get_flat_normals() {
+
+    let dir = test_dir("flat");
+    std::fs::write(dir.join("ngons.obj"), "\
+o Shapes
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+v 0.5 1.5 0
+f 1 2 3 4
+f 1 2 5
+f 1 4 5 3 2
+").unwrap();
+
+    let model = Model::load(dir.join("ngons.obj")).unwrap();
+
+    assert_eq!(model.meshes.len(), 1);
+    assert_eq!(model.meshes[0].name, "Shapes");
+    assert_eq!(model.root_nodes, vec![0]);
+    assert_eq!(model.nodes[0].mesh, Some(0));
+
+    let primitive = &model.meshes[0].primitives[0];
+    let triangle_count = 2 + 1 + 3;
+    assert_eq!(primitive.indices.len(), triangle_count * 3);
+    assert_eq!(primitive.positions.len(), triangle_count * 3, "flat shaded triangles don't share vertices");
+
+    let (counter_clockwise, clockwise) = primitive.normals.split_at(3 * 3);
+    assert!(counter_clockwise.iter().all(|normal| *normal == [0.0, 0.0, 1.0]), "{counter_clockwise:?}");
+    assert!(clockwise.iter().all(|normal| *normal == [0.0, 0.0, -1.0]), "the pentagon is wound the other way");
+}
+
+#[test]
+fn separate_indices_are_combined_into_shared_vertices() {
+
+    let dir = test_dir("indexed");
+    std::fs::write(dir.join("quad.obj"), "\
+mtllib quad.mtl
+v -1 -1 0
+v 1 -1 0
+v 1 1 0
+v -1 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 1
+usemtl Brick
+f 1/1/1 2/2/1 3/3/1
+f 1/1/1 3/3/1 4/4/1
+").unwrap();
+    std::fs::write(dir.join("quad.mtl"), "\
+newmtl Brick
+Kd 0.8 0.2 0.1
+d 0.5
+Ns 0
+map_Kd brick.png
+").unwrap();
+    image::RgbaImage::from_pixel(2, 2, image::Rgba([200, 50, 25, 255])).save(dir.join("brick.png")).unwrap();
+
+    let mut assets = AssetServer::new(&dir);
+    let handle = assets.load_model("quad.obj").unwrap();
+    let model = assets.models.get(&handle).unwrap();
+
+    let primitive = &model.meshes[0].primitives[0];
+    assert_eq!(primitive.positions.len(), 4, "corners used by both triangles are shared");
+    assert_eq!(primitive.indices, vec![0, 1, 2, 0, 2, 3]);
+    assert_eq!(primitive.normals, vec![[0.0, 0.0, 1.0]; 4]);
+    assert_eq!(primitive.uvs[0], [0.0, 1.0], "V is flipped to wgpu's top left origin");
+    assert_eq!(primitive.vertices()[2].uv, [1.0, 0.0]);
+    assert_eq!(primitive.material, Some(0));
+
+    let material = &model.materials[0];
+    assert_eq!(material.name, "Brick");
+    assert_eq!(material.base_color_factor, [0.8, 0.2, 0.1, 0.5]);
+    assert_eq!(material.alpha_mode, AlphaMode::Blend);
+    assert_eq!(material.roughness_factor, 1.0);
+    assert_eq!(material.base_color_texture, Some(0));
+    assert_eq!(model.images[0].name, "brick");
+}
+
+#[test]
+fn missing_material_libraries_are_not_fatal() {
+
+    let dir = test_dir("missing_mtl");
+    std::fs::write(dir.join("triangle.obj"), "mtllib missing.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+
+    let model = Model::load(dir.join("triangle.obj")).unwrap();
+    assert!(model.materials.is_empty());
+    assert_eq!(model.meshes[0].primitives[0].indices.len(), 3);
+
+    assert!(Model::load(dir.join("missing.obj")).is_err());
+    assert!(Model::load(dir.join("triangle.fbx")).is_err());
+}
+
+#[test]
+fn missing_textures_are_not_fatal() {
+
+    let dir = test_dir("missing_texture");
+    std::fs::write(dir.join("triangle.obj"), "mtllib dangling.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl Painted\nf 1 2 3\n").unwrap();
+    std::fs::write(dir.join("dangling.mtl"), "newmtl Painted\nKd 0.5 0.5 0.5\nmap_Kd missing.png\n").unwrap();
+
+    let model = Model::load(dir.join("triangle.obj")).unwrap();
+    assert_eq!(model.meshes[0].primitives[0].indices.len(), 3);
+    assert_eq!(model.materials[0].base_color_factor, [0.5, 0.5, 0.5, 1.0]);
+    assert_eq!(model.materials[0].base_color_texture, None);
+    assert!(model.images.is_empty());
+}
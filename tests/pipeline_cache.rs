@@ -0,0 +1,86 @@
+use candle::{wgpu, AssetServer, MainRenderer};
+
+#[test]
+fn variants_are_built_once_and_rebuilt_with_their_shader() {
+
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+    let mut renderer = pollster::block_on(MainRenderer::new_headless(&instance, 64, 64, true, AssetServer::default()))
+        .expect("Failed to create a headless renderer");
+
+    assert_eq!(renderer.pipeline_cache.len(), 1);
+
+    let mut double_sided = renderer.main_pipeline_key.clone();
+    double_sided.cull_mode = None;
+
+    let mut alpha_blended = renderer.main_pipeline_key.clone();
+    alpha_blended.blend = Some(wgpu::BlendState::ALPHA_BLENDING);
+
+    for key in [&double_sided, &alpha_blended, &double_sided] {
+        renderer.pipeline_cache.get_or_create(&renderer.device, key);
+    }
+    assert_eq!(renderer.pipeline_cache.len(), 3);
+
+    let source = renderer.cpu_resources.shader_source.clone();
+    let shader = renderer.main_pipeline_key.shader;
+
+    renderer.pipeline_cache
+        .try_replace_shader(&renderer.device, shader, &source)
+        .expect("Recompiling the same shader should succeed");
+
+    let broken = source.replace("fn fragment", "fn fragmen");
+    assert!(renderer.pipeline_cache.try_replace_shader(&renderer.device, shader, &broken).is_err());
+
+    assert_eq!(renderer.pipeline_cache.len(), 3);
+    renderer.render_offscreen().expect("The previous pipelines should still render");
+}
+
+#[test]
+fn variants_for_the_previous_depth_format_are_evicted() {
+
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+    let mut renderer = pollster::block_on(MainRenderer::new_headless(&instance, 64, 64, true, AssetServer::default()))
+        .expect("Failed to create a headless renderer");
+
+    let previous_key = renderer.main_pipeline_key.clone();
+    let pipeline_count = renderer.pipeline_cache.len();
+
+    renderer.set_depth_format(wgpu::TextureFormat::Depth24PlusStencil8);
+
+    assert!(renderer.pipeline_cache.get(&previous_key).is_none());
+    assert!(renderer.pipeline_cache.get(&renderer.main_pipeline_key).is_some());
+    assert_eq!(renderer.pipeline_cache.len(), pipeline_count);
+    renderer.render_offscreen().expect("The new pipelines should render");
+}
+
+#[test]
+fn meshes_missing_shader_inputs_are_skipped() {
+
+    use candle::app::main_renderer::model::{MeshData, PrimitiveData};
+    use candle::app::main_renderer::vertex::{VertexAttributeKind, VertexStreams};
+    use candle::{Mesh, Node};
+
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+    let mut renderer = pollster::block_on(MainRenderer::new_headless(&instance, 64, 64, true, AssetServer::default()))
+        .expect("Failed to create a headless renderer");
+
+    // The shader reads UVs, which this mesh doesn't upload
+    let data = MeshData {
+        name: "Positions only".to_owned(),
+        primitives: vec![PrimitiveData {
+            positions: vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+            indices: vec![0, 1, 2],
+            ..Default::default()
+        }],
+    };
+    let streams = VertexStreams::new().interleaved(&[VertexAttributeKind::Position]);
+    let mesh = Mesh::with_streams(data, streams, &renderer.device);
+    let key = renderer.pipeline_key_for(&mesh);
+
+    assert!(renderer.pipeline_cache.prepare(&renderer.device, &key).is_none());
+    assert!(renderer.pipeline_cache.get(&key).is_none());
+    assert!(renderer.pipeline_cache.prepare(&renderer.device, &key).is_none(), "failures aren't retried");
+
+    let handle = renderer.assets.meshes.add(mesh);
+    renderer.scene.add(Node::new("Positions only").with_mesh(handle), None);
+    renderer.render_offscreen().expect("The other meshes should still render");
+}
@@ -0,0 +1,79 @@
+use candle::app::render_graph::{RenderGraph, TextureSize, TransientTextureDesc, OUTPUT};
+use candle::wgpu;
+
+fn color_texture() -> TransientTextureDesc {
+    TransientTextureDesc {
+        format: wgpu::TextureFormat::Rgba16Float,
+        size: TextureSize::Output,
+    }
+}
+
+#[test]
+fn readers_run_after_writers_regardless_of_insertion_order() {
+
+    let mut graph = RenderGraph::new();
+    graph.create_texture("shadow_map", color_texture());
+    graph.create_texture("hdr", color_texture());
+
+    graph.add_pass("Tonemap", &["hdr"], &[OUTPUT], |_, _| {});
+    graph.add_pass("Lighting", &["shadow_map"], &["hdr"], |_, _| {});
+    graph.add_pass("Shadows", &[], &["shadow_map"], |_, _| {});
+    graph.set_composite_pass("GUI", |_, _| {});
+
+    assert_eq!(graph.execution_order().unwrap(), ["Shadows", "Lighting", "Tonemap", "GUI"]);
+}
+
+#[test]
+fn writers_of_the_same_resource_keep_their_order() {
+
+    let mut graph = RenderGraph::new();
+    graph.add_pass("Opaque", &[], &[OUTPUT], |_, _| {});
+    graph.add_pass("Transparent", &[], &[OUTPUT], |_, _| {});
+
+    assert_eq!(graph.execution_order().unwrap(), ["Opaque", "Transparent"]);
+}
+
+#[test]
+fn post_processing_reads_output_before_writing_it_back() {
+
+    let mut graph = RenderGraph::new();
+    graph.create_texture("bloom", color_texture());
+
+    graph.add_pass("Main", &[], &[OUTPUT], |_, _| {});
+    graph.add_pass("Bloom", &[OUTPUT], &["bloom"], |_, _| {});
+    graph.add_pass("Composite", &["bloom"], &[OUTPUT], |_, _| {});
+    graph.add_pass("Debug Overlay", &[OUTPUT], &[OUTPUT], |_, _| {});
+
+    assert_eq!(graph.execution_order().unwrap(), ["Main", "Bloom", "Composite", "Debug Overlay"]);
+}
+
+#[test]
+fn passes_not_contributing_to_output_are_culled() {
+
+    let mut graph = RenderGraph::new();
+    graph.create_texture("debug", color_texture());
+
+    graph.add_pass("Debug", &[], &["debug"], |_, _| {});
+    graph.add_pass("Main", &[], &[OUTPUT], |_, _| {});
+
+    assert_eq!(graph.execution_order().unwrap(), ["Main"]);
+}
+
+#[test]
+fn invalid_graphs_are_rejected() {
+
+    let mut unwritten_read = RenderGraph::new();
+    unwritten_read.add_pass("Main", &["missing"], &[OUTPUT], |_, _| {});
+    assert!(unwritten_read.execution_order().is_err());
+
+    let mut undeclared_write = RenderGraph::new();
+    undeclared_write.add_pass("Main", &[], &["missing"], |_, _| {});
+    assert!(undeclared_write.execution_order().is_err());
+
+    let mut cycle = RenderGraph::new();
+    cycle.create_texture("a", color_texture());
+    cycle.create_texture("b", color_texture());
+    cycle.add_pass("A", &["b"], &["a"], |_, _| {});
+    cycle.add_pass("B", &["a"], &["b", OUTPUT], |_, _| {});
+    assert!(cycle.execution_order().is_err());
+}
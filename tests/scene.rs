@@ -0,0 +1,173 @@
+use std::f32::consts::FRAC_PI_2;
+
+use candle::app::main_renderer::model::{MaterialData, MeshData, ModelNode, PrimitiveData};
+use candle::glam::{Mat4, Quat, Vec3};
+use candle::{Aabb, AssetServer, MainRenderer, Model, Node, Scene, Transform, wgpu};
+
+fn assert_close(actual: Vec3, expected: Vec3) {
+    assert!(actual.abs_diff_eq(expected, 1e-4), "expected {expected}, got {actual}");
+}
+
+fn world_position(scene: &Scene, id: candle::NodeId) -> Vec3 {
+    scene.get(id).unwrap().world_matrix().transform_point3(Vec3::ZERO)
+}
+
+#[test]
+fn children_follow_their_parents() {
+
+    let mut scene = Scene::new();
+    let parent = scene.add(
+        Node::new("Parent").with_transform(Transform {
+            translation: Vec3::new(1.0, 0.0, 0.0),
+            rotation: Quat::from_rotation_y(FRAC_PI_2),
+            scale: Vec3::splat(2.0),
+        }),
+        None,
+    );
+    let child = scene.add(Node::new("Child").with_transform(Transform::from_translation(Vec3::X)), Some(parent));
+    let grandchild = scene.add(Node::new("Grandchild").with_transform(Transform::from_translation(Vec3::X)), Some(child));
+
+    assert_eq!(scene.update_world_matrices(), 3);
+
+    // Scaled by 2 and turned to point down -Z
+    assert_close(world_position(&scene, child), Vec3::new(1.0, 0.0, -2.0));
+    assert_close(world_position(&scene, grandchild), Vec3::new(1.0, 0.0, -4.0));
+    assert_eq!(scene.get(parent).unwrap().children(), &[child]);
+    assert_eq!(scene.ancestors(grandchild).collect::<Vec<_>>(), vec![child, parent]);
+}
+
+#[test]
+fn only_changed_subtrees_are_recomputed() {
+
+    let mut scene = Scene::new();
+    let a = scene.add(Node::new("A"), None);
+    let b = scene.add(Node::new("B"), Some(a));
+    scene.add(Node::new("C"), Some(b));
+    scene.add(Node::new("D"), None);
+
+    assert_eq!(scene.update_world_matrices(), 4);
+    assert_eq!(scene.update_world_matrices(), 0);
+
+    scene.get_mut(b).unwrap().transform_mut().translation.y = 3.0;
+    assert_eq!(world_position(&scene, b), Vec3::ZERO, "matrices are cached until updated");
+    assert_eq!(scene.update_world_matrices(), 2);
+    assert_close(world_position(&scene, scene.find("C").unwrap()), Vec3::new(0.0, 3.0, 0.0));
+}
+
+#[test]
+fn reparenting_keeps_the_local_transform_and_refuses_cycles() {
+
+    let mut scene = Scene::new();
+    let a = scene.add(Node::new("A").with_transform(Transform::from_translation(Vec3::Y)), None);
+    let b = scene.add(Node::new("B").with_transform(Transform::from_translation(Vec3::X)), None);
+    let c = scene.add(Node::new("C"), Some(b));
+
+    assert!(scene.set_parent(b, Some(a)));
+    assert_eq!(scene.roots(), &[a]);
+    scene.update_world_matrices();
+    assert_close(world_position(&scene, c), Vec3::new(1.0, 1.0, 0.0));
+
+    assert!(!scene.set_parent(a, Some(c)));
+    assert!(!scene.set_parent(a, Some(a)));
+    assert!(scene.set_parent(c, None));
+    assert_eq!(scene.roots(), &[a, c]);
+    assert!(scene.get(b).unwrap().children().is_empty());
+}
+
+#[test]
+fn removing_a_node_removes_everything_below_it() {
+
+    let mut scene = Scene::new();
+    let a = scene.add(Node::new("A"), None);
+    let b = scene.add(Node::new("B"), Some(a));
+    let c = scene.add(Node::new("C"), Some(b));
+
+    assert_eq!(scene.remove(b).unwrap().name, "B");
+    assert_eq!(scene.len(), 1);
+    assert!(!scene.contains(c));
+    assert!(scene.get(a).unwrap().children().is_empty());
+
+    // Reused slots don't bring old ids back to life
+    let d = scene.add(Node::new("D"), None);
+    assert!(!scene.contains(b) && !scene.contains(c));
+    assert_eq!(scene.get(d).unwrap().name, "D");
+    assert!(scene.remove(b).is_none());
+
+    scene.clear();
+    assert!(scene.is_empty() && scene.roots().is_empty() && !scene.contains(d));
+}
+
+#[test]
+fn transformed_boxes_contain_every_transformed_corner() {
+
+    let aabb = Aabb { min: [-1.0, -1.0, -1.0], max: [1.0, 1.0, 1.0] };
+    let matrix = Mat4::from_rotation_translation(Quat::from_rotation_z(FRAC_PI_2 / 2.0), Vec3::X);
+
+    // The corners of the turned square reach out to the length of its half diagonal
+    let transformed = aabb.transformed(matrix);
+    let reach = 2.0_f32.sqrt();
+    assert_close(Vec3::from(transformed.min), Vec3::new(1.0 - reach, -reach, -1.0));
+    assert_close(Vec3::from(transformed.max), Vec3::new(1.0 + reach, reach, 1.0));
+}
+
+#[test]
+fn models_keep_their_hierarchy_and_materials() {
+
+    let triangle = PrimitiveData {
+        positions: vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+        indices: vec![0, 1, 2],
+        ..Default::default()
+    };
+
+    let model = Model {
+        meshes: vec![MeshData {
+            name: "Triangles".to_owned(),
+            primitives: vec![
+                PrimitiveData { material: Some(0), ..triangle.clone() },
+                PrimitiveData { material: None, ..triangle },
+            ],
+        }],
+        materials: vec![MaterialData { name: "Red".to_owned(), base_color_factor: [1.0, 0.0, 0.0, 1.0], ..Default::default() }],
+        images: Vec::new(),
+        nodes: vec![
+            ModelNode {
+                name: "Root".to_owned(),
+                translation: [0.0, 0.0, -5.0],
+                rotation: [0.0, 0.0, 0.0, 1.0],
+                scale: [1.0; 3],
+                mesh: None,
+                children: vec![1],
+            },
+            ModelNode {
+                name: "Leaf".to_owned(),
+                translation: [2.0, 0.0, 0.0],
+                rotation: [0.0, 0.0, 0.0, 1.0],
+                scale: [1.0; 3],
+                mesh: Some(0),
+                children: Vec::new(),
+            },
+        ],
+        root_nodes: vec![0],
+    };
+
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+    let mut renderer = pollster::block_on(MainRenderer::new_headless(&instance, 64, 64, true, AssetServer::default()))
+        .expect("Failed to create a headless renderer");
+    renderer.scene.clear();
+
+    let root = renderer.add_model("Model", &model, None);
+    renderer.scene.update_world_matrices();
+
+    let leaf = renderer.scene.find("Leaf").unwrap();
+    assert_eq!(renderer.scene.ancestors(leaf).last(), Some(root));
+    assert_close(world_position(&renderer.scene, leaf), Vec3::new(2.0, 0.0, -5.0));
+    assert_eq!(renderer.bounds(), Some(Aabb { min: [2.0, 0.0, -5.0], max: [3.0, 1.0, -5.0] }));
+
+    let materials = &renderer.scene.get(leaf).unwrap().materials;
+    let material = |index: usize| renderer.assets.materials.get(&materials[index]).unwrap();
+    assert_eq!(material(0).name, "Red");
+    assert_eq!(material(1).base_color_factor, [1.0; 4]);
+    assert!(material(1).base_color_texture.is_none());
+
+    renderer.render_offscreen().expect("Failed to render the model");
+}
@@ -0,0 +1,158 @@
+use std::path::{Path, PathBuf};
+
+use candle::app::camera_controller::{CameraControls, CameraMode, CameraRequest};
+use candle::app::main_renderer::shapes::{Cube, Torus};
+use candle::app::scene_file::{NodeDesc, SCENE_FILE_VERSION};
+use candle::glam::Vec3;
+use candle::{
+    wgpu, AssetServer, CandleError, Light, MainRenderer, Material, Node, ProceduralShape, SceneFile, Transform,
+};
+
+fn test_dir(name: &str) -> PathBuf {
+
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("scene_file").join(name);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::copy(concat!(env!("CARGO_MANIFEST_DIR"), "/src/resources/Checker.png"), dir.join("Checker.png")).unwrap();
+    std::fs::write(dir.join("quad.obj"), "o Quad\nv 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf 1 2 3 4\n").unwrap();
+    dir
+}
+
+fn renderer(root: &Path) -> MainRenderer {
+
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+    pollster::block_on(MainRenderer::new_headless(&instance, 64, 32, true, AssetServer::new(root)))
+        .expect("Failed to create a headless renderer")
+}
+
+fn node_names(renderer: &MainRenderer) -> Vec<String> {
+    renderer.scene.iter_depth_first().map(|(_, node)| node.name.clone()).collect()
+}
+
+#[test]
+fn scenes_survive_a_round_trip() {
+
+    let dir = test_dir("round_trip");
+    let mut renderer = renderer(&dir);
+    let mut controls = CameraControls::new();
+
+    let MainRenderer { assets, device, queue, .. } = &mut renderer;
+    let checker = assets.load_texture("Checker.png", device, queue).unwrap();
+    let tinted = assets.materials.add(Material {
+        name: "Tinted".to_owned(),
+        base_color_factor: [1.0, 0.5, 0.25, 1.0],
+        base_color_texture: Some(checker),
+    });
+
+    renderer.scene.clear();
+    let cube = renderer.add_shape(ProceduralShape::Cube(Cube { size: [2.0, 1.0, 1.0], subdivisions: 1 }));
+    let parent = renderer.scene.add(
+        Node::new("Cube")
+            .with_transform(Transform::from_translation(Vec3::new(1.0, 2.0, 3.0)))
+            .with_mesh(cube)
+            .with_materials(vec![tinted.clone()]),
+        None,
+    );
+    let torus = renderer.add_shape(ProceduralShape::Torus(Torus::default()));
+    renderer.scene.add(Node::new("Torus").with_mesh(torus).with_materials(vec![tinted]), Some(parent));
+    renderer.scene.add(Node::new("Lamp").with_light(Light::default()), Some(parent));
+    renderer.load_model("quad.obj", None).unwrap();
+
+    renderer.settings.clear_color = [0.1, 0.2, 0.3, 1.0];
+    renderer.camera.position = Vec3::new(5.0, 1.0, 0.0);
+    renderer.camera.look_at(Vec3::ZERO);
+    controls.set_mode(CameraMode::Orbit);
+    controls.request(CameraRequest::SaveBookmark("Side".to_owned()));
+    controls.update(&mut renderer.camera, &candle::Input::default(), None, 0.0);
+
+    let saved = SceneFile::capture(&renderer, &controls);
+    assert_eq!(saved.materials.len(), 1, "materials shared between nodes are stored once");
+    assert_eq!(saved.materials[0].base_color_texture.as_deref(), Some(Path::new("Checker.png")));
+    assert_eq!(saved.nodes[1].model.as_deref(), Some(Path::new("quad.obj")));
+    assert!(saved.nodes[1].children.is_empty(), "nodes of models come from the model file");
+
+    let path = dir.join("scene.json");
+    saved.save(&path).unwrap();
+
+    let mut loaded_renderer = self::renderer(&dir);
+    loaded_renderer.resize_surface(100, 50);
+    let mut loaded_controls = CameraControls::new();
+    SceneFile::load(&path).unwrap().apply(&mut loaded_renderer, &mut loaded_controls).unwrap();
+
+    assert_eq!(node_names(&loaded_renderer), node_names(&renderer));
+    assert_eq!(SceneFile::capture(&loaded_renderer, &loaded_controls), saved);
+    assert_eq!(loaded_renderer.camera.aspect, 2.0, "the aspect follows the window, not the file");
+    assert_eq!(loaded_controls.mode(), CameraMode::Orbit);
+    assert_eq!(loaded_controls.bookmarks[0].name, "Side");
+
+    let torus = loaded_renderer.scene.find("Torus").unwrap();
+    let world = loaded_renderer.scene.get(torus).unwrap().world_matrix();
+    assert_eq!(world.transform_point3(Vec3::ZERO), Vec3::new(1.0, 2.0, 3.0), "world matrices are up to date");
+    assert!(loaded_renderer.bounds().is_some());
+    loaded_renderer.render_offscreen().unwrap();
+}
+
+#[test]
+fn missing_fields_get_defaults_and_unknown_ones_are_ignored() {
+
+    let file = SceneFile::from_json(r#"{
+        "version": 1,
+        "nodes": [{ "name": "Box", "mesh": { "Cube": { "subdivisions": 2 } }, "children": [{}] }]
+    }"#).unwrap();
+
+    assert_eq!(file.camera, None);
+    assert_eq!(file.renderer, Default::default());
+    assert_eq!(file.nodes[0].transform, Transform::IDENTITY);
+    assert_eq!(file.nodes[0].mesh, Some(ProceduralShape::Cube(Cube { subdivisions: 2, ..Default::default() })));
+    assert_eq!(file.nodes[0].children, vec![NodeDesc::default()]);
+
+    // Files from later versions load as far as they're understood
+    let newer = SceneFile::from_json(&format!(r#"{{
+        "version": {},
+        "renderer": {{ "clear_color": [0, 0, 0, 1], "exposure": 2.0 }},
+        "environment": "sky.hdr"
+    }}"#, SCENE_FILE_VERSION + 1)).unwrap();
+    assert_eq!(newer.renderer.clear_color, [0.0, 0.0, 0.0, 1.0]);
+
+    assert!(SceneFile::from_json(r#"{ "nodes": [] }"#).is_err(), "the version is required");
+}
+
+#[test]
+fn failing_to_load_leaves_the_scene_untouched() {
+
+    let dir = test_dir("untouched");
+    let mut renderer = renderer(&dir);
+    let mut controls = CameraControls::new();
+    let before = node_names(&renderer);
+
+    let missing_model = SceneFile {
+        nodes: vec![NodeDesc { model: Some("missing.obj".into()), ..Default::default() }],
+        ..Default::default()
+    };
+    assert!(missing_model.apply(&mut renderer, &mut controls).is_err());
+
+    let missing_material = SceneFile {
+        nodes: vec![NodeDesc { materials: vec![0], ..Default::default() }],
+        ..Default::default()
+    };
+    let error = missing_material.apply(&mut renderer, &mut controls).unwrap_err();
+    assert!(matches!(error, CandleError::SceneInvalid(_)), "{error}");
+
+    assert_eq!(node_names(&renderer), before);
+
+    assert!(matches!(SceneFile::load(dir.join("missing.json")), Err(CandleError::SceneRead { .. })));
+    std::fs::write(dir.join("broken.json"), "{ \"version\": ").unwrap();
+    assert!(matches!(SceneFile::load(dir.join("broken.json")), Err(CandleError::SceneParse { .. })));
+}
+
+#[test]
+fn meshes_without_a_shape_are_left_out() {
+
+    let dir = test_dir("unsaved_meshes");
+    let renderer = renderer(&dir);
+
+    // The default triangle was uploaded from its vertices
+    let file = SceneFile::capture(&renderer, &CameraControls::new());
+    assert_eq!(file.nodes.len(), 1);
+    assert_eq!(file.nodes[0].mesh, None);
+    assert!(file.nodes[0].materials.is_empty());
+}
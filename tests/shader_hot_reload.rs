@@ -0,0 +1,38 @@
+use std::time::Duration;
+
+use candle::{wgpu, AssetServer, MainRenderer};
+
+const SHADER: &str = include_str!("../src/shaders/shader.wgsl");
+
+fn wait_for_next_poll() {
+    // Longer than the watcher's check interval, and enough for the file's mtime to change
+    std::thread::sleep(Duration::from_millis(1100));
+}
+
+#[test]
+fn broken_shader_keeps_previous_pipeline_until_fixed() {
+
+    let shader_path = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("hot_reload.wgsl");
+    std::fs::write(&shader_path, SHADER).unwrap();
+
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+    let mut renderer = pollster::block_on(MainRenderer::new_headless(&instance, 64, 64, true, AssetServer::default()))
+        .expect("Failed to create a headless renderer");
+
+    renderer.enable_shader_hot_reload(&shader_path);
+    renderer.reload_changed_shaders();
+    assert_eq!(renderer.shader_error, None);
+    let working_source = renderer.cpu_resources.shader_source.clone();
+
+    wait_for_next_poll();
+    std::fs::write(&shader_path, SHADER.replace("return textureSample", "return textureSampl")).unwrap();
+    renderer.reload_changed_shaders();
+    assert!(renderer.shader_error.is_some());
+    assert_eq!(renderer.cpu_resources.shader_source, working_source);
+    renderer.render_offscreen().expect("The previous pipeline should still render");
+
+    wait_for_next_poll();
+    std::fs::write(&shader_path, SHADER).unwrap();
+    renderer.reload_changed_shaders();
+    assert_eq!(renderer.shader_error, None);
+}
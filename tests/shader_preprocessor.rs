@@ -0,0 +1,81 @@
+use std::path::{Path, PathBuf};
+
+use candle::app::main_renderer::shader_preprocessor::ShaderPreprocessor;
+use candle::CandleError;
+
+fn shader_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("shader_preprocessor").join(name);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    for (file, source) in files {
+        std::fs::write(dir.join(file), source).unwrap();
+    }
+
+    dir
+}
+
+#[test]
+fn builtin_shader_preprocesses_and_validates() {
+
+    let shader = ShaderPreprocessor::builtin().process("shader.wgsl").unwrap();
+    shader.validate().unwrap();
+}
+
+#[test]
+fn includes_are_expanded_once() {
+
+    let dir = shader_dir("includes", &[
+        ("common.wgsl", "struct Light {\n    color: vec3<f32>,\n}\n"),
+        ("lighting.wgsl", "#include \"common.wgsl\"\nfn intensity(light: Light) -> f32 { return length(light.color); }\n"),
+        ("main.wgsl", "#include \"common.wgsl\"\n#include \"lighting.wgsl\"\n@fragment\nfn main() -> @location(0) vec4<f32> { return vec4<f32>(1.0); }\n"),
+    ]);
+
+    let shader = ShaderPreprocessor::new(&dir).process("main.wgsl").unwrap();
+
+    assert_eq!(shader.source.matches("struct Light").count(), 1);
+    assert_eq!(shader.files.len(), 3);
+    shader.validate().unwrap();
+
+    let location = shader.locate(4).unwrap();
+    assert_eq!(location.file, dir.join("lighting.wgsl"));
+    assert_eq!(location.line, 2);
+}
+
+#[test]
+fn defines_toggle_and_substitute_code() {
+
+    let dir = shader_dir("defines", &[
+        ("main.wgsl", "#define SCALE 2.0\n#ifdef SHADOWS\nconst shadows = true;\n#else\nconst shadows = false;\n#endif\n#ifndef SHADOWS\nconst scale = SCALE;\n#endif\n"),
+    ]);
+
+    let without_shadows = ShaderPreprocessor::new(&dir).process("main.wgsl").unwrap();
+    assert_eq!(without_shadows.source, "const shadows = false;\nconst scale = 2.0;\n");
+
+    let with_shadows = ShaderPreprocessor::new(&dir).define("SHADOWS", "").process("main.wgsl").unwrap();
+    assert_eq!(with_shadows.source, "const shadows = true;\n");
+}
+
+#[test]
+fn errors_point_at_the_original_file_and_line() {
+
+    let dir = shader_dir("errors", &[
+        ("broken.wgsl", "fn helper() -> f32 {\n    return undefined_value;\n}\n"),
+        ("main.wgsl", "// Entry point\n#include \"broken.wgsl\"\n"),
+    ]);
+
+    let shader = ShaderPreprocessor::new(&dir).process("main.wgsl").unwrap();
+    let message = shader.validate().unwrap_err().to_string();
+
+    let expected_location = format!("{}:2:", dir.join("broken.wgsl").display());
+    assert!(message.contains(&expected_location), "{message}");
+}
+
+#[test]
+fn unbalanced_conditionals_are_rejected() {
+
+    let dir = shader_dir("unbalanced", &[("main.wgsl", "#ifdef A\nconst a = 1;\n")]);
+
+    let error = ShaderPreprocessor::new(&dir).process("main.wgsl").err().unwrap();
+    assert!(matches!(error, CandleError::ShaderPreprocess { .. }), "{error}");
+}
@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+
+use candle::app::main_renderer::model::PrimitiveData;
+use candle::app::main_renderer::shapes::{Capsule, Cone, Cube, Cylinder, Icosphere, Plane, Shape, Torus, UvSphere};
+use candle::glam::{Vec3, Vec4, Vec4Swizzles};
+
+fn triangles(primitive: &PrimitiveData) -> impl Iterator<Item = [usize; 3]> + '_ {
+    primitive.indices.chunks_exact(3).map(|triangle| [0, 1, 2].map(|corner| triangle[corner] as usize))
+}
+
+/// Every attribute of `Vertex` is there, normals and tangents are unit length and perpendicular.
+fn assert_complete(primitive: &PrimitiveData) {
+
+    let vertex_count = primitive.positions.len();
+    assert!(vertex_count > 0 && primitive.indices.len().is_multiple_of(3));
+    assert_eq!(primitive.normals.len(), vertex_count);
+    assert_eq!(primitive.uvs.len(), vertex_count);
+    assert_eq!(primitive.tangents.len(), vertex_count);
+    assert!(primitive.indices.iter().all(|&index| (index as usize) < vertex_count));
+
+    for (normal, tangent) in primitive.normals.iter().zip(&primitive.tangents) {
+        let (normal, tangent) = (Vec3::from(*normal), Vec4::from(*tangent));
+        assert!(normal.is_normalized(), "normal {normal} isn't normalized");
+        assert!((tangent.xyz().length() - 1.0).abs() < 1e-3, "tangent {tangent} isn't normalized");
+        assert!(normal.dot(tangent.xyz()).abs() < 1e-3, "tangent {tangent} isn't perpendicular to {normal}");
+        assert!(tangent.w.abs() == 1.0);
+    }
+}
+
+fn assert_uvs_inside_texture(primitive: &PrimitiveData) {
+
+    for uv in &primitive.uvs {
+        assert!(uv.iter().all(|value| (0.0..=1.0).contains(value)), "UV {uv:?} is outside the texture");
+    }
+}
+
+/// Once vertices at the same position are welded, every edge has to be shared by exactly two
+/// triangles that run along it in opposite directions.
+fn assert_watertight(primitive: &PrimitiveData) {
+
+    let mut welded: HashMap<[i64; 3], usize> = HashMap::new();
+    let ids: Vec<usize> = primitive
+        .positions
+        .iter()
+        .map(|position| {
+            let key = position.map(|value| (value * 1e4).round() as i64);
+            let next_id = welded.len();
+            *welded.entry(key).or_insert(next_id)
+        })
+        .collect();
+
+    let mut edges: HashMap<(usize, usize), u32> = HashMap::new();
+    for triangle in triangles(primitive) {
+
+        let [a, b, c] = triangle.map(|index| ids[index]);
+        assert!(a != b && b != c && c != a, "degenerate triangle {triangle:?}");
+
+        for edge in [(a, b), (b, c), (c, a)] {
+            *edges.entry(edge).or_default() += 1;
+        }
+    }
+
+    for (&(from, to), &count) in &edges {
+        assert_eq!(count, 1, "edge {from} -> {to} is used by {count} triangles");
+        assert_eq!(edges.get(&(to, from)), Some(&1), "edge {from} -> {to} has no opposite, the mesh has a hole");
+    }
+}
+
+/// Triangles have to be wound counter-clockwise seen from outside, i.e. facing away from the
+/// point `inside` returns for them, and the vertex normals have to agree.
+fn assert_facing_outwards(primitive: &PrimitiveData, inside: impl Fn(Vec3) -> Vec3) {
+
+    for triangle in triangles(primitive) {
+
+        let [a, b, c] = triangle.map(|index| Vec3::from(primitive.positions[index]));
+        let face_normal = (b - a).cross(c - a);
+        let center = (a + b + c) / 3.0;
+
+        assert!(face_normal.dot(center - inside(center)) > 0.0, "triangle {triangle:?} faces inwards");
+
+        for index in triangle {
+            assert!(
+                face_normal.dot(Vec3::from(primitive.normals[index])) > 0.0,
+                "normal of vertex {index} points away from its triangle {triangle:?}"
+            );
+        }
+    }
+}
+
+fn origin(_: Vec3) -> Vec3 {
+    Vec3::ZERO
+}
+
+#[test]
+fn plane_faces_up_and_is_subdivided() {
+
+    let plane = Plane { size: [2.0, 4.0], subdivisions: 3 }.primitive();
+    assert_complete(&plane);
+    assert_uvs_inside_texture(&plane);
+
+    assert_eq!(plane.positions.len(), 5 * 5);
+    assert_eq!(plane.indices.len(), 4 * 4 * 2 * 3);
+    assert!(plane.positions.iter().all(|position| position[1] == 0.0 && position[0].abs() <= 1.0 && position[2].abs() <= 2.0));
+    assert_facing_outwards(&plane, |center| center - Vec3::Y);
+}
+
+#[test]
+fn cube_is_closed_and_faces_outwards() {
+
+    for subdivisions in [0, 2] {
+        let cube = Cube { size: [1.0, 2.0, 3.0], subdivisions }.primitive();
+        assert_complete(&cube);
+        assert_uvs_inside_texture(&cube);
+        assert_watertight(&cube);
+        assert_facing_outwards(&cube, origin);
+        assert_eq!(cube.indices.len() as u32, 6 * (subdivisions + 1).pow(2) * 2 * 3);
+    }
+}
+
+#[test]
+fn spheres_are_closed_and_round() {
+
+    let uv_sphere = UvSphere { radius: 2.0, sectors: 12, stacks: 7 }.primitive();
+    assert_uvs_inside_texture(&uv_sphere);
+
+    let icospheres = (1..=3).map(|subdivisions| (subdivisions, Icosphere { radius: 2.0, subdivisions }.primitive()));
+
+    for sphere in std::iter::once(uv_sphere).chain(icospheres.clone().map(|(_, sphere)| sphere)) {
+        assert_complete(&sphere);
+        assert_watertight(&sphere);
+        assert_facing_outwards(&sphere, origin);
+        assert!(sphere.positions.iter().all(|&position| (Vec3::from(position).length() - 2.0).abs() < 1e-5));
+    }
+
+    for (subdivisions, icosphere) in icospheres {
+
+        assert_eq!(icosphere.indices.len(), 20 * 4_usize.pow(subdivisions) * 3);
+
+        // No triangle may be stretched across the whole texture because of the seam
+        for triangle in triangles(&icosphere) {
+            let us = triangle.map(|index| icosphere.uvs[index][0]);
+            let span = us.iter().fold(f32::MIN, |max, &u| max.max(u)) - us.iter().fold(f32::MAX, |min, &u| min.min(u));
+            assert!(span < 0.5, "triangle {triangle:?} spans {us:?}");
+        }
+    }
+}
+
+#[test]
+fn uv_sphere_leaves_out_triangles_collapsed_at_the_poles() {
+
+    let sphere = UvSphere { radius: 1.0, sectors: 8, stacks: 4 }.primitive();
+    assert_eq!(sphere.indices.len(), (8 * 2 * 2 + 8 * 2) * 3);
+}
+
+#[test]
+fn cylinder_cone_and_capsule_are_closed() {
+
+    let cylinder = Cylinder { radius: 0.5, height: 2.0, sectors: 10, stacks: 3 }.primitive();
+    let cone = Cone { radius: 1.0, height: 0.5, sectors: 10, stacks: 2 }.primitive();
+
+    for shape in [&cylinder, &cone] {
+        assert_complete(shape);
+        assert_uvs_inside_texture(shape);
+        assert_watertight(shape);
+        assert_facing_outwards(shape, origin);
+    }
+
+    let capsule = Capsule { radius: 0.5, length: 1.0, sectors: 10, rings: 4 }.primitive();
+    assert_complete(&capsule);
+    assert_uvs_inside_texture(&capsule);
+    assert_watertight(&capsule);
+    assert_facing_outwards(&capsule, |center| Vec3::Y * center.y.clamp(-0.5, 0.5));
+
+    let height = |primitive: &PrimitiveData| primitive.positions.iter().map(|position| position[1]).fold(f32::MIN, f32::max);
+    assert!((height(&capsule) - 1.0).abs() < 1e-5);
+    assert!((height(&cone) - 0.25).abs() < 1e-5);
+}
+
+#[test]
+fn torus_is_closed_and_faces_away_from_its_ring() {
+
+    let torus = Torus { major_radius: 1.0, minor_radius: 0.25, major_segments: 16, minor_segments: 8 }.primitive();
+    assert_complete(&torus);
+    assert_uvs_inside_texture(&torus);
+    assert_watertight(&torus);
+
+    assert_facing_outwards(&torus, |center| Vec3::new(center.x, 0.0, center.z).normalize());
+}
+
+#[test]
+fn meshes_are_named_after_their_shape() {
+
+    let mesh = Torus::default().mesh();
+    assert_eq!(mesh.name, "Torus");
+    assert_eq!(mesh.primitives.len(), 1);
+    assert_eq!(mesh.primitives[0].vertices().len(), mesh.primitives[0].positions.len());
+}
@@ -0,0 +1,176 @@
+use candle::app::main_renderer::model::PrimitiveData;
+use candle::glam::Vec3;
+
+fn assert_close(actual: [f32; 4], expected: [f32; 4]) {
+    assert!(
+        actual.iter().zip(expected).all(|(a, e)| (a - e).abs() < 1e-5),
+        "expected {expected:?}, got {actual:?}"
+    );
+}
+
+/// Unit quad in the XY plane facing +Z, with UVs in wgpu's top left origin convention.
+fn quad() -> PrimitiveData {
+    PrimitiveData {
+        positions: vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]],
+        normals: vec![[0.0, 0.0, 1.0]; 4],
+        uvs: vec![[0.0, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0]],
+        indices: vec![0, 1, 2, 0, 2, 3],
+        ..Default::default()
+    }
+}
+
+/// Unit cube with its own normals and UVs for each face, like exported from Blender.
+fn cube() -> PrimitiveData {
+
+    let faces: [([f32; 3], [f32; 3], [f32; 3]); 6] = [
+        ([0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+        ([0.0, 0.0, -1.0], [-1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+        ([1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0]),
+        ([-1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]),
+        ([0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, -1.0]),
+        ([0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+    ];
+
+    let mut cube = PrimitiveData::default();
+
+    for (normal, right, up) in faces {
+        let first = cube.positions.len() as u32;
+        for (u, v) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+            let position: [f32; 3] = std::array::from_fn(|axis| 0.5 * (normal[axis] + u * right[axis] + v * up[axis]));
+            cube.positions.push(position);
+            cube.normals.push(normal);
+            cube.uvs.push([(u + 1.0) / 2.0, (1.0 - v) / 2.0]);
+        }
+        cube.indices.extend([first, first + 1, first + 2, first, first + 2, first + 3]);
+    }
+
+    cube
+}
+
+/// Tangents have to be unit length, perpendicular to the normal and point along increasing U,
+/// and the bitangent `w * cross(normal, tangent)` along increasing V.
+fn assert_tangent_frames_follow_uvs(primitive: &PrimitiveData) {
+
+    assert_eq!(primitive.tangents.len(), primitive.positions.len());
+
+    for triangle in primitive.indices.chunks_exact(3) {
+
+        let [a, b, c] = [triangle[0], triangle[1], triangle[2]].map(|index| index as usize);
+
+        let [position_a, position_b, position_c] = [a, b, c].map(|index| Vec3::from(primitive.positions[index]));
+        let (edge_1, edge_2) = (position_b - position_a, position_c - position_a);
+        let (du_1, dv_1) = (primitive.uvs[b][0] - primitive.uvs[a][0], primitive.uvs[b][1] - primitive.uvs[a][1]);
+        let (du_2, dv_2) = (primitive.uvs[c][0] - primitive.uvs[a][0], primitive.uvs[c][1] - primitive.uvs[a][1]);
+
+        let determinant = du_1 * dv_2 - du_2 * dv_1;
+        let along_u = (edge_1 * dv_2 - edge_2 * dv_1) / determinant;
+        let along_v = (edge_2 * du_1 - edge_1 * du_2) / determinant;
+
+        for index in [a, b, c] {
+
+            let [x, y, z, sign] = primitive.tangents[index];
+            let tangent = Vec3::new(x, y, z);
+            let normal = Vec3::from(primitive.normals[index]);
+            let bitangent = normal.cross(tangent) * sign;
+
+            assert!((tangent.length() - 1.0).abs() < 1e-4, "tangent {tangent} isn't normalized");
+            assert!(tangent.dot(normal).abs() < 1e-4, "tangent {tangent} isn't perpendicular to {normal}");
+            assert!(sign == 1.0 || sign == -1.0);
+            assert!(tangent.dot(along_u) > 0.0, "tangent {tangent} doesn't follow U {along_u}");
+            assert!(bitangent.dot(along_v) > 0.0, "bitangent {bitangent} doesn't follow V {along_v}");
+        }
+    }
+}
+
+#[test]
+fn quad_tangents_follow_u_and_flip_the_bitangent_for_top_left_uvs() {
+
+    let mut quad = quad();
+    assert!(quad.generate_tangents());
+
+    // V grows downwards, opposite to cross(normal, tangent) = +Y
+    for tangent in &quad.tangents {
+        assert_close(*tangent, [1.0, 0.0, 0.0, -1.0]);
+    }
+    assert_tangent_frames_follow_uvs(&quad);
+}
+
+#[test]
+fn mirrored_uvs_flip_the_tangent() {
+
+    let mut quad = quad();
+    for uv in &mut quad.uvs {
+        uv[0] = 1.0 - uv[0];
+    }
+
+    assert!(quad.generate_tangents());
+
+    for tangent in &quad.tangents {
+        assert_close(*tangent, [-1.0, 0.0, 0.0, 1.0]);
+    }
+    assert_tangent_frames_follow_uvs(&quad);
+}
+
+#[test]
+fn every_cube_face_gets_its_own_frame() {
+
+    let mut cube = cube();
+    assert!(cube.generate_tangents());
+
+    assert_tangent_frames_follow_uvs(&cube);
+
+    // The +X face's U runs along -Z
+    assert_close(cube.tangents[8], [0.0, 0.0, -1.0, -1.0]);
+}
+
+#[test]
+fn meshes_without_uvs_or_normals_are_left_alone() {
+
+    let mut quad = quad();
+    quad.uvs.clear();
+    assert!(!quad.generate_tangents());
+    assert!(quad.tangents.is_empty());
+
+    let mut quad = self::quad();
+    quad.normals.clear();
+    assert!(!quad.generate_tangents());
+    assert!(quad.tangents.is_empty());
+}
+
+#[test]
+fn meshes_with_out_of_range_indices_are_left_alone() {
+
+    let mut quad = quad();
+    quad.indices[5] = 4;
+    assert!(!quad.generate_tangents());
+    assert!(quad.tangents.is_empty());
+}
+
+#[test]
+fn mirrored_uv_seams_split_the_shared_vertices() {
+
+    // Two quads side by side sharing their middle edge, the right one mirrored in U so both
+    // sides of the seam have the same UVs and the corners on it are welded
+    let mut mirrored = PrimitiveData {
+        positions: vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0], [2.0, 0.0, 0.0], [2.0, 1.0, 0.0]],
+        normals: vec![[0.0, 0.0, 1.0]; 6],
+        uvs: vec![[0.0, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0], [0.0, 1.0], [0.0, 0.0]],
+        colors: vec![[1.0, 0.0, 0.0, 1.0]; 6],
+        indices: vec![0, 1, 2, 0, 2, 3, 1, 4, 5, 1, 5, 2],
+        ..Default::default()
+    };
+    assert!(mirrored.generate_tangents());
+
+    assert_eq!(mirrored.positions.len(), 8, "both seam vertices get a copy for the other side");
+    assert_eq!(mirrored.colors.len(), 8);
+    assert_eq!(mirrored.uvs_1.len(), 0, "missing attributes stay missing");
+    assert_tangent_frames_follow_uvs(&mirrored);
+
+    assert_close(mirrored.tangents[0], [1.0, 0.0, 0.0, -1.0]);
+    assert_close(mirrored.tangents[4], [-1.0, 0.0, 0.0, 1.0]);
+
+    // Without a seam nothing is split
+    let mut quad = quad();
+    quad.generate_tangents();
+    assert_eq!(quad.positions.len(), 4);
+}
@@ -0,0 +1,80 @@
+use candle::app::main_renderer::model::PrimitiveData;
+use candle::app::main_renderer::vertex::{VertexAttributeKind::*, VertexStreams};
+use candle::{wgpu, Vertex};
+
+#[test]
+fn standard_vertex_layout_matches_the_struct() {
+
+    let layout = Vertex::layout();
+
+    assert_eq!(layout.array_stride, std::mem::size_of::<Vertex>() as u64);
+
+    let offsets: Vec<u64> = layout.attributes.iter().map(|attribute| attribute.offset).collect();
+    assert_eq!(offsets, vec![
+        std::mem::offset_of!(Vertex, position) as u64,
+        std::mem::offset_of!(Vertex, uv) as u64,
+        std::mem::offset_of!(Vertex, normal) as u64,
+        std::mem::offset_of!(Vertex, tangent) as u64,
+    ]);
+
+    let primitive = PrimitiveData {
+        positions: vec![[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]],
+        uvs: vec![[0.5, 0.25], [0.75, 1.0]],
+        ..Default::default()
+    };
+    assert_eq!(
+        primitive.vertex_bytes(&Vertex::ATTRIBUTES),
+        bytemuck::cast_slice::<Vertex, u8>(&primitive.vertices()),
+        "packing by attributes and the Vertex struct agree, defaults included"
+    );
+}
+
+#[test]
+fn deinterleaved_streams_get_one_buffer_per_attribute() {
+
+    let streams = VertexStreams::new().deinterleaved(&[Position, Normal]).interleaved(&[Color, Joints, Weights]);
+    let layouts = streams.layouts();
+
+    assert_eq!(layouts.len(), 3);
+    assert_eq!(layouts[0].array_stride, 12);
+    assert_eq!(layouts[1].attributes[0].offset, 0);
+    assert_eq!(layouts[1].attributes[0].shader_location, Normal.shader_location());
+
+    assert_eq!(layouts[2].array_stride, 16 + 8 + 16);
+    let locations: Vec<u32> = layouts[2].attributes.iter().map(|attribute| attribute.shader_location).collect();
+    assert_eq!(locations, vec![4, 6, 7]);
+    assert_eq!(layouts[2].attributes[1].format, wgpu::VertexFormat::Uint16x4);
+
+    let primitive = PrimitiveData {
+        positions: vec![[0.0; 3]; 2],
+        joints: vec![[1, 2, 3, 4], [5, 6, 7, 8]],
+        ..Default::default()
+    };
+    let bytes = primitive.vertex_bytes(&streams.streams()[2]);
+    assert_eq!(bytes.len(), 2 * layouts[2].array_stride as usize);
+    assert_eq!(&bytes[0..4], &1.0f32.to_le_bytes(), "colors default to white");
+    assert_eq!(&bytes[16..18], &1u16.to_le_bytes());
+    assert_eq!(&bytes[24..28], &1.0f32.to_le_bytes(), "the first weight defaults to 1");
+}
+
+#[test]
+fn generated_wgsl_input_compiles() {
+
+    let streams = VertexStreams::new().interleaved(&[Uv1, Position]).deinterleaved(&[Joints, Tangent]);
+    let input = streams.wgsl_input("VertexInput");
+
+    assert!(input.contains("@location(0) position: vec3<f32>"), "{input}");
+    assert!(input.find("position").unwrap() < input.find("uv_1").unwrap(), "fields are sorted by location");
+
+    let shader = format!("{input}
+@vertex
+fn vertex(in: VertexInput) -> @builtin(position) vec4<f32> {{
+    return vec4<f32>(in.position + in.tangent.xyz * f32(in.joints.x), in.uv_1.x);
+}}
+");
+
+    let module = wgpu::naga::front::wgsl::parse_str(&shader).expect("Generated WGSL doesn't parse");
+    wgpu::naga::valid::Validator::new(wgpu::naga::valid::ValidationFlags::all(), wgpu::naga::valid::Capabilities::all())
+        .validate(&module)
+        .expect("Generated WGSL doesn't validate");
+}